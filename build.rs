@@ -3,18 +3,27 @@
 // Opaque wrappers: https://anssi-fr.github.io/rust-guide/07_ffi.html#memory-and-resource-management
 
 use std::env;
+use std::path::PathBuf;
 
 fn main() {
-    let rpath = "/Users/ian/Projects/symphony-lang/extern/symphony-runtime/target/debug";
+    // The profile directory the library is built into (`target/<profile>`), three levels above `OUT_DIR`, unless
+    // overridden for installed copies.
+    println!("cargo:rerun-if-env-changed=SYMPHONY_RPATH");
+    let rpath = env::var("SYMPHONY_RPATH").unwrap_or_else(|_| {
+        let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
+        out_dir.ancestors().nth(3).unwrap().display().to_string()
+    });
     println!("cargo:rustc-cdylib-link-arg=-Wl,-rpath,{}", rpath);
 
-    let mut motion_lib_path = env::current_dir().unwrap();
-    motion_lib_path.push("extern/MOTION/build/lib");
-    let motion_lib_path_str = motion_lib_path.into_os_string().into_string().unwrap();
-    println!("cargo:rustc-link-search=native={}", motion_lib_path_str);
-    println!("cargo:rustc-link-lib=motionffi");
-    println!(
-        "cargo:rustc-cdylib-link-arg=-Wl,-rpath,{}",
-        motion_lib_path_str
-    );
+    if env::var_os("CARGO_FEATURE_MOTION").is_some() {
+        let mut motion_lib_path = env::current_dir().unwrap();
+        motion_lib_path.push("extern/MOTION/build/lib");
+        let motion_lib_path_str = motion_lib_path.into_os_string().into_string().unwrap();
+        println!("cargo:rustc-link-search=native={}", motion_lib_path_str);
+        println!("cargo:rustc-link-lib=motionffi");
+        println!(
+            "cargo:rustc-cdylib-link-arg=-Wl,-rpath,{}",
+            motion_lib_path_str
+        );
+    }
 }
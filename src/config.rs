@@ -0,0 +1,438 @@
+//! Cluster configuration files, so that deployment tooling can describe every party's setup in one file rather
+//! than passing addresses, certificates and timeouts through the FFI piece by piece.
+//!
+//! A file is TOML or JSON, chosen by its extension:
+//!
+//! ```toml
+//! backend = "native"   # or "motion"; must match the backend this library was built with
+//! seed = "random"      # or { fixed = 42 } for reproducible runs, which makes every share predictable
+//!
+//! [[parties]]
+//! id = 0
+//! host = "10.0.0.1"
+//! port = 23000
+//! cert = "party0.pem"  # only with [tls]
+//! key = "party0.key"
+//!
+//! [[parties]]
+//! id = 1
+//! host = "10.0.0.2"
+//! port = 23000
+//! cert = "party1.pem"
+//! key = "party1.key"
+//!
+//! [tls]
+//! ca = "ca.pem"
+//!
+//! [timeouts]           # as in `ConnectOptions`, with zero meaning no limit
+//! connect_ms = 5000
+//! max_attempts = 20
+//! ```
+//!
+//! Since one file usually describes the whole cluster, each party names itself through the `SYMPHONY_PARTY_ID`
+//! environment variable, unless the file sets a top-level `id`. Relative paths are resolved against the file's
+//! directory.
+//!
+//! Only the native backend secures, times and seeds its own channels; MOTION rejects a file that sets `[tls]`,
+//! `[timeouts]` or a fixed `seed`.
+
+use crate::error::{Error, Result};
+use crate::util::{ConnectOptions, TlsIdentity};
+use rand::SeedableRng;
+use scuttlebutt::{AesRng, Block};
+use serde::Deserialize;
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+
+/// The environment variable naming this party when its configuration file does not.
+pub const PARTY_ID_VAR: &str = "SYMPHONY_PARTY_ID";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BackendKind {
+    Motion,
+    Native,
+}
+
+impl BackendKind {
+    /// The backend this library was built to use for GMW.
+    pub fn compiled() -> Self {
+        if cfg!(feature = "motion") {
+            BackendKind::Motion
+        } else {
+            BackendKind::Native
+        }
+    }
+}
+
+/// How each party seeds the generator behind its shares and preprocessing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Seed {
+    /// Fresh entropy from the operating system.
+    #[default]
+    Random,
+    /// A seed derived from the given value and the party's id, so that runs can be reproduced. Anyone who knows it
+    /// can recover every share, so it is only for testing.
+    Fixed(u64),
+}
+
+impl Seed {
+    pub fn rng(&self, my_id: usize) -> AesRng {
+        match self {
+            Seed::Random => AesRng::new(),
+            Seed::Fixed(seed) => {
+                AesRng::from_seed(Block::from(((my_id as u128) << 64) | *seed as u128))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Timeouts {
+    pub connect_ms: u64,
+    pub initial_backoff_ms: u64,
+    pub max_backoff_ms: u64,
+    pub max_attempts: u32,
+    pub read_ms: u64,
+    pub write_ms: u64,
+}
+
+impl Default for Timeouts {
+    fn default() -> Self {
+        let options = ConnectOptions::default();
+        Self {
+            connect_ms: options.connect_timeout_ms,
+            initial_backoff_ms: options.initial_backoff_ms,
+            max_backoff_ms: options.max_backoff_ms,
+            max_attempts: options.max_attempts,
+            read_ms: options.read_timeout_ms,
+            write_ms: options.write_timeout_ms,
+        }
+    }
+}
+
+impl Timeouts {
+    pub fn options(&self) -> ConnectOptions {
+        ConnectOptions {
+            connect_timeout_ms: self.connect_ms,
+            initial_backoff_ms: self.initial_backoff_ms,
+            max_backoff_ms: self.max_backoff_ms,
+            max_attempts: self.max_attempts,
+            read_timeout_ms: self.read_ms,
+            write_timeout_ms: self.write_ms,
+            cancel: std::ptr::null(),
+        }
+    }
+}
+
+/// A validated configuration, from the point of view of one party.
+#[derive(Clone)]
+pub struct Config {
+    pub id: usize,
+    /// The address of every party, indexed by id.
+    pub hosts: Vec<String>,
+    pub ports: Vec<u16>,
+    pub backend: BackendKind,
+    /// This party's certificate and key, and the authority that issued every party's, if channels use TLS.
+    pub tls: Option<TlsIdentity>,
+    pub timeouts: Timeouts,
+    pub seed: Seed,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct File {
+    id: Option<usize>,
+    parties: Vec<PartyEntry>,
+    backend: Option<BackendKind>,
+    tls: Option<TlsEntry>,
+    #[serde(default)]
+    timeouts: Timeouts,
+    #[serde(default)]
+    seed: Seed,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PartyEntry {
+    id: usize,
+    host: String,
+    port: u16,
+    cert: Option<PathBuf>,
+    key: Option<PathBuf>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TlsEntry {
+    ca: PathBuf,
+}
+
+fn malformed(what: impl Display) -> Error {
+    Error::Malformed(what.to_string())
+}
+
+impl Config {
+    /// Reads and validates the configuration at `path`, loading this party's TLS material if any.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let located = |e: Error| match e {
+            Error::Malformed(what) => Error::Malformed(format!("{}: {}", path.display(), what)),
+            e => e,
+        };
+        let text = std::fs::read_to_string(path).map_err(|e| located(malformed(e)))?;
+        let file = match path.extension().and_then(|e| e.to_str()) {
+            Some("toml") => toml::from_str(&text).map_err(malformed),
+            Some("json") => serde_json::from_str(&text).map_err(malformed),
+            _ => Err(malformed("expected a .toml or .json file")),
+        }
+        .map_err(located)?;
+        let dir = path.parent().unwrap_or_else(|| Path::new(""));
+        Self::resolve(file, dir).map_err(located)
+    }
+
+    fn resolve(mut file: File, dir: &Path) -> Result<Self> {
+        let id = match file.id {
+            Some(id) => id,
+            None => {
+                let var = std::env::var(PARTY_ID_VAR).map_err(|_| {
+                    malformed(format!(
+                        "no party id: set `id` in the file or {} in the environment",
+                        PARTY_ID_VAR
+                    ))
+                })?;
+                var.trim().parse().map_err(|_| {
+                    malformed(format!("{} is {:?}, not a party id", PARTY_ID_VAR, var))
+                })?
+            }
+        };
+
+        let parties = &mut file.parties;
+        if parties.len() < 2 {
+            return Err(malformed(format!(
+                "a cluster needs at least two parties, but {} listed",
+                if parties.len() == 1 {
+                    "only one is"
+                } else {
+                    "none are"
+                }
+            )));
+        }
+        parties.sort_by_key(|p| p.id);
+        for (i, party) in parties.iter().enumerate() {
+            if i > 0 && parties[i - 1].id == party.id {
+                return Err(malformed(format!("party {} is listed twice", party.id)));
+            }
+            if party.id != i {
+                return Err(malformed(format!(
+                    "party ids must run from 0 to {}, but party {} is listed",
+                    parties.len() - 1,
+                    party.id
+                )));
+            }
+            if let Some(other) = parties[..i]
+                .iter()
+                .find(|p| p.host == party.host && p.port == party.port)
+            {
+                return Err(malformed(format!(
+                    "parties {} and {} both listen on {}:{}",
+                    other.id, party.id, party.host, party.port
+                )));
+            }
+            if file.tls.is_none() && (party.cert.is_some() || party.key.is_some()) {
+                return Err(malformed(format!(
+                    "party {} has a certificate or key, but there is no [tls] section",
+                    party.id
+                )));
+            }
+        }
+        if id >= parties.len() {
+            return Err(malformed(format!(
+                "party {} is not among the {} listed parties",
+                id,
+                parties.len()
+            )));
+        }
+
+        let backend = file.backend.unwrap_or_else(BackendKind::compiled);
+        if backend != BackendKind::compiled() {
+            return Err(Error::Unsupported(match backend {
+                BackendKind::Motion => "this library was built without the `motion` feature",
+                BackendKind::Native => {
+                    "this library was built to use MOTION; rebuild without the `motion` feature"
+                }
+            }));
+        }
+
+        let timeouts = file.timeouts;
+        if timeouts.initial_backoff_ms > timeouts.max_backoff_ms {
+            return Err(malformed(
+                "timeouts.initial_backoff_ms is larger than timeouts.max_backoff_ms",
+            ));
+        }
+
+        let tls = match &file.tls {
+            Some(tls) => {
+                let me = &parties[id];
+                let required = |path: &Option<PathBuf>, what| {
+                    path.as_ref().map(|p| dir.join(p)).ok_or_else(|| {
+                        malformed(format!("[tls] is set, but party {} has no `{}`", id, what))
+                    })
+                };
+                let cert = required(&me.cert, "cert")?;
+                let key = required(&me.key, "key")?;
+                Some(TlsIdentity::load(cert, key, dir.join(&tls.ca))?)
+            }
+            None => None,
+        };
+
+        Ok(Self {
+            id,
+            hosts: parties.iter().map(|p| p.host.clone()).collect(),
+            ports: parties.iter().map(|p| p.port).collect(),
+            backend,
+            tls,
+            timeouts,
+            seed: file.seed,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Writes `text` to a temporary file with the given extension and loads it.
+    fn load(name: &str, extension: &str, text: &str) -> Result<Config> {
+        let path = std::env::temp_dir().join(format!(
+            "symphony-config-{}-{}.{}",
+            std::process::id(),
+            name,
+            extension
+        ));
+        std::fs::write(&path, text).unwrap();
+        let config = Config::load(&path);
+        std::fs::remove_file(&path).unwrap();
+        config
+    }
+
+    fn message(result: Result<Config>) -> String {
+        match result {
+            Err(Error::Malformed(what)) => what,
+            Err(e) => panic!("unexpected error: {}", e),
+            Ok(_) => panic!("configuration was accepted"),
+        }
+    }
+
+    #[test]
+    fn loads_toml_and_json() {
+        let toml = load(
+            "toml",
+            "toml",
+            r#"
+            id = 1
+            seed = { fixed = 7 }
+
+            [[parties]]
+            id = 1
+            host = "b"
+            port = 2
+
+            [[parties]]
+            id = 0
+            host = "a"
+            port = 1
+
+            [timeouts]
+            max_attempts = 3
+            "#,
+        )
+        .unwrap();
+        assert_eq!(toml.id, 1);
+        assert_eq!(toml.hosts, ["a", "b"]);
+        assert_eq!(toml.ports, [1, 2]);
+        assert_eq!(toml.seed, Seed::Fixed(7));
+        assert_eq!(toml.timeouts.options().max_attempts, 3);
+        assert!(toml.tls.is_none());
+
+        let json = load(
+            "json",
+            "json",
+            r#"{ "id": 0, "parties": [
+                { "id": 0, "host": "a", "port": 1 },
+                { "id": 1, "host": "b", "port": 2 }
+            ] }"#,
+        )
+        .unwrap();
+        assert_eq!(json.hosts, toml.hosts);
+        assert_eq!(json.seed, Seed::Random);
+        assert_eq!(json.backend, BackendKind::compiled());
+    }
+
+    fn party(id: usize, host: &str, port: u16) -> String {
+        format!(
+            "[[parties]]\nid = {}\nhost = \"{}\"\nport = {}\n",
+            id, host, port
+        )
+    }
+
+    #[test]
+    fn invalid_files_are_explained() {
+        let gap = format!("id = 0\n{}{}", party(0, "a", 1), party(2, "b", 1));
+        assert!(message(load("gap", "toml", &gap)).contains("but party 2 is listed"));
+
+        let twice = format!("id = 0\n{}{}", party(0, "a", 1), party(0, "b", 1));
+        assert!(message(load("twice", "toml", &twice)).contains("party 0 is listed twice"));
+
+        let shared = format!("id = 0\n{}{}", party(0, "a", 1), party(1, "a", 1));
+        assert!(message(load("shared", "toml", &shared)).contains("both listen on a:1"));
+
+        let unknown = format!("id = 0\nport = 3\n{}{}", party(0, "a", 1), party(1, "b", 1));
+        assert!(message(load("unknown", "toml", &unknown)).contains("unknown field"));
+
+        let outside = format!("id = 2\n{}{}", party(0, "a", 1), party(1, "b", 1));
+        assert!(message(load("outside", "toml", &outside)).contains("not among the 2 listed"));
+
+        let tls = format!(
+            "id = 0\n[tls]\nca = \"ca.pem\"\n{}{}",
+            party(0, "a", 1),
+            party(1, "b", 1)
+        );
+        assert!(message(load("tls", "toml", &tls)).contains("party 0 has no `cert`"));
+
+        assert!(message(load("extension", "yaml", "")).contains("expected a .toml or .json file"));
+    }
+
+    #[test]
+    fn default_backends_reject_what_they_ignore() {
+        use crate::gmw::{Backend, Plain};
+
+        let parties = format!("id = 0\n{}{}", party(0, "a", 1), party(1, "b", 2));
+        let plain = load("plain", "toml", &parties).unwrap();
+        assert!(Plain::from_config(&plain).is_ok());
+
+        let seeded = load(
+            "seeded",
+            "toml",
+            &format!("seed = {{ fixed = 7 }}\n{}", parties),
+        )
+        .unwrap();
+        assert!(matches!(
+            Plain::from_config(&seeded),
+            Err(Error::Unsupported(_))
+        ));
+
+        let timed = load(
+            "timed",
+            "toml",
+            &format!("{}[timeouts]\nconnect_ms = 1\n", parties),
+        )
+        .unwrap();
+        assert!(matches!(
+            Plain::from_config(&timed),
+            Err(Error::Unsupported(_))
+        ));
+    }
+}
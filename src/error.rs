@@ -0,0 +1,214 @@
+//! The errors returned by the runtime, and how they cross the FFI boundary.
+//!
+//! Fallible FFI functions either return a `Status`, writing any result through an out-pointer, or return a
+//! pointer that is null on failure. In both cases the failure's message can then be retrieved with
+//! `symphony_last_error`.
+
+use std::cell::RefCell;
+use std::ffi::CString;
+use std::fmt;
+use std::io;
+
+#[derive(Debug)]
+pub enum Error {
+    /// A connection could not be established, or an established one failed.
+    Network(io::Error),
+    /// A peer closed its connection before the protocol finished.
+    Disconnected,
+    /// An argument could not be interpreted, such as a host name that is not valid UTF-8.
+    Malformed(String),
+    /// A peer did not follow the protocol, such as announcing an unexpected party id.
+    Mismatch(String),
+    /// The operation is not supported by the chosen backend.
+    Unsupported(&'static str),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Cloning a network error keeps its kind and message, but not its source.
+impl Clone for Error {
+    fn clone(&self) -> Self {
+        match self {
+            Error::Network(e) => Error::Network(io::Error::new(e.kind(), e.to_string())),
+            Error::Disconnected => Error::Disconnected,
+            Error::Malformed(what) => Error::Malformed(what.clone()),
+            Error::Mismatch(what) => Error::Mismatch(what.clone()),
+            Error::Unsupported(what) => Error::Unsupported(what),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Network(e) => write!(f, "network error: {}", e),
+            Error::Disconnected => write!(f, "peer disconnected"),
+            Error::Malformed(what) => write!(f, "malformed input: {}", what),
+            Error::Mismatch(what) => write!(f, "protocol mismatch: {}", what),
+            Error::Unsupported(what) => write!(f, "unsupported: {}", what),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Network(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        match e.kind() {
+            io::ErrorKind::UnexpectedEof
+            | io::ErrorKind::ConnectionReset
+            | io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::BrokenPipe => Error::Disconnected,
+            io::ErrorKind::InvalidData => Error::Mismatch(e.to_string()),
+            _ => Error::Network(e),
+        }
+    }
+}
+
+impl From<ocelot::Error> for Error {
+    fn from(e: ocelot::Error) -> Self {
+        match e {
+            ocelot::Error::IoError(e) => e.into(),
+            e => Error::Mismatch(format!("oblivious transfer failed: {}", e)),
+        }
+    }
+}
+
+impl From<std::str::Utf8Error> for Error {
+    fn from(e: std::str::Utf8Error) -> Self {
+        Error::Malformed(e.to_string())
+    }
+}
+
+impl From<std::ffi::NulError> for Error {
+    fn from(e: std::ffi::NulError) -> Self {
+        Error::Malformed(e.to_string())
+    }
+}
+
+/// The outcome of a fallible FFI call.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Ok = 0,
+    Network = 1,
+    Disconnected = 2,
+    Malformed = 3,
+    Mismatch = 4,
+    Unsupported = 5,
+}
+
+impl Error {
+    pub fn status(&self) -> Status {
+        match self {
+            Error::Network(_) => Status::Network,
+            Error::Disconnected => Status::Disconnected,
+            Error::Malformed(_) => Status::Malformed,
+            Error::Mismatch(_) => Status::Mismatch,
+            Error::Unsupported(_) => Status::Unsupported,
+        }
+    }
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = RefCell::new(None);
+}
+
+/// Records `error` as this thread's last error, returning its status.
+pub fn record(error: Error) -> Status {
+    let status = error.status();
+    let message = CString::new(error.to_string().replace('\0', " ")).unwrap_or_default();
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(message));
+    status
+}
+
+/// Converts `result` to a status, recording any error.
+pub fn status(result: Result<()>) -> Status {
+    match result {
+        Ok(()) => Status::Ok,
+        Err(e) => record(e),
+    }
+}
+
+/// Writes the value of `result` through `out`, or records its error.
+///
+/// # Safety
+///
+/// `out` must be valid for writes.
+pub unsafe fn status_out<T>(result: Result<T>, out: *mut T) -> Status {
+    match result {
+        Ok(value) => {
+            out.write(value);
+            Status::Ok
+        }
+        Err(e) => record(e),
+    }
+}
+
+/// Boxes the value of `result`, or records its error and returns null.
+pub fn boxed<T>(result: Result<T>) -> *mut T {
+    match result {
+        Ok(value) => Box::into_raw(Box::new(value)),
+        Err(e) => {
+            record(e);
+            std::ptr::null_mut()
+        }
+    }
+}
+
+pub mod ffi {
+    use super::*;
+
+    /// The message of the last error on this thread, or null if there has been none. The message remains valid
+    /// until the next error on this thread, and is only meaningful immediately after a call reports failure.
+    #[no_mangle]
+    pub extern "C" fn symphony_last_error() -> *const libc::c_char {
+        LAST_ERROR.with(|last| match &*last.borrow() {
+            Some(message) => message.as_ptr(),
+            None => std::ptr::null(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::CStr;
+
+    #[test]
+    fn io_errors_are_classified() {
+        let eof = io::Error::new(io::ErrorKind::UnexpectedEof, "eof");
+        assert!(matches!(Error::from(eof), Error::Disconnected));
+
+        let refused = io::Error::new(io::ErrorKind::ConnectionRefused, "refused");
+        assert_eq!(Error::from(refused).status(), Status::Network);
+
+        let forged = io::Error::new(io::ErrorKind::InvalidData, "frame failed authentication");
+        assert_eq!(Error::from(forged).status(), Status::Mismatch);
+    }
+
+    #[test]
+    fn last_error_is_recorded() {
+        assert_eq!(status(Ok(())), Status::Ok);
+
+        let mut out = 0u32;
+        let result = Err(Error::Mismatch("party 7 announced itself twice".to_owned()));
+        assert_eq!(unsafe { status_out(result, &mut out) }, Status::Mismatch);
+        let message = unsafe { CStr::from_ptr(ffi::symphony_last_error()) };
+        assert_eq!(
+            message.to_str().unwrap(),
+            "protocol mismatch: party 7 announced itself twice"
+        );
+
+        assert!(boxed::<u32>(Err(Error::Disconnected)).is_null());
+        let message = unsafe { CStr::from_ptr(ffi::symphony_last_error()) };
+        assert_eq!(message.to_str().unwrap(), "peer disconnected");
+    }
+}
@@ -1,122 +1,307 @@
-use crate::motion;
+use crate::config::Config;
+use crate::error::{Error, Result};
 use crate::util;
 use crate::util::BitVec;
 use rand::{CryptoRng, Rng};
-use std::cell::RefCell;
 use std::io::{Read, Write};
 use std::os::unix::io::RawFd;
 use std::os::unix::prelude::FromRawFd;
-use std::rc::Rc;
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+mod backend;
+pub use backend::Backend;
+
+#[cfg(feature = "motion")]
+pub use crate::motion::Engine as Motion;
+pub use crate::native::Engine as Native;
+pub use crate::native::{Cost, Preprocessing, Simulator as Plain};
+
+/// The backend used by `Protocol` when none is specified, and by the FFI.
+#[cfg(feature = "motion")]
+pub type DefaultBackend = Motion;
+#[cfg(not(feature = "motion"))]
+pub type DefaultBackend = Native;
 
 /// A GMW Protocol instance, each owned by a participating party.
-pub struct Protocol {
-    my_id: usize,
-    delayed: Vec<Rc<RefCell<CachedBool>>>,
-    delayed_nat: Vec<Rc<RefCell<CachedNat>>>,
-    party: motion::Party,
-    transports: motion::Transports,
+///
+/// A protocol and its shares can be sent between threads, and shares shared between them, but every operation goes
+/// through `&mut Protocol`, so only one thread computes with a protocol at a time.
+pub struct Protocol<B: Backend = DefaultBackend> {
+    delayed: Vec<Arc<Mutex<CachedBool<B>>>>,
+    delayed_nat: Vec<Arc<Mutex<CachedNat<B>>>>,
+    backend: B,
 }
 
-impl Protocol {
-    pub fn new(my_id: usize, hosts: Vec<String>, ports: Vec<u16>) -> Self {
-        let transports = motion::Transports::new(my_id, &hosts, &ports);
-        let party = motion::Party::new(my_id, &transports);
+impl<B: Backend> Protocol<B> {
+    pub fn new(my_id: usize, hosts: Vec<String>, ports: Vec<u16>) -> Result<Self> {
+        Ok(Self::with_backend(B::new(my_id, &hosts, &ports)?))
+    }
+
+    /// Connects to the other parties as `config` describes.
+    pub fn from_config(config: &Config) -> Result<Self> {
+        Ok(Self::with_backend(B::from_config(config)?))
+    }
+
+    pub fn with_backend(backend: B) -> Self {
         Self {
-            my_id,
             delayed: Vec::new(),
             delayed_nat: Vec::new(),
-            transports,
-            party,
+            backend,
+        }
+    }
+
+    /// The number of parties in the protocol, including this one.
+    pub fn parties(&self) -> usize {
+        self.backend.parties()
+    }
+
+    pub fn backend(&self) -> &B {
+        &self.backend
+    }
+
+    pub fn backend_mut(&mut self) -> &mut B {
+        &mut self.backend
+    }
+
+    /// Generates `triples` multiplication triples and `bits` random bits ahead of time, so that later circuits
+    /// need no interaction beyond opening masked values. Every party must call this with the same counts.
+    pub fn preprocess(&mut self, triples: usize, bits: usize) -> Result<()> {
+        self.backend.preprocess(triples, bits)
+    }
+
+    /// The preprocessed material not yet consumed, or `None` if the backend does not expose it.
+    pub fn preprocessing(&mut self) -> Option<&mut Preprocessing> {
+        self.backend.preprocessing()
+    }
+
+    /// Saves the unconsumed preprocessed material to `path`, to be loaded by this same party in a later session
+    /// with the same peers.
+    pub fn save_preprocessing<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        match self.backend.preprocessing() {
+            Some(store) => Ok(store.save(path)?),
+            None => Err(unsupported()),
         }
     }
 
-    fn run(&mut self) {
-        self.party.run();
+    /// Replaces the preprocessed material with that saved at `path`.
+    pub fn load_preprocessing<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        let loaded = Preprocessing::load(path)?;
+        match self.backend.preprocessing() {
+            Some(store) => {
+                *store = loaded;
+                Ok(())
+            }
+            None => Err(unsupported()),
+        }
+    }
 
+    /// Evaluates every delayed share. On failure the shares stay unevaluated, and the protocol should not be
+    /// used further.
+    fn run(&mut self) -> Result<()> {
+        self.backend.run()?;
+        self.collect();
+        Ok(())
+    }
+
+    /// Caches the value of every delayed share once the backend has evaluated them, and starts a new circuit.
+    fn collect(&mut self) {
         while let Some(cbr) = self.delayed.pop() {
-            let r = &mut *cbr.borrow_mut();
+            let r = &mut *cbr.lock().unwrap();
             let share = match r {
-                CachedBool::Expr(e) => e.get(),
+                CachedBool::Expr(e) => self.backend.bool_get(e),
                 _ => unreachable!(),
             };
             *r = CachedBool::Value(share);
         }
 
         while let Some(cnr) = self.delayed_nat.pop() {
-            let r = &mut *cnr.borrow_mut();
+            let r = &mut *cnr.lock().unwrap();
             let share = match r {
-                CachedNat::Expr(e) => e.get(),
+                CachedNat::Expr(e) => self.backend.nat_get(e),
                 _ => unreachable!(),
             };
             *r = CachedNat::Value(share);
         }
 
-        self.party = motion::Party::new(self.my_id, &self.transports);
+        self.backend.reset();
+    }
+
+    /// Whether any share awaits evaluation.
+    #[cfg(feature = "async")]
+    fn pending(&self) -> bool {
+        !self.delayed.is_empty() || !self.delayed_nat.is_empty()
     }
 }
 
+fn unsupported() -> Error {
+    Error::Unsupported("backend does not expose its preprocessing")
+}
+
 fn share_send_bool<Prg: Rng + CryptoRng, W: Write>(
     prg: &mut Prg,
     channels: &mut [&mut W],
     clear: bool,
-) {
+) -> Result<()> {
     let mut masked = clear;
     let mut share = false;
 
     for c in channels.iter_mut().skip(1) {
         share = prg.gen();
         let buf = [share as u8; 1];
-        c.write_all(&buf).expect("TODO");
+        c.write_all(&buf)?;
         masked ^= share;
     }
 
     let buf = [masked as u8; 1];
-    channels[0].write_all(&buf).expect("TODO")
+    channels[0].write_all(&buf)?;
+    Ok(())
 }
 
 fn share_send<Prg: Rng + CryptoRng, W: Write>(
     prg: &mut Prg,
     channels: &mut [&mut W],
     clear: &[u8],
-) {
+) -> Result<()> {
     let mut masked = clear.to_vec();
     let mut share = vec![0; masked.len()];
 
     for c in channels.iter_mut().skip(1) {
         prg.fill_bytes(&mut share);
-        c.write_all(&share).expect("TODO");
+        c.write_all(&share)?;
         util::xor_inplace(&mut masked, &share);
     }
 
-    channels[0].write_all(&masked).expect("TODO")
+    channels[0].write_all(&masked)?;
+    Ok(())
 }
 
-fn reveal_recv_bool<R: Read>(channels: &mut [&mut R]) -> bool {
+fn reveal_recv_bool<R: Read>(channels: &mut [&mut R]) -> Result<bool> {
     let mut ret = false;
 
     for c in channels {
         let mut buf = [0u8; 1];
-        c.read_exact(&mut buf).expect("TODO");
+        c.read_exact(&mut buf)?;
         let share = buf[0] != 0;
         ret ^= share;
     }
 
-    ret
+    Ok(ret)
 }
 
-fn reveal_recv<R: Read>(channels: &mut [&mut R], clear: &mut [u8]) {
+fn reveal_recv<R: Read>(channels: &mut [&mut R], clear: &mut [u8]) -> Result<()> {
     clear.iter_mut().for_each(|b| *b = 0);
 
     let mut share = vec![0u8; clear.len()];
 
     for c in channels {
-        c.read_exact(&mut share).expect("TODO");
+        c.read_exact(&mut share)?;
         util::xor_inplace(clear, &share);
     }
+
+    Ok(())
+}
+
+/// How many bytes hold a `bits`-bit value.
+fn byte_len(bits: usize) -> usize {
+    bits.div_ceil(8)
+}
+
+/// Checks that a share has the width an FFI caller expects.
+pub(crate) fn check_width(bits: &[bool], width: usize) -> Result<()> {
+    if bits.len() != width {
+        return Err(Error::Malformed(format!(
+            "expected a {}-bit share, but it has {} bits",
+            width,
+            bits.len()
+        )));
+    }
+    Ok(())
 }
 
+/// The FFI for a share type at one of the fixed widths of the primitive integer type `$ty`, which C callers can
+/// pass by value. Values of other widths go through the functions taking a byte buffer and a bit count.
+macro_rules! fixed_width_ffi {
+    (
+        $share:ident, $ty:ty,
+        $new:ident, $constant:ident, $get:ident,
+        $share_send:ident, $share_recv:ident, $reveal_send:ident, $reveal_recv:ident
+    ) => {
+        #[no_mangle]
+        pub unsafe extern "C" fn $new(protocol: *mut Protocol, share: $ty) -> *mut $share {
+            let ret = $share::new(&mut *protocol, &share.to_le_bytes());
+            Box::into_raw(Box::new(ret))
+        }
+
+        #[no_mangle]
+        pub unsafe extern "C" fn $constant(protocol: *mut Protocol, value: $ty) -> *mut $share {
+            let ret = $share::constant(&mut *protocol, &value.to_le_bytes());
+            Box::into_raw(Box::new(ret))
+        }
+
+        #[no_mangle]
+        pub unsafe extern "C" fn $get(
+            protocol: *mut Protocol,
+            share: *mut $share,
+            out: *mut $ty,
+        ) -> Status {
+            let ret = $share::get_bits(&mut *protocol, &mut *share).and_then(|bits| {
+                $crate::gmw::check_width(&bits, <$ty>::BITS as usize)?;
+                let bytes = $crate::util::from_bits(&bits);
+                Ok(<$ty>::from_le_bytes(bytes.try_into().unwrap()))
+            });
+            error::status_out(ret, out)
+        }
+
+        #[no_mangle]
+        pub unsafe extern "C" fn $share_send(
+            prg: *mut AesRng,
+            channels: *mut *mut Channel,
+            channels_len: usize,
+            clear: $ty,
+        ) -> Status {
+            let channels: &mut [&mut Channel] =
+                std::mem::transmute(std::slice::from_raw_parts_mut(channels, channels_len));
+            error::status(share_send(&mut *prg, channels, &clear.to_le_bytes()))
+        }
+
+        #[no_mangle]
+        pub unsafe extern "C" fn $share_recv(channel: *mut Channel, out: *mut $ty) -> Status {
+            let channel = &mut *channel;
+            let mut buf = [0u8; std::mem::size_of::<$ty>()];
+            let ret = channel
+                .read_exact(&mut buf)
+                .map(|()| <$ty>::from_le_bytes(buf));
+            error::status_out(ret.map_err(Into::into), out)
+        }
+
+        #[no_mangle]
+        pub unsafe extern "C" fn $reveal_send(channel: *mut Channel, share: $ty) -> Status {
+            let channel = &mut *channel;
+            error::status(channel.write_all(&share.to_le_bytes()).map_err(Into::into))
+        }
+
+        #[no_mangle]
+        pub unsafe extern "C" fn $reveal_recv(
+            channels: *mut *mut Channel,
+            channels_len: usize,
+            out: *mut $ty,
+        ) -> Status {
+            let channels: &mut [&mut Channel] =
+                std::mem::transmute(std::slice::from_raw_parts_mut(channels, channels_len));
+            let mut buf = [0u8; std::mem::size_of::<$ty>()];
+            let ret = reveal_recv(channels, &mut buf).map(|()| <$ty>::from_le_bytes(buf));
+            error::status_out(ret, out)
+        }
+    };
+}
+
+/// A protocol and the shares created through it may be used from any thread, but calls involving the same protocol,
+/// including freeing its shares, must not overlap. Calls involving different protocols may run concurrently.
 pub mod ffi {
     use super::*;
+    use crate::error::{self, Status};
     use crate::util::ffi::*;
     use std::ffi::CStr;
 
@@ -127,13 +312,25 @@ pub mod ffi {
         ports: *const u16,
         len: usize,
     ) -> *mut Protocol {
-        let hosts = c_to_vec(hosts, len)
-            .into_iter()
-            .map(|host_ptr| CStr::from_ptr(host_ptr).to_str().expect("TODO").to_owned())
-            .collect();
-        let ports = c_to_vec(ports, len);
-        let ret = Protocol::new(id, hosts, ports);
-        Box::into_raw(Box::new(ret))
+        let ret = (|| {
+            let hosts = c_to_vec(hosts, len)
+                .into_iter()
+                .map(|host_ptr| Ok(CStr::from_ptr(host_ptr).to_str()?.to_owned()))
+                .collect::<Result<_>>()?;
+            let ports = c_to_vec(ports, len);
+            Protocol::new(id, hosts, ports)
+        })();
+        error::boxed(ret)
+    }
+
+    /// Connects to the other parties as the configuration file at `path` describes. See `config` for its format.
+    #[no_mangle]
+    pub unsafe extern "C" fn gmw_protocol_from_config(path: *const libc::c_char) -> *mut Protocol {
+        let ret = (|| {
+            let path = CStr::from_ptr(path).to_str()?;
+            Protocol::from_config(&Config::load(path)?)
+        })();
+        error::boxed(ret)
     }
 
     #[no_mangle]
@@ -141,14 +338,49 @@ pub mod ffi {
         Box::from_raw(protocol);
     }
 
+    #[no_mangle]
+    pub unsafe extern "C" fn gmw_protocol_preprocess(
+        protocol: *mut Protocol,
+        triples: usize,
+        bits: usize,
+    ) -> Status {
+        error::status((*protocol).preprocess(triples, bits))
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn gmw_protocol_save_preprocessing(
+        protocol: *mut Protocol,
+        path: *const libc::c_char,
+    ) -> Status {
+        let ret = CStr::from_ptr(path)
+            .to_str()
+            .map_err(Error::from)
+            .and_then(|path| (*protocol).save_preprocessing(path));
+        error::status(ret)
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn gmw_protocol_load_preprocessing(
+        protocol: *mut Protocol,
+        path: *const libc::c_char,
+    ) -> Status {
+        let ret = CStr::from_ptr(path)
+            .to_str()
+            .map_err(Error::from)
+            .and_then(|path| (*protocol).load_preprocessing(path));
+        error::status(ret)
+    }
+
+    pub use arith::ffi::*;
     pub use boolean::ffi::*;
+    pub use fixed::ffi::*;
     pub use integer::ffi::*;
     pub use natural::ffi::*;
 }
 
 mod boolean;
 pub use boolean::Bool;
-use boolean::CachedBool;
+pub use boolean::CachedBool;
 
 mod natural;
 use natural::CachedNat;
@@ -157,5 +389,408 @@ pub use natural::Nat;
 mod integer;
 pub use integer::Int;
 
+mod fixed;
+pub use fixed::{decode_fixed, encode_fixed, Fixed, Rounding};
+
+mod arith;
+pub use arith::{a2b, a2b_int, b2a, b2a_int, Arith, ArithProtocol};
+
+#[cfg(feature = "async")]
+mod async_protocol;
+#[cfg(feature = "async")]
+pub use async_protocol::AsyncProtocol;
+
 #[cfg(test)]
-mod tests {}
+mod tests {
+    use super::*;
+    use crate::util::harness;
+    use crate::util::{Channel, LocalChannel};
+    use scuttlebutt::AesRng;
+
+    fn protocol() -> Protocol<Plain> {
+        Protocol::with_backend(Plain::new(3))
+    }
+
+    #[test]
+    fn bool_ops() {
+        let p = &mut protocol();
+        for a in [false, true] {
+            for b in [false, true] {
+                let x = Bool::new(p, a);
+                let y = Bool::new(p, b);
+                let g = Bool::constant(p, true);
+                let ops = [
+                    (Bool::xor(p, &x, &y), a ^ b),
+                    (Bool::and(p, &x, &y), a & b),
+                    (Bool::or(p, &x, &y), a | b),
+                    (Bool::not(p, &x), !a),
+                    (Bool::eq(p, &x, &y), a == b),
+                    (Bool::mux(p, &g, &x, &y), a),
+                ];
+                for (share, expected) in ops {
+                    assert_eq!(Bool::get(p, &share).unwrap(), expected);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn nat_ops() {
+        let p = &mut protocol();
+        for (a, b) in [(0u32, 0u32), (7, 3), (3, 7), (u32::MAX, 1), (65536, 65535)] {
+            let x = Nat::new(p, &a.to_le_bytes());
+            let y = Nat::new(p, &b.to_le_bytes());
+            let sums = [
+                (Nat::add(p, &x, &y), a.wrapping_add(b)),
+                (Nat::sub(p, &x, &y), a.wrapping_sub(b)),
+                (Nat::mul(p, &x, &y), a.wrapping_mul(b)),
+            ];
+            for (share, expected) in sums {
+                let bytes = Nat::get(p, &share).unwrap();
+                assert_eq!(u32::from_le_bytes(bytes.try_into().unwrap()), expected);
+            }
+
+            let flags = [
+                (Nat::eq(p, &x, &y), a == b),
+                (Nat::gt(p, &x, &y), a > b),
+                (Nat::lt(p, &x, &y), a < b),
+                (Nat::gte(p, &x, &y), a >= b),
+                (Nat::lte(p, &x, &y), a <= b),
+            ];
+            for (share, expected) in flags {
+                assert_eq!(Bool::get(p, &share).unwrap(), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn nat_bitwise_and_division() {
+        let p = &mut protocol();
+        let get = |p: &mut Protocol<Plain>, share: Nat<Plain>| Nat::get(p, &share).unwrap()[0];
+        for (a, b) in [
+            (0u8, 0u8),
+            (7, 3),
+            (3, 7),
+            (200, 13),
+            (255, 1),
+            (128, 255),
+            (9, 0),
+        ] {
+            let x = Nat::new(p, &[a]);
+            let y = Nat::new(p, &[b]);
+            let results = [
+                (Nat::and(p, &x, &y), a & b),
+                (Nat::or(p, &x, &y), a | b),
+                (Nat::xor(p, &x, &y), a ^ b),
+                (Nat::not(p, &x), !a),
+                (Nat::shl(p, &x, 3), a << 3),
+                (Nat::shr(p, &x, 3), a >> 3),
+                (Nat::shl(p, &x, 9), 0),
+                (Nat::min(p, &x, &y), a.min(b)),
+                (Nat::max(p, &x, &y), a.max(b)),
+                (Nat::div(p, &x, &y), a.checked_div(b).unwrap_or(u8::MAX)),
+                (Nat::modulo(p, &x, &y), a.checked_rem(b).unwrap_or(a)),
+            ];
+            for (i, (share, expected)) in results.into_iter().enumerate() {
+                assert_eq!(get(p, share), expected, "operation {} on {} {}", i, a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn nat_casts() {
+        let p = &mut protocol();
+        for a in [0u16, 1, 255, 256, 0xbeef] {
+            let x = Nat::new(p, &a.to_le_bytes());
+            let wide = Nat::zero_extend(p, &x, 32);
+            assert_eq!(Nat::get(p, &wide).unwrap(), (a as u32).to_le_bytes());
+            let narrow = Nat::truncate(p, &x, 8);
+            assert_eq!(Nat::get(p, &narrow).unwrap(), [a as u8]);
+
+            let nonzero = Nat::is_nonzero(p, &narrow);
+            assert_eq!(Bool::get(p, &nonzero).unwrap(), a as u8 != 0);
+            let flag = Nat::from_bool(p, &nonzero, 16);
+            assert_eq!(
+                Nat::get(p, &flag).unwrap(),
+                ((a as u8 != 0) as u16).to_le_bytes()
+            );
+        }
+    }
+
+    /// Runs the operations built from boolean circuits on the backend `Protocol` uses by default.
+    #[test]
+    fn default_backend_bit_ops() {
+        let (a, b) = (200u8, 13u8);
+        let parties = 2;
+        let hosts = vec!["127.0.0.1".to_owned(); parties];
+        let ports = harness::free_ports(parties);
+        let shares = harness::run_with(vec![(); parties], |my_id, ()| {
+            let p =
+                &mut Protocol::<DefaultBackend>::new(my_id, hosts.clone(), ports.clone()).unwrap();
+            let inputs = if my_id == 0 { [a, b] } else { [0, 0] };
+            let x = Nat::new(p, &[inputs[0]]);
+            let y = Nat::new(p, &[inputs[1]]);
+            let results = [
+                Nat::div(p, &x, &y),
+                Nat::modulo(p, &x, &y),
+                Nat::and(p, &x, &y),
+                Nat::xor(p, &x, &y),
+                Nat::shr(p, &x, 3),
+                Nat::zero_extend(p, &x, 16),
+            ];
+            results
+                .iter()
+                .flat_map(|r| Nat::get(p, r).unwrap())
+                .collect::<Vec<u8>>()
+        });
+        let revealed: Vec<u8> = shares[0]
+            .iter()
+            .zip(&shares[1])
+            .map(|(l, r)| l ^ r)
+            .collect();
+        assert_eq!(revealed, [a / b, a % b, a & b, a ^ b, a >> 3, a, 0]);
+    }
+
+    #[test]
+    fn nat_widths() {
+        let p = &mut protocol();
+        // 12-bit values wrap at 4096, and are read back zero-extended to two bytes.
+        let x = Nat::new_bits(p, &4000u16.to_le_bytes(), 12);
+        let y = Nat::constant_bits(p, &100u16.to_le_bytes(), 12);
+        let sum = Nat::add(p, &x, &y);
+        assert_eq!(Nat::get(p, &sum).unwrap(), 4u16.to_le_bytes());
+        let bits = Nat::get_bits(p, &sum).unwrap();
+        assert!(check_width(&bits, 12).is_ok());
+        assert!(matches!(check_width(&bits, 16), Err(Error::Malformed(_))));
+
+        let (a, b) = (u128::MAX - 5, (1u128 << 100) + 3);
+        let x = Nat::new(p, &a.to_le_bytes());
+        let y = Nat::new(p, &b.to_le_bytes());
+        let prod = Nat::mul(p, &x, &y);
+        let bytes = Nat::get(p, &prod).unwrap();
+        assert_eq!(
+            u128::from_le_bytes(bytes.try_into().unwrap()),
+            a.wrapping_mul(b)
+        );
+    }
+
+    #[test]
+    fn plain_cost() {
+        let p = &mut protocol();
+        let x = Bool::new(p, true);
+        let y = Bool::new(p, false);
+        let z = Bool::new(p, true);
+        let xy = Bool::and(p, &x, &y);
+        let yz = Bool::and(p, &y, &z);
+        let xyz = Bool::and(p, &xy, &z);
+        let _ = Bool::xor(p, &yz, &xyz);
+        assert!(!Bool::get(p, &xyz).unwrap());
+
+        let cost = p.backend().cost();
+        assert_eq!(cost.and_gates, 3);
+        assert_eq!(cost.rounds, 2);
+        assert_eq!(cost.bytes, 2 * 2);
+    }
+
+    #[test]
+    fn shares_cross_threads() {
+        fn send_sync<T: Send + Sync>() {}
+        send_sync::<Bool<Native>>();
+        send_sync::<Nat<Native>>();
+        send_sync::<Int<Native>>();
+        #[cfg(feature = "motion")]
+        {
+            fn send<T: Send>() {}
+            send::<Bool<Motion>>();
+            send::<Nat<Motion>>();
+            send::<Int<Motion>>();
+        }
+
+        let (a, b) = (0x1234u32, 0x5678u32);
+        let products = harness::run(2, |my_id, engine| {
+            let mut p = Protocol::with_backend(Native::from_channels(my_id, engine));
+            let shares = if my_id == 0 { [a, b] } else { [0, 0] };
+            let x = Nat::new(&mut p, &shares[0].to_le_bytes());
+            let y = Nat::new(&mut p, &shares[1].to_le_bytes());
+            // A clone of `x` stays behind while the protocol and the shares move to another thread.
+            let kept = x.clone();
+            let prod = std::thread::spawn(move || {
+                let prod = Nat::mul(&mut p, &x, &y);
+                Nat::get(&mut p, &prod).unwrap()
+            })
+            .join()
+            .unwrap();
+            drop(kept);
+            prod
+        });
+        let bytes: Vec<u8> = products[0]
+            .iter()
+            .zip(&products[1])
+            .map(|(l, r)| l ^ r)
+            .collect();
+        assert_eq!(u32::from_le_bytes(bytes.try_into().unwrap()), a * b);
+    }
+
+    /// Gives this party a loopback channel to itself, since the share and reveal helpers address every party.
+    fn with_loopback(channels: Vec<Option<Channel>>) -> Vec<Channel> {
+        channels
+            .into_iter()
+            .map(|c| c.unwrap_or_else(|| Channel::Local(LocalChannel::new())))
+            .collect()
+    }
+
+    /// Secret-shares `clear` from `dealer` to every party, returning this party's share.
+    fn deal(my_id: usize, dealer: usize, channels: &mut [Channel], clear: &[u8]) -> Vec<u8> {
+        if my_id == dealer {
+            let mut prg = AesRng::new();
+            let mut refs: Vec<&mut Channel> = channels.iter_mut().collect();
+            share_send(&mut prg, &mut refs, clear).unwrap();
+            refs.iter_mut().for_each(|c| c.flush().unwrap());
+        }
+
+        let mut share = vec![0u8; clear.len()];
+        channels[dealer].read_exact(&mut share).unwrap();
+        share
+    }
+
+    /// Sends this party's share to every party and reconstructs the value from theirs.
+    fn reveal(channels: &mut [Channel], share: &[u8]) -> Vec<u8> {
+        for c in channels.iter_mut() {
+            c.write_all(share).unwrap();
+            c.flush().unwrap();
+        }
+
+        let mut refs: Vec<&mut Channel> = channels.iter_mut().collect();
+        let mut clear = vec![0u8; share.len()];
+        reveal_recv(&mut refs, &mut clear).unwrap();
+        clear
+    }
+
+    #[test]
+    fn share_and_reveal() {
+        for parties in [2, 3, 5] {
+            let clear = 0xdeadbeefu32.to_le_bytes();
+            let revealed = harness::run(parties, |my_id, channels| {
+                let mut channels = with_loopback(channels);
+                let share = deal(my_id, parties - 1, &mut channels, &clear);
+                reveal(&mut channels, &share)
+            });
+            assert!(revealed.iter().all(|r| *r == clear));
+        }
+    }
+
+    #[test]
+    fn share_and_reveal_bool() {
+        for parties in [2, 3, 5] {
+            let revealed = harness::run(parties, |my_id, channels| {
+                let mut channels = with_loopback(channels);
+                if my_id == 0 {
+                    let mut prg = AesRng::new();
+                    let mut refs: Vec<&mut Channel> = channels.iter_mut().collect();
+                    share_send_bool(&mut prg, &mut refs, true).unwrap();
+                    refs.iter_mut().for_each(|c| c.flush().unwrap());
+                }
+
+                let mut share = [0u8; 1];
+                channels[0].read_exact(&mut share).unwrap();
+                for c in channels.iter_mut() {
+                    c.write_all(&share).unwrap();
+                    c.flush().unwrap();
+                }
+
+                let mut refs: Vec<&mut Channel> = channels.iter_mut().collect();
+                reveal_recv_bool(&mut refs).unwrap()
+            });
+            assert!(revealed.into_iter().all(|r| r));
+        }
+    }
+
+    #[test]
+    fn native_protocol() {
+        let (a, b) = (1234567u32, 7654321u32);
+        for parties in [2, 3, 5] {
+            let meshes = harness::mesh(parties)
+                .into_iter()
+                .zip(harness::mesh(parties))
+                .collect();
+            let revealed = harness::run_with(meshes, |my_id, (io, engine)| {
+                let mut io = with_loopback(io);
+                let a_share = deal(my_id, 0, &mut io, &a.to_le_bytes());
+                let b_share = deal(my_id, parties - 1, &mut io, &b.to_le_bytes());
+
+                let p = &mut Protocol::with_backend(Native::from_channels(my_id, engine));
+                let x = Nat::new(p, &a_share);
+                let y = Nat::new(p, &b_share);
+                let sum = Nat::add(p, &x, &y);
+                let prod = Nat::mul(p, &x, &y);
+                let lt = Nat::lt(p, &x, &y);
+
+                let mut shares = Nat::get(p, &sum).unwrap();
+                shares.extend(Nat::get(p, &prod).unwrap());
+                shares.push(Bool::get(p, &lt).unwrap() as u8);
+                reveal(&mut io, &shares)
+            });
+
+            let mut expected = a.wrapping_add(b).to_le_bytes().to_vec();
+            expected.extend(a.wrapping_mul(b).to_le_bytes());
+            expected.push(1);
+            assert!(revealed.iter().all(|r| *r == expected));
+        }
+    }
+
+    #[test]
+    fn preprocessed_native_protocol() {
+        let (a, b) = (0x0123_4567u32, 0x7654_3210u32);
+        let parties = 3;
+        let dir = std::env::temp_dir();
+        let path = |id: usize| {
+            dir.join(format!(
+                "symphony-preprocessing-{}-{}",
+                std::process::id(),
+                id
+            ))
+        };
+
+        // Preprocess in one session and save the material.
+        harness::run(parties, |my_id, engine| {
+            let p = &mut Protocol::with_backend(Native::from_channels(my_id, engine));
+            p.preprocess(2000, 16).unwrap();
+            p.save_preprocessing(path(my_id)).unwrap();
+        });
+
+        // Load it in another, over fresh channels, and consume some of it.
+        let meshes = harness::mesh(parties)
+            .into_iter()
+            .zip(harness::mesh(parties))
+            .collect();
+        let revealed = harness::run_with(meshes, |my_id, (io, engine)| {
+            let mut io = with_loopback(io);
+            let a_share = deal(my_id, 0, &mut io, &a.to_le_bytes());
+            let b_share = deal(my_id, 1, &mut io, &b.to_le_bytes());
+
+            let p = &mut Protocol::with_backend(Native::from_channels(my_id, engine));
+            p.load_preprocessing(path(my_id)).unwrap();
+            std::fs::remove_file(path(my_id)).unwrap();
+
+            let x = Nat::new(p, &a_share);
+            let y = Nat::new(p, &b_share);
+            let prod = Nat::mul(p, &x, &y);
+            let r = Bool::random(p);
+            let r_and_not_r = {
+                let not_r = Bool::not(p, &r);
+                Bool::and(p, &r, &not_r)
+            };
+
+            let mut shares = Nat::get(p, &prod).unwrap();
+            shares.push(Bool::get(p, &r_and_not_r).unwrap() as u8);
+            let store = p.preprocessing().unwrap();
+            assert!(store.triples() < 2000);
+            assert_eq!(store.bits(), 15);
+            reveal(&mut io, &shares)
+        });
+
+        let mut expected = a.wrapping_mul(b).to_le_bytes().to_vec();
+        expected.push(0);
+        assert!(revealed.iter().all(|r| *r == expected));
+    }
+}
@@ -0,0 +1,602 @@
+//! Additive secret sharing over `Z_2^k`, alongside the boolean shares of `Nat` and `Int`.
+//!
+//! Addition, subtraction and multiplication by a constant are local. Multiplication consumes an arithmetic
+//! Beaver triple, generated by oblivious transfer, and costs one round. A2B and B2A convert to and from the
+//! boolean representation through a `gmw::Protocol` between the same parties, with the same ids.
+
+use crate::error::{Error, Result};
+use crate::gmw::*;
+use crate::native;
+use crate::native::Peers;
+use crate::util;
+use crate::util::{Channel, Mesh};
+use rand::{CryptoRng, Rng};
+use std::io::{Read, Write};
+
+/// The channels over which arithmetic shares are multiplied and converted, each owned by a participating party.
+pub struct ArithProtocol {
+    my_id: usize,
+    parties: usize,
+    peers: Peers,
+}
+
+impl ArithProtocol {
+    pub fn new(my_id: usize, hosts: Vec<String>, ports: Vec<u16>) -> Result<Self> {
+        Ok(Self::from_channels(
+            my_id,
+            Mesh::new(my_id, &hosts, &ports).connect()?,
+        ))
+    }
+
+    /// Uses already-established channels, where `channels[i]` connects to party `i` and this party's own entry
+    /// is `None`.
+    pub fn from_channels(my_id: usize, channels: Vec<Option<Channel>>) -> Self {
+        Self {
+            my_id,
+            parties: channels.len(),
+            peers: Peers::new(channels),
+        }
+    }
+
+    fn leader(&self) -> bool {
+        self.my_id == 0
+    }
+}
+
+/// An additive share of an element of `Z_2^k`, where `k` is a multiple of 8 no larger than 64.
+///
+/// Shares are kept modulo `2^64`, which is compatible with every smaller power of two, and only reduced when
+/// they leave the protocol.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Arith {
+    share: u64,
+    bits: usize,
+}
+
+impl Arith {
+    fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        if bytes.is_empty() || bytes.len() > 8 {
+            return Err(Error::Malformed(format!(
+                "an arithmetic share has 1 to 8 bytes, not {}",
+                bytes.len()
+            )));
+        }
+        let mut buf = [0u8; 8];
+        buf[..bytes.len()].copy_from_slice(bytes);
+        Ok(Self {
+            share: u64::from_le_bytes(buf),
+            bits: 8 * bytes.len(),
+        })
+    }
+
+    /// Checks that `a` and `b` are shares in the same ring.
+    fn check_bits(a: &Self, b: &Self) -> Result<()> {
+        if a.bits != b.bits {
+            return Err(Error::Malformed(format!(
+                "cannot combine a {}-bit share with a {}-bit share",
+                a.bits, b.bits
+            )));
+        }
+        Ok(())
+    }
+
+    fn to_bytes(self) -> Vec<u8> {
+        self.share.to_le_bytes()[..self.bits / 8].to_vec()
+    }
+
+    /// Inputs this party's additive share of a value, least significant byte first.
+    pub fn new(_protocol: &mut ArithProtocol, share: &[u8]) -> Result<Self> {
+        Self::from_bytes(share)
+    }
+
+    pub fn constant(protocol: &mut ArithProtocol, value: &[u8]) -> Result<Self> {
+        let mut ret = Self::from_bytes(value)?;
+        if !protocol.leader() {
+            ret.share = 0;
+        }
+        Ok(ret)
+    }
+
+    pub fn add(_protocol: &mut ArithProtocol, a: &Self, b: &Self) -> Result<Self> {
+        Self::check_bits(a, b)?;
+        Ok(Self {
+            share: a.share.wrapping_add(b.share),
+            bits: a.bits,
+        })
+    }
+
+    pub fn sub(_protocol: &mut ArithProtocol, a: &Self, b: &Self) -> Result<Self> {
+        Self::check_bits(a, b)?;
+        Ok(Self {
+            share: a.share.wrapping_sub(b.share),
+            bits: a.bits,
+        })
+    }
+
+    pub fn neg(_protocol: &mut ArithProtocol, a: &Self) -> Self {
+        Self {
+            share: a.share.wrapping_neg(),
+            bits: a.bits,
+        }
+    }
+
+    pub fn mul_constant(_protocol: &mut ArithProtocol, a: &Self, value: u64) -> Self {
+        Self {
+            share: a.share.wrapping_mul(value),
+            bits: a.bits,
+        }
+    }
+
+    pub fn mul(protocol: &mut ArithProtocol, a: &Self, b: &Self) -> Result<Self> {
+        Ok(Self::mul_many(protocol, &[*a], &[*b])?[0])
+    }
+
+    /// Multiplies `a[i]` by `b[i]` for every `i`, in a single round.
+    pub fn mul_many(protocol: &mut ArithProtocol, a: &[Self], b: &[Self]) -> Result<Vec<Self>> {
+        debug_assert_eq!(a.len(), b.len());
+        for (a, b) in a.iter().zip(b) {
+            Self::check_bits(a, b)?;
+        }
+        let triples = native::generate_arith(protocol.my_id, &mut protocol.peers, a.len())?;
+
+        let mut masked = Vec::with_capacity(2 * a.len());
+        for ((a, b), t) in a.iter().zip(b).zip(&triples) {
+            masked.push(a.share.wrapping_sub(t.a));
+            masked.push(b.share.wrapping_sub(t.b));
+        }
+        let opened = protocol.peers.open_words(&masked)?;
+
+        let products = a
+            .iter()
+            .zip(b)
+            .zip(&triples)
+            .zip(opened.chunks(2))
+            .map(|(((a, _), t), de)| {
+                let (d, e) = (de[0], de[1]);
+                let mut share =
+                    t.c.wrapping_add(d.wrapping_mul(t.b))
+                        .wrapping_add(e.wrapping_mul(t.a));
+                if protocol.leader() {
+                    share = share.wrapping_add(d.wrapping_mul(e));
+                }
+                Self {
+                    share,
+                    bits: a.bits,
+                }
+            })
+            .collect();
+        Ok(products)
+    }
+
+    /// Returns this party's additive share of the value, least significant byte first.
+    pub fn get(_protocol: &mut ArithProtocol, share: &Self) -> Vec<u8> {
+        share.to_bytes()
+    }
+}
+
+/// Inputs every party's share as a separate boolean value, which is zero for all but its owner.
+fn summands<B: Backend>(arith: &ArithProtocol, gmw: &mut Protocol<B>, share: &[u8]) -> Vec<Nat<B>> {
+    let zero = vec![0u8; share.len()];
+    (0..arith.parties)
+        .map(|id| {
+            let input = if id == arith.my_id { share } else { &zero };
+            Nat::new(gmw, input)
+        })
+        .collect()
+}
+
+/// Converts an arithmetic share to a boolean `Nat` of the same width.
+pub fn a2b<B: Backend>(arith: &mut ArithProtocol, gmw: &mut Protocol<B>, share: &Arith) -> Nat<B> {
+    let summands = summands(arith, gmw, &share.to_bytes());
+    let mut sum = summands[0].clone();
+    for s in &summands[1..] {
+        sum = Nat::add(gmw, &sum, s);
+    }
+    sum
+}
+
+/// Converts an arithmetic share to a boolean two's complement `Int` of the same width.
+pub fn a2b_int<B: Backend>(
+    arith: &mut ArithProtocol,
+    gmw: &mut Protocol<B>,
+    share: &Arith,
+) -> Int<B> {
+    let bytes = share.to_bytes();
+    let zero = vec![0u8; bytes.len()];
+    let mut sum = Int::constant(gmw, &zero);
+    for id in 0..arith.parties {
+        let input = if id == arith.my_id { &bytes } else { &zero };
+        let mut summand = Int::new(gmw, input);
+        sum = Int::add(gmw, &mut sum, &mut summand);
+    }
+    sum
+}
+
+/// Converts a boolean `Nat` to an arithmetic share of the same width.
+///
+/// Every party masks the value with a random summand of its own, and the masked value is opened. The leader's
+/// share is the opened value less its mask, and every other party's share is its negated mask.
+pub fn b2a<B: Backend>(
+    arith: &mut ArithProtocol,
+    gmw: &mut Protocol<B>,
+    share: &Nat<B>,
+) -> Result<Arith> {
+    let width = Nat::width(gmw, share);
+    if width == 0 || width % 8 != 0 || width > 64 {
+        return Err(Error::Malformed(format!(
+            "cannot convert a {}-bit number to an arithmetic share",
+            width
+        )));
+    }
+    let len = width / 8;
+    let mask = Arith::from_bytes(&arith.peers.rng().gen::<u64>().to_le_bytes()[..len])?;
+
+    let mut masked = share.clone();
+    for s in summands(arith, gmw, &mask.to_bytes()) {
+        masked = Nat::add(gmw, &masked, &s);
+    }
+
+    let bits = util::to_bits(&Nat::get(gmw, &masked)?);
+    let opened = Arith::from_bytes(&util::from_bits(&arith.peers.open(&bits)?))?;
+
+    if arith.leader() {
+        Arith::sub(arith, &opened, &mask)
+    } else {
+        Ok(Arith::neg(arith, &mask))
+    }
+}
+
+/// Converts a boolean two's complement `Int` to an arithmetic share of the same width.
+pub fn b2a_int<B: Backend>(
+    arith: &mut ArithProtocol,
+    gmw: &mut Protocol<B>,
+    share: &mut Int<B>,
+) -> Result<Arith> {
+    let nat = Int::to_nat(gmw, share);
+    b2a(arith, gmw, &nat)
+}
+
+fn arith_share_send<Prg: Rng + CryptoRng, W: Write>(
+    prg: &mut Prg,
+    channels: &mut [&mut W],
+    clear: u64,
+) -> Result<()> {
+    let mut masked = clear;
+
+    for c in channels.iter_mut().skip(1) {
+        let share: u64 = prg.gen();
+        c.write_all(&share.to_le_bytes())?;
+        masked = masked.wrapping_sub(share);
+    }
+
+    channels[0].write_all(&masked.to_le_bytes())?;
+    Ok(())
+}
+
+fn arith_reveal_recv<R: Read>(channels: &mut [&mut R]) -> Result<u64> {
+    let mut ret = 0u64;
+
+    for c in channels {
+        let mut buf = [0u8; 8];
+        c.read_exact(&mut buf)?;
+        ret = ret.wrapping_add(u64::from_le_bytes(buf));
+    }
+
+    Ok(ret)
+}
+
+pub mod ffi {
+    use super::*;
+    use crate::error::{self, Status};
+    use crate::util::ffi::*;
+    use scuttlebutt::AesRng;
+    use std::ffi::CStr;
+
+    #[no_mangle]
+    pub unsafe extern "C" fn gmw_arith_protocol_new(
+        id: usize,
+        hosts: *const *const libc::c_char,
+        ports: *const u16,
+        len: usize,
+    ) -> *mut ArithProtocol {
+        let ret = (|| {
+            let hosts = c_to_vec(hosts, len)
+                .into_iter()
+                .map(|host_ptr| Ok(CStr::from_ptr(host_ptr).to_str()?.to_owned()))
+                .collect::<Result<_>>()?;
+            let ports = c_to_vec(ports, len);
+            ArithProtocol::new(id, hosts, ports)
+        })();
+        error::boxed(ret)
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn gmw_arith_protocol_drop(protocol: *mut ArithProtocol) {
+        Box::from_raw(protocol);
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn gmw_arith32_new(
+        protocol: *mut ArithProtocol,
+        share: u32,
+    ) -> *mut Arith {
+        error::boxed(Arith::new(&mut *protocol, &share.to_le_bytes()))
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn gmw_arith32_constant(
+        protocol: *mut ArithProtocol,
+        value: u32,
+    ) -> *mut Arith {
+        error::boxed(Arith::constant(&mut *protocol, &value.to_le_bytes()))
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn gmw_arith_add(
+        protocol: *mut ArithProtocol,
+        a: *mut Arith,
+        b: *mut Arith,
+    ) -> *mut Arith {
+        error::boxed(Arith::add(&mut *protocol, &*a, &*b))
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn gmw_arith_sub(
+        protocol: *mut ArithProtocol,
+        a: *mut Arith,
+        b: *mut Arith,
+    ) -> *mut Arith {
+        error::boxed(Arith::sub(&mut *protocol, &*a, &*b))
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn gmw_arith_mul(
+        protocol: *mut ArithProtocol,
+        a: *mut Arith,
+        b: *mut Arith,
+    ) -> *mut Arith {
+        error::boxed(Arith::mul(&mut *protocol, &*a, &*b))
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn gmw_arith32_get(
+        protocol: *mut ArithProtocol,
+        share: *mut Arith,
+        out: *mut u32,
+    ) -> Status {
+        let share = &*share;
+        let ret = if share.bits == 32 {
+            let bytes = Arith::get(&mut *protocol, share);
+            Ok(u32::from_le_bytes(bytes.try_into().unwrap()))
+        } else {
+            Err(Error::Malformed(format!(
+                "expected a 32-bit share, but it has {} bits",
+                share.bits
+            )))
+        };
+        error::status_out(ret, out)
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn gmw_arith_drop(share: *mut Arith) {
+        Box::from_raw(share);
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn gmw_arith_a2b(
+        arith: *mut ArithProtocol,
+        gmw: *mut Protocol,
+        share: *mut Arith,
+    ) -> *mut Nat {
+        let ret = a2b(&mut *arith, &mut *gmw, &*share);
+        Box::into_raw(Box::new(ret))
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn gmw_arith_a2b_int(
+        arith: *mut ArithProtocol,
+        gmw: *mut Protocol,
+        share: *mut Arith,
+    ) -> *mut Int {
+        let ret = a2b_int(&mut *arith, &mut *gmw, &*share);
+        Box::into_raw(Box::new(ret))
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn gmw_arith_b2a(
+        arith: *mut ArithProtocol,
+        gmw: *mut Protocol,
+        share: *mut Nat,
+    ) -> *mut Arith {
+        error::boxed(b2a(&mut *arith, &mut *gmw, &*share))
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn gmw_arith_b2a_int(
+        arith: *mut ArithProtocol,
+        gmw: *mut Protocol,
+        share: *mut Int,
+    ) -> *mut Arith {
+        error::boxed(b2a_int(&mut *arith, &mut *gmw, &mut *share))
+    }
+
+    // Convenience
+
+    #[no_mangle]
+    pub unsafe extern "C" fn gmw_arith_share_send32(
+        prg: *mut AesRng,
+        channels: *mut *mut Channel,
+        channels_len: usize,
+        clear: u32,
+    ) -> Status {
+        let channels: &mut [&mut Channel] =
+            std::mem::transmute(std::slice::from_raw_parts_mut(channels, channels_len));
+        error::status(arith_share_send(&mut *prg, channels, clear as u64))
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn gmw_arith_share_recv32(
+        channel: *mut Channel,
+        out: *mut u32,
+    ) -> Status {
+        let channel = &mut *channel;
+        let mut buf = [0u8; 8];
+        let ret = channel
+            .read_exact(&mut buf)
+            .map(|()| u64::from_le_bytes(buf) as u32);
+        error::status_out(ret.map_err(Into::into), out)
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn gmw_arith_reveal_send32(channel: *mut Channel, share: u32) -> Status {
+        let channel = &mut *channel;
+        let ret = channel.write_all(&(share as u64).to_le_bytes());
+        error::status(ret.map_err(Into::into))
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn gmw_arith_reveal_recv32(
+        channels: *mut *mut Channel,
+        channels_len: usize,
+        out: *mut u32,
+    ) -> Status {
+        let channels: &mut [&mut Channel] =
+            std::mem::transmute(std::slice::from_raw_parts_mut(channels, channels_len));
+        error::status_out(arith_reveal_recv(channels).map(|r| r as u32), out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::harness;
+
+    const A: u32 = 0xdead_beef;
+    const B: u32 = 123_456_789;
+
+    /// Additive shares of `value` for `my_id` out of `parties`, from a fixed pseudorandom split.
+    fn split(my_id: usize, parties: usize, value: u32) -> u32 {
+        let piece = |id: usize| (id as u32 + 1).wrapping_mul(0x9e37_79b9);
+        if my_id == 0 {
+            (1..parties).fold(value, |acc, id| acc.wrapping_sub(piece(id)))
+        } else {
+            piece(my_id)
+        }
+    }
+
+    fn combine(shares: &[u32]) -> u32 {
+        shares.iter().fold(0, |acc, s| acc.wrapping_add(*s))
+    }
+
+    #[test]
+    fn arithmetic() {
+        for parties in [2, 3] {
+            let shares = harness::run(parties, |my_id, channels| {
+                let p = &mut ArithProtocol::from_channels(my_id, channels);
+                let x = Arith::new(p, &split(my_id, parties, A).to_le_bytes()).unwrap();
+                let y = Arith::new(p, &split(my_id, parties, B).to_le_bytes()).unwrap();
+                let seven = Arith::constant(p, &7u32.to_le_bytes()).unwrap();
+
+                let sum = Arith::add(p, &x, &y).unwrap();
+                let diff = Arith::sub(p, &x, &seven).unwrap();
+                let prods = Arith::mul_many(p, &[x, sum], &[y, sum]).unwrap();
+                [sum, diff, prods[0], prods[1]]
+                    .iter()
+                    .map(|s| u32::from_le_bytes(Arith::get(p, s).try_into().unwrap()))
+                    .collect::<Vec<_>>()
+            });
+
+            let values: Vec<u32> = (0..4)
+                .map(|i| combine(&shares.iter().map(|s| s[i]).collect::<Vec<_>>()))
+                .collect();
+            let sum = A.wrapping_add(B);
+            assert_eq!(
+                values,
+                vec![
+                    sum,
+                    A.wrapping_sub(7),
+                    A.wrapping_mul(B),
+                    sum.wrapping_mul(sum)
+                ]
+            );
+        }
+    }
+
+    #[test]
+    fn conversions() {
+        for parties in [2, 3] {
+            let meshes = harness::mesh(parties)
+                .into_iter()
+                .zip(harness::mesh(parties))
+                .collect();
+            let shares = harness::run_with(meshes, |my_id, (arith, engine)| {
+                let arith = &mut ArithProtocol::from_channels(my_id, arith);
+                let gmw = &mut Protocol::with_backend(Native::from_channels(my_id, engine));
+
+                // Multiply arithmetically, then compare in boolean and convert back.
+                let x = Arith::new(arith, &split(my_id, parties, A).to_le_bytes()).unwrap();
+                let y = Arith::new(arith, &split(my_id, parties, B).to_le_bytes()).unwrap();
+                let prod = Arith::mul(arith, &x, &y).unwrap();
+                let prod_b = a2b(arith, gmw, &prod);
+                let y_b = a2b(arith, gmw, &y);
+                let lt = Nat::lt(gmw, &prod_b, &y_b);
+                let round_trip = b2a(arith, gmw, &prod_b).unwrap();
+
+                let prod_b = Nat::get(gmw, &prod_b).unwrap();
+                let prod_b = u32::from_le_bytes(prod_b.try_into().unwrap());
+                let lt = Bool::get(gmw, &lt).unwrap() as u32;
+                let round_trip =
+                    u32::from_le_bytes(Arith::get(arith, &round_trip).try_into().unwrap());
+                (prod_b, lt, round_trip)
+            });
+
+            let prod = A.wrapping_mul(B);
+            let xor = |f: fn(&(u32, u32, u32)) -> u32| shares.iter().fold(0, |acc, s| acc ^ f(s));
+            assert_eq!(xor(|s| s.0), prod);
+            assert_eq!(xor(|s| s.1), (prod < B) as u32);
+            assert_eq!(
+                combine(&shares.iter().map(|s| s.2).collect::<Vec<_>>()),
+                prod
+            );
+        }
+    }
+
+    #[test]
+    fn b2a_rejects_widths() {
+        let arith = &mut ArithProtocol::from_channels(0, vec![None, None]);
+        let gmw = &mut Protocol::with_backend(Plain::new(2));
+        for width in [0, 12, 128] {
+            let share = Nat::new_bits(gmw, &[0u8; 16], width);
+            assert!(matches!(b2a(arith, gmw, &share), Err(Error::Malformed(_))));
+        }
+    }
+
+    #[test]
+    fn rejects_widths() {
+        let p = &mut ArithProtocol::from_channels(0, vec![None, None]);
+        assert!(matches!(Arith::new(p, &[]), Err(Error::Malformed(_))));
+        assert!(matches!(
+            Arith::constant(p, &[0; 9]),
+            Err(Error::Malformed(_))
+        ));
+
+        let mut narrow = Arith::new(p, &[1, 2]).unwrap();
+        let wide = Arith::new(p, &[1, 2, 3, 4, 5, 6, 7, 8]).unwrap();
+        assert!(matches!(
+            Arith::add(p, &narrow, &wide),
+            Err(Error::Malformed(_))
+        ));
+        assert!(matches!(
+            Arith::sub(p, &wide, &narrow),
+            Err(Error::Malformed(_))
+        ));
+        assert!(matches!(
+            Arith::mul_many(p, &[narrow], &[wide]),
+            Err(Error::Malformed(_))
+        ));
+
+        let mut out = 0;
+        let status = unsafe { ffi::gmw_arith32_get(p, &mut narrow, &mut out) };
+        assert_eq!(status, crate::error::Status::Malformed);
+    }
+}
@@ -0,0 +1,328 @@
+use crate::config::{Config, Timeouts};
+use crate::error::{Error, Result};
+use crate::gmw::{byte_len, Bool, Int, Nat, Native, Protocol};
+use crate::native::{self, Peers};
+use crate::util::{self, AsyncChannel, BlockingChannel, Channel, Mesh};
+use rand::RngCore;
+use std::panic;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::task::{self, JoinError};
+
+/// A GMW protocol driven from async code, whose rounds of communication with the other parties are futures over
+/// `AsyncChannel`s, so that one runtime can serve many concurrent sessions without a thread per party.
+///
+/// Sharing inputs, reading shares and revealing outputs are futures. Reading a share evaluates the pending circuit
+/// one round of AND gates at a time, opening each round's masked values over the channels. Computing on shares
+/// only builds the circuit, so it goes through the usual `gmw` API on `protocol()`.
+///
+/// The exception is preprocessing: the oblivious transfers that generate triples only speak blocking I/O, so
+/// `preprocess`, and an evaluation that runs short of preprocessed triples, run them on tokio's blocking pool, each
+/// party holding a pool thread until its peers have done their part. Preprocessing ahead of time keeps the online
+/// phase off the pool.
+///
+/// Dropping one of the futures part-way leaves the parties out of step, after which the protocol should not be
+/// used further.
+pub struct AsyncProtocol {
+    my_id: usize,
+    protocol: Protocol<Native>,
+    /// The channels to the other parties, or `None` if they were lost to an interrupted preprocessing.
+    peers: Option<Peers>,
+}
+
+/// Resumes a panic from a blocking task in the caller.
+fn join<T>(result: std::result::Result<T, JoinError>) -> T {
+    result.unwrap_or_else(|e| panic::resume_unwind(e.into_panic()))
+}
+
+impl AsyncProtocol {
+    /// Uses already-established channels, where `channels[i]` connects to party `i` and this party's own entry
+    /// is `None`.
+    pub fn from_channels(my_id: usize, channels: Vec<Option<AsyncChannel>>) -> Self {
+        let parties = channels.len();
+        let channels = channels
+            .into_iter()
+            .map(|c| c.map(|c| Channel::Async(BlockingChannel::new(c))))
+            .collect();
+        Self {
+            my_id,
+            protocol: Protocol::with_backend(Native::detached(my_id, parties)),
+            peers: Some(Peers::new(channels)),
+        }
+    }
+
+    /// Connects to the other parties as `config` describes. Neither TLS nor read and write timeouts are
+    /// supported; wrap the futures in `tokio::time::timeout` instead.
+    pub async fn from_config(config: &Config) -> Result<Self> {
+        let untimed = Timeouts {
+            read_ms: 0,
+            write_ms: 0,
+            ..config.timeouts
+        };
+        if untimed != config.timeouts {
+            return Err(Error::Unsupported(
+                "read and write timeouts on async channels",
+            ));
+        }
+        let channels = Mesh::new(config.id, &config.hosts, &config.ports)
+            .options(config.timeouts.options())
+            .connect_async()
+            .await?;
+        let mut protocol = Self::from_channels(config.id, channels);
+        *protocol.peers()?.rng() = config.seed.rng(config.id);
+        Ok(protocol)
+    }
+
+    /// The number of parties in the protocol, including this one.
+    pub fn parties(&self) -> usize {
+        self.protocol.parties()
+    }
+
+    /// The protocol, for computing on shares. Reading a share through it fails with `Error::Unsupported` until
+    /// one of the `get_*` futures has evaluated it.
+    pub fn protocol(&mut self) -> &mut Protocol<Native> {
+        &mut self.protocol
+    }
+
+    fn peers(&mut self) -> Result<&mut Peers> {
+        self.peers.as_mut().ok_or(Error::Disconnected)
+    }
+
+    /// Generates `triples` multiplication triples and `bits` random bits on the blocking pool, as
+    /// `Protocol::preprocess` does. Every party must call this with the same counts.
+    pub async fn preprocess(&mut self, triples: usize, bits: usize) -> Result<()> {
+        let my_id = self.my_id;
+        let mut peers = self.peers.take().ok_or(Error::Disconnected)?;
+        let (peers, fresh) = join(
+            task::spawn_blocking(move || {
+                let fresh = native::preprocess(my_id, &mut peers, triples, bits);
+                (peers, fresh)
+            })
+            .await,
+        );
+        self.peers = Some(peers);
+        self.protocol.preprocessing().unwrap().append(fresh?);
+        Ok(())
+    }
+
+    /// Shares a bit held by `dealer`, who passes it as `clear` while the other parties pass `None`.
+    pub async fn share_bool(&mut self, dealer: usize, clear: Option<bool>) -> Result<Bool<Native>> {
+        let clear = clear.map(|b| [b as u8]);
+        let share = self.deal(dealer, clear.as_ref().map(|c| &c[..]), 1).await?;
+        Ok(Bool::new(&mut self.protocol, share[0] & 1 == 1))
+    }
+
+    /// Shares a `width`-bit natural number held by `dealer`, who passes it in `clear` as little-endian bytes
+    /// covering every bit, while the other parties pass `None`.
+    pub async fn share_nat(
+        &mut self,
+        dealer: usize,
+        clear: Option<&[u8]>,
+        width: usize,
+    ) -> Result<Nat<Native>> {
+        let share = self.deal(dealer, clear, width).await?;
+        Ok(Nat::new_bits(&mut self.protocol, &share, width))
+    }
+
+    /// Like `share_nat`, for a two's complement integer.
+    pub async fn share_int(
+        &mut self,
+        dealer: usize,
+        clear: Option<&[u8]>,
+        width: usize,
+    ) -> Result<Int<Native>> {
+        let share = self.deal(dealer, clear, width).await?;
+        Ok(Int::new_bits(&mut self.protocol, &share, width))
+    }
+
+    /// This party's share of a `width`-bit value from `dealer`, which sends every other party a random share and
+    /// keeps the value masked with all of them.
+    async fn deal(&mut self, dealer: usize, clear: Option<&[u8]>, width: usize) -> Result<Vec<u8>> {
+        let len = byte_len(width);
+        let my_id = self.my_id;
+        let peers = self.peers()?;
+
+        if my_id != dealer {
+            let channel = peers.async_channel(dealer).ok_or(Error::Disconnected)?;
+            let mut share = vec![0u8; len];
+            channel.read_exact(&mut share).await?;
+            return Ok(share);
+        }
+
+        let mut masked = match clear {
+            Some(clear) if clear.len() >= len => clear[..len].to_vec(),
+            _ => {
+                return Err(Error::Malformed(format!(
+                    "the dealing party must pass a value of at least {} bytes",
+                    len
+                )))
+            }
+        };
+        let others = peers.async_channels().count();
+        let mut shares = Vec::with_capacity(others);
+        for _ in 0..others {
+            let mut share = vec![0u8; len];
+            peers.rng().fill_bytes(&mut share);
+            util::xor_inplace(&mut masked, &share);
+            shares.push(share);
+        }
+        for (channel, share) in peers.async_channels().zip(&shares) {
+            channel.write_all(share).await?;
+            channel.flush().await?;
+        }
+        Ok(masked)
+    }
+
+    /// Evaluates every pending share, opening the masked values of each round over the channels.
+    async fn run(&mut self) -> Result<()> {
+        if !self.protocol.pending() {
+            return Ok(());
+        }
+
+        let missing = self.protocol.backend().missing_triples();
+        if missing > 0 {
+            self.preprocess(missing, 0).await?;
+        }
+
+        let mut rounds = self.protocol.backend_mut().rounds();
+        let peers = self.peers.as_mut().ok_or(Error::Disconnected)?;
+        while let Some(masked) = rounds.masked() {
+            let opened = peers.open_async(&masked).await?;
+            rounds.resume(&opened);
+        }
+        rounds.finish();
+        self.protocol.collect();
+        Ok(())
+    }
+
+    /// This party's share of `share`, as `Bool::get` returns it, evaluating the circuit first if needed.
+    pub async fn get_bool(&mut self, share: &Bool<Native>) -> Result<bool> {
+        self.run().await?;
+        Bool::get(&mut self.protocol, share)
+    }
+
+    /// This party's share of `share`, as `Nat::get` returns it, evaluating the circuit first if needed.
+    pub async fn get_nat(&mut self, share: &Nat<Native>) -> Result<Vec<u8>> {
+        self.run().await?;
+        Nat::get(&mut self.protocol, share)
+    }
+
+    /// This party's share of `share`, as `Int::get` returns it, evaluating the circuit first if needed.
+    pub async fn get_int(&mut self, share: &mut Int<Native>) -> Result<Vec<u8>> {
+        self.run().await?;
+        Int::get(&mut self.protocol, share)
+    }
+
+    /// Reveals the value of `share` to every party.
+    pub async fn reveal_bool(&mut self, share: &Bool<Native>) -> Result<bool> {
+        let share = self.get_bool(share).await?;
+        Ok(self.peers()?.open_async(&[share]).await?[0])
+    }
+
+    /// Reveals the value of `share` to every party, in little-endian bytes with any unused high bits zero.
+    pub async fn reveal_nat(&mut self, share: &Nat<Native>) -> Result<Vec<u8>> {
+        let share = self.get_nat(share).await?;
+        self.open(&share).await
+    }
+
+    /// Reveals the value of `share` to every party, in little-endian bytes with the sign bit repeated into any
+    /// unused high bits.
+    pub async fn reveal_int(&mut self, share: &mut Int<Native>) -> Result<Vec<u8>> {
+        let share = self.get_int(share).await?;
+        self.open(&share).await
+    }
+
+    async fn open(&mut self, share: &[u8]) -> Result<Vec<u8>> {
+        let opened = self.peers()?.open_async(&util::to_bits(share)).await?;
+        Ok(util::from_bits(&opened))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::harness;
+
+    fn runtime() -> tokio::runtime::Runtime {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn sessions_run_concurrently() {
+        let (a, b) = (1234u32, 5678u32);
+        let products = runtime().block_on(async {
+            // Several independent two-party sessions, all driven from one runtime thread.
+            let mut tasks = Vec::new();
+            for session in 0..4u32 {
+                let (left, right) = AsyncChannel::pair().unwrap();
+                for (my_id, channel) in [(0, left), (1, right)] {
+                    // Party 0 holds `a + session` and party 1 holds `b`.
+                    let input = if my_id == 0 { a + session } else { b };
+                    let mut channels = vec![None, None];
+                    channels[1 - my_id] = Some(channel);
+                    tasks.push(tokio::spawn(async move {
+                        let mut protocol = AsyncProtocol::from_channels(my_id, channels);
+                        // Every party must build the same circuit, so both share party 0's input first.
+                        let input = input.to_le_bytes();
+                        let mut x = Vec::new();
+                        for dealer in 0..2 {
+                            let clear = (dealer == my_id).then_some(&input[..]);
+                            x.push(protocol.share_nat(dealer, clear, 32).await?);
+                        }
+                        let prod = Nat::mul(protocol.protocol(), &x[0], &x[1]);
+                        protocol.reveal_nat(&prod).await
+                    }));
+                }
+            }
+            let mut products = Vec::new();
+            for task in tasks {
+                let bytes = task.await.unwrap().unwrap();
+                products.push(u32::from_le_bytes(bytes.try_into().unwrap()));
+            }
+            products
+        });
+        let expected: Vec<u32> = (0..4).flat_map(|s| [(a + s).wrapping_mul(b); 2]).collect();
+        assert_eq!(products, expected);
+    }
+
+    #[test]
+    fn preprocessed_parties_over_a_mesh() {
+        let parties = 3;
+        let hosts = vec!["127.0.0.1".to_owned(); parties];
+        let ports = harness::free_ports(parties);
+        let revealed = runtime().block_on(async {
+            let tasks: Vec<_> = (0..parties)
+                .map(|id| {
+                    let (hosts, ports) = (hosts.clone(), ports.clone());
+                    tokio::spawn(async move {
+                        let channels = Mesh::new(id, &hosts, &ports).connect_async().await?;
+                        let mut protocol = AsyncProtocol::from_channels(id, channels);
+                        protocol.preprocess(64, 0).await?;
+                        // Party 2 shares -10, and every party learns it and that it is negative.
+                        let input = (id == 2).then_some(&[0xf6][..]);
+                        let mut x = protocol.share_int(2, input, 8).await?;
+                        let mut zero = Int::constant(protocol.protocol(), &[0]);
+                        let negative = Int::lt(protocol.protocol(), &mut x, &mut zero);
+                        let value = protocol.reveal_int(&mut x).await?;
+                        let negative = protocol.reveal_bool(&negative).await?;
+                        let left = protocol.protocol().preprocessing().unwrap().triples();
+                        Ok::<_, Error>((value, negative, left))
+                    })
+                })
+                .collect();
+            let mut revealed = Vec::new();
+            for task in tasks {
+                revealed.push(task.await.unwrap().unwrap());
+            }
+            revealed
+        });
+        for (value, negative, left) in revealed {
+            assert_eq!(value, vec![0xf6]);
+            assert!(negative);
+            // The comparison ran on preprocessed triples, without generating more.
+            assert!(left < 64);
+        }
+    }
+}
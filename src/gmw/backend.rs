@@ -0,0 +1,93 @@
+use crate::config::{Config, Seed, Timeouts};
+use crate::error::{Error, Result};
+use crate::gmw::Preprocessing;
+
+/// An engine that executes GMW circuits on behalf of a `Protocol`.
+///
+/// Share handles (`Self::Bool`, `Self::Nat`) are expressions in the engine's current circuit. Nothing is
+/// evaluated until `run`, after which `bool_get` and `nat_get` return this party's share of each expression.
+/// `reset` then discards the circuit so that a new one can be built.
+///
+/// Engines and their share handles must be `Send`, so that a `Protocol` and its shares can move between threads.
+pub trait Backend: Sized + Send {
+    type Bool: Clone + std::fmt::Debug + Send;
+    type Nat: Clone + std::fmt::Debug + Send;
+
+    fn new(my_id: usize, hosts: &[String], ports: &[u16]) -> Result<Self>;
+
+    /// Creates this party's engine as `config` describes. By default only the parties' addresses are used, so
+    /// engines that do their own networking reject TLS, timeouts and a fixed seed rather than ignore them.
+    fn from_config(config: &Config) -> Result<Self> {
+        if config.tls.is_some() {
+            return Err(Error::Unsupported(
+                "this backend cannot secure its channels with TLS",
+            ));
+        }
+        if config.timeouts != Timeouts::default() {
+            return Err(Error::Unsupported(
+                "this backend cannot apply connection timeouts",
+            ));
+        }
+        if config.seed != Seed::Random {
+            return Err(Error::Unsupported("this backend cannot be seeded"));
+        }
+        Self::new(config.id, &config.hosts, &config.ports)
+    }
+
+    /// The number of parties in the protocol, including this one.
+    fn parties(&self) -> usize;
+
+    fn bool_new(&mut self, share: bool) -> Self::Bool;
+
+    fn bool_constant(&mut self, value: bool) -> Self::Bool;
+
+    fn bool_inv(&mut self, a: &Self::Bool) -> Self::Bool;
+
+    fn bool_xor(&mut self, a: &Self::Bool, b: &Self::Bool) -> Self::Bool;
+
+    fn bool_and(&mut self, a: &Self::Bool, b: &Self::Bool) -> Self::Bool;
+
+    fn bool_mux(&mut self, g: &Self::Bool, a: &Self::Bool, b: &Self::Bool) -> Self::Bool;
+
+    fn bool_get(&mut self, share: &Self::Bool) -> bool;
+
+    fn nat_new(&mut self, share: Vec<bool>) -> Self::Nat;
+
+    fn nat_constant(&mut self, value: Vec<bool>) -> Self::Nat;
+
+    fn nat_add(&mut self, a: &Self::Nat, b: &Self::Nat) -> Self::Nat;
+
+    fn nat_sub(&mut self, a: &Self::Nat, b: &Self::Nat) -> Self::Nat;
+
+    fn nat_mul(&mut self, a: &Self::Nat, b: &Self::Nat) -> Self::Nat;
+
+    fn nat_mux(&mut self, g: &Self::Bool, a: &Self::Nat, b: &Self::Nat) -> Self::Nat;
+
+    fn nat_eq(&mut self, a: &Self::Nat, b: &Self::Nat) -> Self::Bool;
+
+    fn nat_gt(&mut self, a: &Self::Nat, b: &Self::Nat) -> Self::Bool;
+
+    fn nat_get(&mut self, share: &Self::Nat) -> Vec<bool>;
+
+    /// The number of bits in `share`, which is known before the circuit is evaluated.
+    fn nat_width(&mut self, share: &Self::Nat) -> usize;
+
+    /// Splits `a` into its bits, least significant first, for the operations built from boolean circuits.
+    fn nat_bits(&mut self, a: &Self::Nat) -> Vec<Self::Bool>;
+
+    /// Joins bits, least significant first, into a natural number.
+    fn nat_from_bits(&mut self, bits: &[Self::Bool]) -> Self::Nat;
+
+    /// Generates `triples` multiplication triples and `bits` random bits ahead of time, to be consumed by later
+    /// circuits and by `Bool::random`. Engines that manage their own preprocessing may ignore this.
+    fn preprocess(&mut self, triples: usize, bits: usize) -> Result<()>;
+
+    /// The preprocessed material not yet consumed, or `None` if the engine does not expose it.
+    fn preprocessing(&mut self) -> Option<&mut Preprocessing>;
+
+    /// Evaluates the current circuit. If this fails, the engine's connections should be considered unusable.
+    fn run(&mut self) -> Result<()>;
+
+    /// Discards the evaluated circuit and starts a new one.
+    fn reset(&mut self);
+}
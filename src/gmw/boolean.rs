@@ -1,24 +1,42 @@
+use crate::error::Result;
 use crate::gmw::*;
-use crate::motion;
+use crate::util;
 use crate::util::Channel;
-use std::cell::RefCell;
-use std::rc::Rc;
+use std::sync::Arc;
+use std::sync::Mutex;
 
-#[derive(Debug, Clone)]
-pub enum CachedBool {
+pub enum CachedBool<B: Backend = DefaultBackend> {
     Value(bool),
-    Expr(motion::Bool),
+    Expr(B::Bool),
 }
 
-impl CachedBool {
-    fn into_expr(self, protocol: &mut Protocol) -> motion::Bool {
+impl<B: Backend> Clone for CachedBool<B> {
+    fn clone(&self) -> Self {
         match self {
-            CachedBool::Value(share) => motion::Bool::new(&mut protocol.party, share),
+            CachedBool::Value(share) => CachedBool::Value(*share),
+            CachedBool::Expr(e) => CachedBool::Expr(e.clone()),
+        }
+    }
+}
+
+impl<B: Backend> std::fmt::Debug for CachedBool<B> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CachedBool::Value(share) => f.debug_tuple("Value").field(share).finish(),
+            CachedBool::Expr(e) => f.debug_tuple("Expr").field(e).finish(),
+        }
+    }
+}
+
+impl<B: Backend> CachedBool<B> {
+    fn into_expr(self, protocol: &mut Protocol<B>) -> B::Bool {
+        match self {
+            CachedBool::Value(share) => protocol.backend.bool_new(share),
             CachedBool::Expr(e) => e,
         }
     }
 
-    fn value(&self, _protocol: &mut Protocol) -> Option<bool> {
+    fn value(&self, _protocol: &mut Protocol<B>) -> Option<bool> {
         match self {
             CachedBool::Value(share) => Some(*share),
             CachedBool::Expr(_) => None,
@@ -27,109 +45,166 @@ impl CachedBool {
 }
 
 /// A boolean GMW share.
-#[derive(Debug, Clone)]
-pub struct Bool {
-    repr: Rc<RefCell<CachedBool>>,
+pub struct Bool<B: Backend = DefaultBackend> {
+    repr: Arc<Mutex<CachedBool<B>>>,
 }
 
-impl Bool {
-    pub fn from_expr(protocol: &mut Protocol, expr: motion::Bool) -> Self {
-        let repr = Rc::new(RefCell::new(CachedBool::Expr(expr)));
+impl<B: Backend> Clone for Bool<B> {
+    fn clone(&self) -> Self {
+        Self {
+            repr: self.repr.clone(),
+        }
+    }
+}
+
+impl<B: Backend> std::fmt::Debug for Bool<B> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Bool").field("repr", &self.repr).finish()
+    }
+}
+
+impl<B: Backend> Bool<B> {
+    pub fn from_expr(protocol: &mut Protocol<B>, expr: B::Bool) -> Self {
+        let repr = Arc::new(Mutex::new(CachedBool::Expr(expr)));
         protocol.delayed.push(repr.clone());
         Self { repr }
     }
 
-    pub fn to_expr(protocol: &mut Protocol, share: &Self) -> motion::Bool {
-        (*share.repr).borrow().clone().into_expr(protocol)
+    pub fn to_expr(protocol: &mut Protocol<B>, share: &Self) -> B::Bool {
+        share.repr.lock().unwrap().clone().into_expr(protocol)
     }
 
-    pub fn new(protocol: &mut Protocol, share: bool) -> Self {
-        let expr = motion::Bool::new(&mut protocol.party, share);
+    pub fn new(protocol: &mut Protocol<B>, share: bool) -> Self {
+        let expr = protocol.backend.bool_new(share);
         Self::from_expr(protocol, expr)
     }
 
-    pub fn constant(protocol: &mut Protocol, value: bool) -> Self {
-        let expr = motion::Bool::constant(&mut protocol.party, value);
+    /// A uniformly random value unknown to every party, from a preprocessed random bit if one is available.
+    pub fn random(protocol: &mut Protocol<B>) -> Self {
+        let share = match protocol.preprocessing().and_then(|store| store.take_bit()) {
+            Some(share) => share,
+            None => rand::random(),
+        };
+        Self::new(protocol, share)
+    }
+
+    pub fn constant(protocol: &mut Protocol<B>, value: bool) -> Self {
+        let expr = protocol.backend.bool_constant(value);
         Self::from_expr(protocol, expr)
     }
 
-    pub fn xor(protocol: &mut Protocol, a: &Self, b: &Self) -> Self {
+    pub fn xor(protocol: &mut Protocol<B>, a: &Self, b: &Self) -> Self {
         let expr_a = Self::to_expr(protocol, a);
         let expr_b = Self::to_expr(protocol, b);
-        let expr = expr_a.xor(&expr_b);
+        let expr = protocol.backend.bool_xor(&expr_a, &expr_b);
         Self::from_expr(protocol, expr)
     }
 
-    pub fn or(protocol: &mut Protocol, a: &Self, b: &Self) -> Self {
+    pub fn or(protocol: &mut Protocol<B>, a: &Self, b: &Self) -> Self {
         let ab = Self::and(protocol, a, b);
         let axb = Self::xor(protocol, a, b);
         Self::xor(protocol, &ab, &axb)
     }
 
-    pub fn and(protocol: &mut Protocol, a: &Self, b: &Self) -> Self {
+    pub fn and(protocol: &mut Protocol<B>, a: &Self, b: &Self) -> Self {
         let expr_a = Self::to_expr(protocol, a);
         let expr_b = Self::to_expr(protocol, b);
-        let expr = expr_a.and(&expr_b);
+        let expr = protocol.backend.bool_and(&expr_a, &expr_b);
         Self::from_expr(protocol, expr)
     }
 
-    pub fn not(protocol: &mut Protocol, share: &Self) -> Self {
+    pub fn not(protocol: &mut Protocol<B>, share: &Self) -> Self {
         let expr_share = Self::to_expr(protocol, share);
-        let expr = expr_share.inv();
+        let expr = protocol.backend.bool_inv(&expr_share);
         Self::from_expr(protocol, expr)
     }
 
-    pub fn mux(protocol: &mut Protocol, g: &Self, a: &Self, b: &Self) -> Self {
+    pub fn mux(protocol: &mut Protocol<B>, g: &Self, a: &Self, b: &Self) -> Self {
         let axb = Self::xor(protocol, a, b);
         let t = Self::and(protocol, g, &axb);
         Self::xor(protocol, b, &t)
     }
 
-    pub fn eq(protocol: &mut Protocol, a: &Self, b: &Self) -> Self {
+    pub fn eq(protocol: &mut Protocol<B>, a: &Self, b: &Self) -> Self {
         let axb = Self::xor(protocol, a, b);
         Self::not(protocol, &axb)
     }
 
-    pub fn get(protocol: &mut Protocol, value: &Self) -> bool {
-        let cached = (*value.repr).borrow().value(protocol);
+    pub fn get(protocol: &mut Protocol<B>, value: &Self) -> Result<bool> {
+        let cached = value.repr.lock().unwrap().value(protocol);
         match cached {
             None => {
-                protocol.run();
-                (*value.repr).borrow().value(protocol).unwrap()
+                protocol.run()?;
+                Ok(value.repr.lock().unwrap().value(protocol).unwrap())
             }
-            Some(share) => share,
+            Some(share) => Ok(share),
         }
     }
 
-    pub fn into_raw(this: Self) -> *const RefCell<CachedBool> {
-        Rc::into_raw(this.repr)
+    pub fn into_raw(this: Self) -> *const Mutex<CachedBool<B>> {
+        Arc::into_raw(this.repr)
     }
 
-    pub unsafe fn from_raw(ptr: *const RefCell<CachedBool>) -> Self {
+    pub unsafe fn from_raw(ptr: *const Mutex<CachedBool<B>>) -> Self {
         Self {
-            repr: Rc::from_raw(ptr),
+            repr: Arc::from_raw(ptr),
         }
     }
 }
 
+impl<B: Backend> util::Gates for Protocol<B> {
+    type Bool = Bool<B>;
+
+    fn constant(&mut self, value: bool) -> Bool<B> {
+        Bool::constant(self, value)
+    }
+
+    fn xor(&mut self, a: &Bool<B>, b: &Bool<B>) -> Bool<B> {
+        Bool::xor(self, a, b)
+    }
+
+    fn and(&mut self, a: &Bool<B>, b: &Bool<B>) -> Bool<B> {
+        Bool::and(self, a, b)
+    }
+
+    fn not(&mut self, a: &Bool<B>) -> Bool<B> {
+        Bool::not(self, a)
+    }
+
+    fn or(&mut self, a: &Bool<B>, b: &Bool<B>) -> Bool<B> {
+        Bool::or(self, a, b)
+    }
+
+    fn mux(&mut self, g: &Bool<B>, a: &Bool<B>, b: &Bool<B>) -> Bool<B> {
+        Bool::mux(self, g, a, b)
+    }
+}
+
 pub mod ffi {
     use super::*;
+    use crate::error::{self, Status};
     use scuttlebutt::AesRng;
 
     #[no_mangle]
     pub unsafe extern "C" fn gmw_bool_new(
         protocol: *mut Protocol,
         share: bool,
-    ) -> *const RefCell<CachedBool> {
+    ) -> *const Mutex<CachedBool> {
         let ret = Bool::new(&mut *protocol, share);
         Bool::into_raw(ret)
     }
 
+    #[no_mangle]
+    pub unsafe extern "C" fn gmw_bool_random(protocol: *mut Protocol) -> *const Mutex<CachedBool> {
+        let ret = Bool::random(&mut *protocol);
+        Bool::into_raw(ret)
+    }
+
     #[no_mangle]
     pub unsafe extern "C" fn gmw_bool_constant(
         protocol: *mut Protocol,
         value: bool,
-    ) -> *const RefCell<CachedBool> {
+    ) -> *const Mutex<CachedBool> {
         let ret = Bool::constant(&mut *protocol, value);
         Bool::into_raw(ret)
     }
@@ -137,20 +212,21 @@ pub mod ffi {
     #[no_mangle]
     pub unsafe extern "C" fn gmw_bool_get(
         protocol: *mut Protocol,
-        share_raw: *const RefCell<CachedBool>,
-    ) -> bool {
+        share_raw: *const Mutex<CachedBool>,
+        out: *mut bool,
+    ) -> Status {
         let share = Bool::from_raw(share_raw);
         let ret = Bool::get(&mut *protocol, &share);
         assert_eq!(share_raw, Bool::into_raw(share));
-        ret
+        error::status_out(ret, out)
     }
 
     #[no_mangle]
     pub unsafe extern "C" fn gmw_bool_xor(
         protocol: *mut Protocol,
-        a_raw: *const RefCell<CachedBool>,
-        b_raw: *const RefCell<CachedBool>,
-    ) -> *const RefCell<CachedBool> {
+        a_raw: *const Mutex<CachedBool>,
+        b_raw: *const Mutex<CachedBool>,
+    ) -> *const Mutex<CachedBool> {
         let a = Bool::from_raw(a_raw);
         let b = Bool::from_raw(b_raw);
         let ret = Bool::xor(&mut *protocol, &a, &b);
@@ -162,9 +238,9 @@ pub mod ffi {
     #[no_mangle]
     pub unsafe extern "C" fn gmw_bool_and(
         protocol: *mut Protocol,
-        a_raw: *const RefCell<CachedBool>,
-        b_raw: *const RefCell<CachedBool>,
-    ) -> *const RefCell<CachedBool> {
+        a_raw: *const Mutex<CachedBool>,
+        b_raw: *const Mutex<CachedBool>,
+    ) -> *const Mutex<CachedBool> {
         let a = Bool::from_raw(a_raw);
         let b = Bool::from_raw(b_raw);
         let ret = Bool::and(&mut *protocol, &a, &b);
@@ -176,9 +252,9 @@ pub mod ffi {
     #[no_mangle]
     pub unsafe extern "C" fn gmw_bool_or(
         protocol: *mut Protocol,
-        a_raw: *const RefCell<CachedBool>,
-        b_raw: *const RefCell<CachedBool>,
-    ) -> *const RefCell<CachedBool> {
+        a_raw: *const Mutex<CachedBool>,
+        b_raw: *const Mutex<CachedBool>,
+    ) -> *const Mutex<CachedBool> {
         let a = Bool::from_raw(a_raw);
         let b = Bool::from_raw(b_raw);
         let ret = Bool::or(&mut *protocol, &a, &b);
@@ -190,10 +266,10 @@ pub mod ffi {
     #[no_mangle]
     pub unsafe extern "C" fn gmw_bool_mux(
         protocol: *mut Protocol,
-        g_raw: *const RefCell<CachedBool>,
-        a_raw: *const RefCell<CachedBool>,
-        b_raw: *const RefCell<CachedBool>,
-    ) -> *const RefCell<CachedBool> {
+        g_raw: *const Mutex<CachedBool>,
+        a_raw: *const Mutex<CachedBool>,
+        b_raw: *const Mutex<CachedBool>,
+    ) -> *const Mutex<CachedBool> {
         let g = Bool::from_raw(g_raw);
         let a = Bool::from_raw(a_raw);
         let b = Bool::from_raw(b_raw);
@@ -205,7 +281,7 @@ pub mod ffi {
     }
 
     #[no_mangle]
-    pub unsafe extern "C" fn gmw_bool_drop(share: *const RefCell<CachedBool>) {
+    pub unsafe extern "C" fn gmw_bool_drop(share: *const Mutex<CachedBool>) {
         Bool::from_raw(share);
     }
 
@@ -217,35 +293,36 @@ pub mod ffi {
         channels: *mut *mut Channel,
         channels_len: usize,
         clear: bool,
-    ) {
+    ) -> Status {
         let prg = &mut *prg;
         let channels: &mut [&mut Channel] =
             std::mem::transmute(std::slice::from_raw_parts_mut(channels, channels_len));
-        share_send_bool(prg, channels, clear)
+        error::status(share_send_bool(prg, channels, clear))
     }
 
     #[no_mangle]
-    pub unsafe extern "C" fn gmw_share_recv_bool(channel: *mut Channel) -> bool {
+    pub unsafe extern "C" fn gmw_share_recv_bool(channel: *mut Channel, out: *mut bool) -> Status {
         let channel = &mut *channel;
         let mut buf = [0u8; 1];
-        channel.read_exact(&mut buf).expect("TODO");
-        buf[0] != 0
+        let ret = channel.read_exact(&mut buf).map(|()| buf[0] != 0);
+        error::status_out(ret.map_err(Into::into), out)
     }
 
     #[no_mangle]
-    pub unsafe extern "C" fn gmw_reveal_send_bool(channel: *mut Channel, share: bool) {
+    pub unsafe extern "C" fn gmw_reveal_send_bool(channel: *mut Channel, share: bool) -> Status {
         let channel = &mut *channel;
         let buf = [share as u8; 1];
-        channel.write_all(&buf).expect("TODO")
+        error::status(channel.write_all(&buf).map_err(Into::into))
     }
 
     #[no_mangle]
     pub unsafe extern "C" fn gmw_reveal_recv_bool(
         channels: *mut *mut Channel,
         channels_len: usize,
-    ) -> bool {
+        out: *mut bool,
+    ) -> Status {
         let channels: &mut [&mut Channel] =
             std::mem::transmute(std::slice::from_raw_parts_mut(channels, channels_len));
-        reveal_recv_bool(channels)
+        error::status_out(reveal_recv_bool(channels), out)
     }
 }
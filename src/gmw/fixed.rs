@@ -0,0 +1,506 @@
+//! Signed fixed-point numbers, as `Int`s scaled by a power of two.
+//!
+//! A `Fixed` of `width` bits with `frac` fractional bits represents `x / 2^frac` by the `width`-bit two's
+//! complement integer `x`, so it holds `width - frac` integer bits, including the sign. Addition and subtraction
+//! are those of `Int`. Multiplication and division work at double width and then rescale.
+
+use std::io::{Read, Write};
+
+use crate::error::{Error, Result};
+use crate::gmw::*;
+use crate::util::Channel;
+
+/// How a product is rescaled to the fraction bits of its factors.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rounding {
+    /// Drops the low bits, rounding towards negative infinity.
+    Floor = 0,
+    /// Rounds to the nearest value, with ties rounding up.
+    Nearest = 1,
+}
+
+pub struct Fixed<B: Backend = DefaultBackend> {
+    value: Int<B>,
+    width: usize,
+    frac: usize,
+}
+
+impl<B: Backend> Clone for Fixed<B> {
+    fn clone(&self) -> Self {
+        Self {
+            value: self.value.clone(),
+            width: self.width,
+            frac: self.frac,
+        }
+    }
+}
+
+/// The little-endian bytes of `value` scaled by `2^frac` and rounded, for a `width`-bit `Fixed`. `width` may be at
+/// most 128, and values out of range wrap around.
+pub fn encode_fixed(value: f64, width: usize, frac: usize) -> Vec<u8> {
+    debug_assert!(width <= 128);
+    let scaled = (value * 2f64.powi(frac as i32)).round() as i128;
+    scaled.to_le_bytes()[..byte_len(width)].to_vec()
+}
+
+/// The value of the `width`-bit fixed-point number in the little-endian `bytes`.
+pub fn decode_fixed(bytes: &[u8], width: usize, frac: usize) -> f64 {
+    debug_assert!(width <= 128);
+    let mut buf = [0u8; 16];
+    buf[..bytes.len()].copy_from_slice(bytes);
+    let shift = 128 - width as u32;
+    let scaled = (i128::from_le_bytes(buf) << shift) >> shift;
+    scaled as f64 / 2f64.powi(frac as i32)
+}
+
+impl<B: Backend> Fixed<B> {
+    /// A share of a `width`-bit number with `frac` fractional bits, given as the little-endian bytes of this
+    /// party's share of the scaled integer.
+    pub fn new(protocol: &mut Protocol<B>, share: &[u8], width: usize, frac: usize) -> Self {
+        debug_assert!(frac <= width);
+        Self {
+            value: Int::new_bits(protocol, share, width),
+            width,
+            frac,
+        }
+    }
+
+    pub fn constant(protocol: &mut Protocol<B>, value: f64, width: usize, frac: usize) -> Self {
+        debug_assert!(frac <= width);
+        Self {
+            value: Int::constant_bits(protocol, &encode_fixed(value, width, frac), width),
+            width,
+            frac,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn frac(&self) -> usize {
+        self.frac
+    }
+
+    fn with_value(&self, value: Int<B>) -> Self {
+        Self {
+            value,
+            width: self.width,
+            frac: self.frac,
+        }
+    }
+
+    fn check(a: &Self, b: &Self) {
+        debug_assert_eq!((a.width, a.frac), (b.width, b.frac));
+    }
+
+    pub fn add(protocol: &mut Protocol<B>, a: &Self, b: &Self) -> Self {
+        Self::check(a, b);
+        let (mut x, mut y) = (a.value.clone(), b.value.clone());
+        a.with_value(Int::add(protocol, &mut x, &mut y))
+    }
+
+    pub fn sub(protocol: &mut Protocol<B>, a: &Self, b: &Self) -> Self {
+        Self::check(a, b);
+        let (mut x, mut y) = (a.value.clone(), b.value.clone());
+        a.with_value(Int::sub(protocol, &mut x, &mut y))
+    }
+
+    pub fn neg(protocol: &mut Protocol<B>, a: &Self) -> Self {
+        let mut x = a.value.clone();
+        a.with_value(Int::neg(protocol, &mut x))
+    }
+
+    /// Multiplies at double width, so that only the rescaling loses precision. Products out of range wrap around.
+    pub fn mul(protocol: &mut Protocol<B>, a: &Self, b: &Self, rounding: Rounding) -> Self {
+        Self::check(a, b);
+        let wide = 2 * a.width;
+        let mut x = Int::sign_extend(protocol, &mut a.value.clone(), wide);
+        let mut y = Int::sign_extend(protocol, &mut b.value.clone(), wide);
+        let mut product = Int::mul(protocol, &mut x, &mut y);
+        if rounding == Rounding::Nearest && a.frac > 0 {
+            let mut bytes = vec![0u8; byte_len(wide)];
+            bytes[(a.frac - 1) / 8] = 1 << ((a.frac - 1) % 8);
+            let mut half = Int::constant_bits(protocol, &bytes, wide);
+            product = Int::add(protocol, &mut product, &mut half);
+        }
+        let mut scaled = Int::shr(protocol, &mut product, a.frac);
+        a.with_value(Int::truncate(protocol, &mut scaled, a.width))
+    }
+
+    /// Divides the values, rounding towards zero. Quotients out of range wrap around, and the quotient of
+    /// dividing by zero is unspecified.
+    pub fn div(protocol: &mut Protocol<B>, a: &Self, b: &Self) -> Self {
+        Self::check(a, b);
+        let wide = a.width + a.frac;
+        let mut x = Int::sign_extend(protocol, &mut a.value.clone(), wide);
+        let mut x = Int::shl(protocol, &mut x, a.frac);
+        let mut y = Int::sign_extend(protocol, &mut b.value.clone(), wide);
+        let mut quot = Int::div(protocol, &mut x, &mut y);
+        a.with_value(Int::truncate(protocol, &mut quot, a.width))
+    }
+
+    pub fn mux(protocol: &mut Protocol<B>, g: &Bool<B>, a: &Self, b: &Self) -> Self {
+        Self::check(a, b);
+        let (mut x, mut y) = (a.value.clone(), b.value.clone());
+        a.with_value(Int::mux(protocol, &mut g.clone(), &mut x, &mut y))
+    }
+
+    pub fn eq(protocol: &mut Protocol<B>, a: &Self, b: &Self) -> Bool<B> {
+        Self::check(a, b);
+        Int::eq(protocol, &mut a.value.clone(), &mut b.value.clone())
+    }
+
+    pub fn lt(protocol: &mut Protocol<B>, a: &Self, b: &Self) -> Bool<B> {
+        Self::check(a, b);
+        Int::lt(protocol, &mut a.value.clone(), &mut b.value.clone())
+    }
+
+    pub fn lte(protocol: &mut Protocol<B>, a: &Self, b: &Self) -> Bool<B> {
+        Self::check(a, b);
+        Int::lte(protocol, &mut a.value.clone(), &mut b.value.clone())
+    }
+
+    pub fn gt(protocol: &mut Protocol<B>, a: &Self, b: &Self) -> Bool<B> {
+        Self::lt(protocol, b, a)
+    }
+
+    pub fn gte(protocol: &mut Protocol<B>, a: &Self, b: &Self) -> Bool<B> {
+        Self::lte(protocol, b, a)
+    }
+
+    /// This party's share of the scaled integer, as `Int::get` returns it.
+    pub fn get(protocol: &mut Protocol<B>, share: &Self) -> Result<Vec<u8>> {
+        Int::get(protocol, &mut share.value.clone())
+    }
+}
+
+pub mod ffi {
+    use super::*;
+    use crate::error::{self, Status};
+    use scuttlebutt::AesRng;
+
+    /// The FFI passes shares as `int64_t`, so it supports widths up to 64 bits.
+    fn check_ffi_width(width: usize, frac: usize) -> Result<()> {
+        if width == 0 || width > 64 || frac > width {
+            return Err(Error::Malformed(format!(
+                "unsupported fixed-point format of {} bits with {} fractional bits",
+                width, frac
+            )));
+        }
+        Ok(())
+    }
+
+    /// Sign-extends the `width`-bit little-endian `bytes` to an `i64`.
+    fn to_i64(bytes: &[u8], width: usize) -> i64 {
+        let mut buf = [0u8; 8];
+        buf[..bytes.len()].copy_from_slice(bytes);
+        let shift = 64 - width as u32;
+        (i64::from_le_bytes(buf) << shift) >> shift
+    }
+
+    /// A share of a `width`-bit number with `frac` fractional bits, given as this party's share of the scaled
+    /// integer. Returns null if the format is unsupported.
+    #[no_mangle]
+    pub unsafe extern "C" fn gmw_fixed_new(
+        protocol: *mut Protocol,
+        share: i64,
+        width: usize,
+        frac: usize,
+    ) -> *mut Fixed {
+        error::boxed(
+            check_ffi_width(width, frac)
+                .map(|()| Fixed::new(&mut *protocol, &share.to_le_bytes(), width, frac)),
+        )
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn gmw_fixed_constant(
+        protocol: *mut Protocol,
+        value: f64,
+        width: usize,
+        frac: usize,
+    ) -> *mut Fixed {
+        error::boxed(
+            check_ffi_width(width, frac)
+                .map(|()| Fixed::constant(&mut *protocol, value, width, frac)),
+        )
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn gmw_fixed_add(
+        protocol: *mut Protocol,
+        a: *mut Fixed,
+        b: *mut Fixed,
+    ) -> *mut Fixed {
+        let ret = Fixed::add(&mut *protocol, &*a, &*b);
+        Box::into_raw(Box::new(ret))
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn gmw_fixed_sub(
+        protocol: *mut Protocol,
+        a: *mut Fixed,
+        b: *mut Fixed,
+    ) -> *mut Fixed {
+        let ret = Fixed::sub(&mut *protocol, &*a, &*b);
+        Box::into_raw(Box::new(ret))
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn gmw_fixed_neg(protocol: *mut Protocol, a: *mut Fixed) -> *mut Fixed {
+        let ret = Fixed::neg(&mut *protocol, &*a);
+        Box::into_raw(Box::new(ret))
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn gmw_fixed_mul(
+        protocol: *mut Protocol,
+        a: *mut Fixed,
+        b: *mut Fixed,
+        rounding: Rounding,
+    ) -> *mut Fixed {
+        let ret = Fixed::mul(&mut *protocol, &*a, &*b, rounding);
+        Box::into_raw(Box::new(ret))
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn gmw_fixed_div(
+        protocol: *mut Protocol,
+        a: *mut Fixed,
+        b: *mut Fixed,
+    ) -> *mut Fixed {
+        let ret = Fixed::div(&mut *protocol, &*a, &*b);
+        Box::into_raw(Box::new(ret))
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn gmw_fixed_mux(
+        protocol: *mut Protocol,
+        guard_raw: *const Mutex<CachedBool>,
+        t: *mut Fixed,
+        f: *mut Fixed,
+    ) -> *mut Fixed {
+        let guard = Bool::from_raw(guard_raw);
+        let ret = Fixed::mux(&mut *protocol, &guard, &*t, &*f);
+        Bool::into_raw(guard);
+        Box::into_raw(Box::new(ret))
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn gmw_fixed_eq(
+        protocol: *mut Protocol,
+        a: *mut Fixed,
+        b: *mut Fixed,
+    ) -> *const Mutex<CachedBool> {
+        let ret = Fixed::eq(&mut *protocol, &*a, &*b);
+        Bool::into_raw(ret)
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn gmw_fixed_lt(
+        protocol: *mut Protocol,
+        a: *mut Fixed,
+        b: *mut Fixed,
+    ) -> *const Mutex<CachedBool> {
+        let ret = Fixed::lt(&mut *protocol, &*a, &*b);
+        Bool::into_raw(ret)
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn gmw_fixed_lte(
+        protocol: *mut Protocol,
+        a: *mut Fixed,
+        b: *mut Fixed,
+    ) -> *const Mutex<CachedBool> {
+        let ret = Fixed::lte(&mut *protocol, &*a, &*b);
+        Bool::into_raw(ret)
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn gmw_fixed_gt(
+        protocol: *mut Protocol,
+        a: *mut Fixed,
+        b: *mut Fixed,
+    ) -> *const Mutex<CachedBool> {
+        let ret = Fixed::gt(&mut *protocol, &*a, &*b);
+        Bool::into_raw(ret)
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn gmw_fixed_gte(
+        protocol: *mut Protocol,
+        a: *mut Fixed,
+        b: *mut Fixed,
+    ) -> *const Mutex<CachedBool> {
+        let ret = Fixed::gte(&mut *protocol, &*a, &*b);
+        Bool::into_raw(ret)
+    }
+
+    /// Writes this party's share of the scaled integer to `out`, sign-extended to 64 bits.
+    #[no_mangle]
+    pub unsafe extern "C" fn gmw_fixed_get(
+        protocol: *mut Protocol,
+        share: *mut Fixed,
+        out: *mut i64,
+    ) -> Status {
+        let share = &*share;
+        let ret = Fixed::get(&mut *protocol, share).map(|bytes| to_i64(&bytes, share.width));
+        error::status_out(ret, out)
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn gmw_fixed_drop(share: *mut Fixed) {
+        drop(Box::from_raw(share));
+    }
+
+    // Convenience
+
+    /// Encodes `clear` in the given format and sends a share of it to each party.
+    #[no_mangle]
+    pub unsafe extern "C" fn gmw_share_send_fixed(
+        prg: *mut AesRng,
+        channels: *mut *mut Channel,
+        channels_len: usize,
+        clear: f64,
+        width: usize,
+        frac: usize,
+    ) -> Status {
+        let channels: &mut [&mut Channel] =
+            std::mem::transmute(std::slice::from_raw_parts_mut(channels, channels_len));
+        let ret = check_ffi_width(width, frac)
+            .and_then(|()| share_send(&mut *prg, channels, &encode_fixed(clear, width, frac)));
+        error::status(ret)
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn gmw_share_recv_fixed(
+        channel: *mut Channel,
+        out: *mut i64,
+        width: usize,
+    ) -> Status {
+        let ret = check_ffi_width(width, 0).and_then(|()| {
+            let mut buf = vec![0u8; byte_len(width)];
+            (&mut *channel).read_exact(&mut buf)?;
+            Ok(to_i64(&buf, width))
+        });
+        error::status_out(ret, out)
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn gmw_reveal_send_fixed(
+        channel: *mut Channel,
+        share: i64,
+        width: usize,
+    ) -> Status {
+        let ret = check_ffi_width(width, 0).and_then(|()| {
+            let channel = &mut *channel;
+            channel.write_all(&share.to_le_bytes()[..byte_len(width)])?;
+            Ok(())
+        });
+        error::status(ret)
+    }
+
+    /// Combines every party's share into the value they represent.
+    #[no_mangle]
+    pub unsafe extern "C" fn gmw_reveal_recv_fixed(
+        channels: *mut *mut Channel,
+        channels_len: usize,
+        out: *mut f64,
+        width: usize,
+        frac: usize,
+    ) -> Status {
+        let channels: &mut [&mut Channel] =
+            std::mem::transmute(std::slice::from_raw_parts_mut(channels, channels_len));
+        let ret = check_ffi_width(width, frac).and_then(|()| {
+            let mut buf = vec![0u8; byte_len(width)];
+            reveal_recv(channels, &mut buf)?;
+            Ok(decode_fixed(&buf, width, frac))
+        });
+        error::status_out(ret, out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WIDTH: usize = 32;
+    const FRAC: usize = 12;
+
+    fn fixed(protocol: &mut Protocol<Plain>, value: f64) -> Fixed<Plain> {
+        Fixed::new(protocol, &encode_fixed(value, WIDTH, FRAC), WIDTH, FRAC)
+    }
+
+    fn value(protocol: &mut Protocol<Plain>, share: Fixed<Plain>) -> f64 {
+        decode_fixed(&Fixed::get(protocol, &share).unwrap(), WIDTH, FRAC)
+    }
+
+    #[test]
+    fn encoding() {
+        for v in [0.0, 1.5, -1.5, 1234.25, -0.000244140625] {
+            assert_eq!(decode_fixed(&encode_fixed(v, WIDTH, FRAC), WIDTH, FRAC), v);
+        }
+        // Values are rounded to the nearest multiple of 2^-frac.
+        assert_eq!(decode_fixed(&encode_fixed(0.3, 8, 2), 8, 2), 0.25);
+    }
+
+    #[test]
+    fn arithmetic() {
+        let p = &mut Protocol::with_backend(Plain::new(2));
+        let samples = [0.0, 1.0, -1.0, 2.5, -3.75, 100.125, -0.5];
+        let ulp = 1.0 / (1 << FRAC) as f64;
+        for a in samples {
+            for b in samples {
+                let (x, y) = (fixed(p, a), fixed(p, b));
+                // These samples' products are exact, so both roundings agree.
+                let results = [
+                    (Fixed::add(p, &x, &y), a + b),
+                    (Fixed::sub(p, &x, &y), a - b),
+                    (Fixed::neg(p, &x), -a),
+                    (Fixed::mul(p, &x, &y, Rounding::Floor), a * b),
+                    (Fixed::mul(p, &x, &y, Rounding::Nearest), a * b),
+                ];
+                for (share, expected) in results {
+                    assert_eq!(value(p, share), expected, "{} {}", a, b);
+                }
+                if b != 0.0 {
+                    let quot = Fixed::div(p, &x, &y);
+                    let quot = value(p, quot);
+                    assert!((quot - a / b).abs() < ulp, "{} / {} = {}", a, b, quot);
+                }
+
+                let flags = [
+                    (Fixed::eq(p, &x, &y), a == b),
+                    (Fixed::lt(p, &x, &y), a < b),
+                    (Fixed::lte(p, &x, &y), a <= b),
+                    (Fixed::gt(p, &x, &y), a > b),
+                    (Fixed::gte(p, &x, &y), a >= b),
+                ];
+                for (share, expected) in flags {
+                    assert_eq!(Bool::get(p, &share).unwrap(), expected, "{} {}", a, b);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn rounding() {
+        let p = &mut Protocol::with_backend(Plain::new(2));
+        let ulp = 1.0 / (1 << FRAC) as f64;
+        // The exact products are 3/4 of the way between two representable values.
+        let x = fixed(p, 3.0 * ulp);
+        let y = Fixed::constant(p, 0.25, WIDTH, FRAC);
+        let neg = Fixed::neg(p, &x);
+        let results = [
+            (Fixed::mul(p, &x, &y, Rounding::Floor), 0.0),
+            (Fixed::mul(p, &x, &y, Rounding::Nearest), ulp),
+            (Fixed::mul(p, &neg, &y, Rounding::Floor), -ulp),
+            (Fixed::mul(p, &neg, &y, Rounding::Nearest), -ulp),
+        ];
+        for (share, expected) in results {
+            assert_eq!(value(p, share), expected);
+        }
+    }
+}
@@ -1,27 +1,119 @@
 use rand::{CryptoRng, Rng};
 use std::io::{Read, Write};
 
+use crate::error::Result;
 use crate::gmw::Bool;
 use crate::gmw::Protocol;
 use crate::gmw::*;
 use crate::util;
 use crate::util::Channel;
 
-#[derive(Clone)]
-pub struct Int {
-    repr: Vec<Bool>,
+/// Packs `bits` into whole bytes, repeating the sign bit into the unused high bits.
+fn sign_extend(mut bits: Vec<bool>) -> Vec<u8> {
+    if let Some(&sign) = bits.last() {
+        bits.resize(byte_len(bits.len()) * 8, sign);
+    }
+    util::from_bits(&bits)
+}
+
+/// Moves `bits`, least significant first, `n` places towards the most significant end.
+fn shift_up<T: Clone>(bits: &[T], n: usize, fill: &T) -> Vec<T> {
+    let n = n.min(bits.len());
+    let mut shifted = vec![fill.clone(); n];
+    shifted.extend_from_slice(&bits[..bits.len() - n]);
+    shifted
+}
+
+fn shift_down<T: Clone>(bits: &[T], n: usize, fill: &T) -> Vec<T> {
+    let n = n.min(bits.len());
+    let mut shifted = bits[n..].to_vec();
+    shifted.resize(bits.len(), fill.clone());
+    shifted
+}
+
+fn rotate_up<T: Clone>(bits: &[T], n: usize) -> Vec<T> {
+    let mut rotated = bits.to_vec();
+    if !bits.is_empty() {
+        rotated.rotate_right(n % bits.len());
+    }
+    rotated
+}
+
+fn rotate_down<T: Clone>(bits: &[T], n: usize) -> Vec<T> {
+    let mut rotated = bits.to_vec();
+    if !bits.is_empty() {
+        rotated.rotate_left(n % bits.len());
+    }
+    rotated
 }
 
-impl Int {
-    pub fn new(protocol: &mut Protocol, share: &[u8]) -> Self {
-        let bits = util::to_bits(share);
+/// Moves `bits` by the secret `amount` with `stage`, one power of two at a time. Each stage moves by its power of
+/// two modulo the width if `wrap` is set, as rotates need, and otherwise by at most the width, as shifts need.
+fn barrel<B, F>(
+    protocol: &mut Protocol<B>,
+    bits: &[Bool<B>],
+    amount: &[Bool<B>],
+    wrap: bool,
+    stage: F,
+) -> Vec<Bool<B>>
+where
+    B: Backend,
+    F: Fn(&[Bool<B>], usize) -> Vec<Bool<B>>,
+{
+    let len = bits.len();
+    let reduce = |n: usize| {
+        if wrap {
+            n.checked_rem(len).unwrap_or(0)
+        } else {
+            n.min(len)
+        }
+    };
+    let mut value = bits.to_vec();
+    let mut step = reduce(1);
+    for bit in amount {
+        let moved = stage(&value, step);
+        value = moved
+            .iter()
+            .zip(&value)
+            .map(|(m, v)| Bool::mux(protocol, bit, m, v))
+            .collect();
+        step = reduce(step * 2);
+    }
+    value
+}
+
+pub struct Int<B: Backend = DefaultBackend> {
+    repr: Vec<Bool<B>>,
+}
+
+impl<B: Backend> Clone for Int<B> {
+    fn clone(&self) -> Self {
+        Self {
+            repr: self.repr.clone(),
+        }
+    }
+}
+
+impl<B: Backend> Int<B> {
+    pub fn new(protocol: &mut Protocol<B>, share: &[u8]) -> Self {
+        Self::new_bits(protocol, share, share.len() * 8)
+    }
+
+    pub fn constant(protocol: &mut Protocol<B>, value: &[u8]) -> Self {
+        Self::constant_bits(protocol, value, value.len() * 8)
+    }
+
+    /// A `width`-bit share, taken from the low bits of the little-endian `share` and sign-extended if `share` is
+    /// narrower.
+    pub fn new_bits(protocol: &mut Protocol<B>, share: &[u8], width: usize) -> Self {
+        let bits = util::to_bits_signed(share, width);
         Self {
             repr: bits.into_iter().map(|b| Bool::new(protocol, b)).collect(),
         }
     }
 
-    pub fn constant(protocol: &mut Protocol, value: &[u8]) -> Self {
-        let bits = util::to_bits(value);
+    pub fn constant_bits(protocol: &mut Protocol<B>, value: &[u8], width: usize) -> Self {
+        let bits = util::to_bits_signed(value, width);
         Self {
             repr: bits
                 .into_iter()
@@ -30,7 +122,7 @@ impl Int {
         }
     }
 
-    pub fn xor(protocol: &mut Protocol, a: &mut Self, b: &mut Self) -> Self {
+    pub fn xor(protocol: &mut Protocol<B>, a: &mut Self, b: &mut Self) -> Self {
         let repr = a
             .repr
             .iter()
@@ -40,17 +132,189 @@ impl Int {
         Self { repr }
     }
 
-    pub fn abs(protocol: &mut Protocol, a: &mut Self) -> Self {
+    /// Widens `a` to `width` bits by repeating its sign bit. A narrower `width` truncates.
+    pub fn sign_extend(protocol: &mut Protocol<B>, a: &mut Self, width: usize) -> Self {
+        let sign = a.sign(protocol);
+        a.resized(width, &sign)
+    }
+
+    /// Widens `a` to `width` bits by filling with zeros. A narrower `width` truncates.
+    pub fn zero_extend(protocol: &mut Protocol<B>, a: &mut Self, width: usize) -> Self {
+        let zero = Bool::constant(protocol, false);
+        a.resized(width, &zero)
+    }
+
+    /// Keeps the low `width` bits of `a`, which wrap around as casting does in Rust. A wider `width` leaves `a`
+    /// unchanged.
+    pub fn truncate(_protocol: &mut Protocol<B>, a: &mut Self, width: usize) -> Self {
+        Self {
+            repr: a.repr[..width.min(a.repr.len())].to_vec(),
+        }
+    }
+
+    /// The sign bit, or zero for a value with no bits.
+    fn sign(&self, protocol: &mut Protocol<B>) -> Bool<B> {
+        match self.repr.last() {
+            Some(sign) => sign.clone(),
+            None => Bool::constant(protocol, false),
+        }
+    }
+
+    fn resized(&self, width: usize, fill: &Bool<B>) -> Self {
+        let mut repr = self.repr.clone();
+        repr.resize(width, fill.clone());
+        Self { repr }
+    }
+
+    /// Reinterprets the bits of `a`, so that values of `2^(width - 1)` and above become negative.
+    pub fn from_nat(protocol: &mut Protocol<B>, a: &Nat<B>) -> Self {
+        Self {
+            repr: Nat::to_bits(protocol, a),
+        }
+    }
+
+    /// Reinterprets the bits of `a` as a natural number, so that negative values become `2^width` larger.
+    pub fn to_nat(protocol: &mut Protocol<B>, a: &mut Self) -> Nat<B> {
+        Nat::from_bits(protocol, &a.repr)
+    }
+
+    /// A `width`-bit 0 or 1.
+    pub fn from_bool(protocol: &mut Protocol<B>, b: &Bool<B>, width: usize) -> Self {
+        let zero = Bool::constant(protocol, false);
+        let mut repr = vec![b.clone()];
+        repr.resize(width, zero);
+        Self { repr }
+    }
+
+    pub fn is_nonzero(protocol: &mut Protocol<B>, a: &mut Self) -> Bool<B> {
+        util::any(protocol, &a.repr)
+    }
+
+    pub fn and(protocol: &mut Protocol<B>, a: &mut Self, b: &mut Self) -> Self {
+        let repr = a
+            .repr
+            .iter()
+            .zip(b.repr.iter())
+            .map(|(a, b)| Bool::and(protocol, a, b))
+            .collect();
+        Self { repr }
+    }
+
+    pub fn or(protocol: &mut Protocol<B>, a: &mut Self, b: &mut Self) -> Self {
+        let repr = a
+            .repr
+            .iter()
+            .zip(b.repr.iter())
+            .map(|(a, b)| Bool::or(protocol, a, b))
+            .collect();
+        Self { repr }
+    }
+
+    pub fn not(protocol: &mut Protocol<B>, a: &mut Self) -> Self {
+        let repr = a.repr.iter().map(|a| Bool::not(protocol, a)).collect();
+        Self { repr }
+    }
+
+    pub fn neg(protocol: &mut Protocol<B>, a: &mut Self) -> Self {
         let len = a.repr.len();
+        let sign = Bool::constant(protocol, true);
+        let mut repr = vec![sign.clone(); len];
+        unsafe {
+            util::cond_neg(protocol, &sign, repr.as_mut_ptr(), a.repr.as_ptr(), len);
+        }
+        Self { repr }
+    }
+
+    /// Shifts left by the public amount `n`, filling with zeros.
+    pub fn shl(protocol: &mut Protocol<B>, a: &mut Self, n: usize) -> Self {
+        let zero = Bool::constant(protocol, false);
+        Self {
+            repr: shift_up(&a.repr, n, &zero),
+        }
+    }
+
+    /// Shifts right by the public amount `n`, filling with the sign bit, as `>>` does for Rust's signed integers.
+    pub fn shr(protocol: &mut Protocol<B>, a: &mut Self, n: usize) -> Self {
+        let sign = a.sign(protocol);
+        Self {
+            repr: shift_down(&a.repr, n, &sign),
+        }
+    }
+
+    /// Shifts right by the public amount `n`, filling with zeros.
+    pub fn shr_logical(protocol: &mut Protocol<B>, a: &mut Self, n: usize) -> Self {
+        let zero = Bool::constant(protocol, false);
+        Self {
+            repr: shift_down(&a.repr, n, &zero),
+        }
+    }
+
+    /// Rotates left by the public amount `n`, which may exceed the width.
+    pub fn rotl(_protocol: &mut Protocol<B>, a: &mut Self, n: usize) -> Self {
+        Self {
+            repr: rotate_up(&a.repr, n),
+        }
+    }
+
+    pub fn rotr(_protocol: &mut Protocol<B>, a: &mut Self, n: usize) -> Self {
+        Self {
+            repr: rotate_down(&a.repr, n),
+        }
+    }
+
+    // The shifts and rotates by a secret amount read `amount` as unsigned, so a negative amount shifts everything
+    // out. Each bit of `amount` selects whether to move the value by the matching power of two, which costs a
+    // multiplexer per bit of the value and of `amount`.
+
+    pub fn shl_by(protocol: &mut Protocol<B>, a: &mut Self, amount: &mut Self) -> Self {
+        let zero = Bool::constant(protocol, false);
+        Self {
+            repr: barrel(protocol, &a.repr, &amount.repr, false, |bits, n| {
+                shift_up(bits, n, &zero)
+            }),
+        }
+    }
+
+    pub fn shr_by(protocol: &mut Protocol<B>, a: &mut Self, amount: &mut Self) -> Self {
+        let sign = a.sign(protocol);
+        Self {
+            repr: barrel(protocol, &a.repr, &amount.repr, false, |bits, n| {
+                shift_down(bits, n, &sign)
+            }),
+        }
+    }
+
+    pub fn shr_logical_by(protocol: &mut Protocol<B>, a: &mut Self, amount: &mut Self) -> Self {
+        let zero = Bool::constant(protocol, false);
+        Self {
+            repr: barrel(protocol, &a.repr, &amount.repr, false, |bits, n| {
+                shift_down(bits, n, &zero)
+            }),
+        }
+    }
+
+    pub fn rotl_by(protocol: &mut Protocol<B>, a: &mut Self, amount: &mut Self) -> Self {
+        Self {
+            repr: barrel(protocol, &a.repr, &amount.repr, true, rotate_up),
+        }
+    }
+
+    pub fn rotr_by(protocol: &mut Protocol<B>, a: &mut Self, amount: &mut Self) -> Self {
+        Self {
+            repr: barrel(protocol, &a.repr, &amount.repr, true, rotate_down),
+        }
+    }
+
+    pub fn abs(protocol: &mut Protocol<B>, a: &mut Self) -> Self {
         let mut res = Self {
-            repr: vec![a.repr[len - 1].clone(); len],
+            repr: vec![a.sign(protocol); a.repr.len()],
         };
         let mut sum = Int::add(protocol, a, &mut res);
 
         Int::xor(protocol, &mut sum, &mut res)
     }
 
-    pub fn add(protocol: &mut Protocol, a: &mut Self, b: &mut Self) -> Self {
+    pub fn add(protocol: &mut Protocol<B>, a: &mut Self, b: &mut Self) -> Self {
         debug_assert_eq!(a.repr.len(), b.repr.len());
         let mut repr = vec![Bool::constant(protocol, false); a.repr.len()];
         unsafe {
@@ -65,7 +329,7 @@ impl Int {
         Self { repr }
     }
 
-    pub fn sub(protocol: &mut Protocol, a: &mut Self, b: &mut Self) -> Self {
+    pub fn sub(protocol: &mut Protocol<B>, a: &mut Self, b: &mut Self) -> Self {
         debug_assert_eq!(a.repr.len(), b.repr.len());
         let mut repr = vec![Bool::constant(protocol, false); a.repr.len()];
         unsafe {
@@ -81,19 +345,20 @@ impl Int {
         Self { repr }
     }
 
-    pub fn mul(protocol: &mut Protocol, a: &mut Self, b: &mut Self) -> Self {
+    pub fn mul(protocol: &mut Protocol<B>, a: &mut Self, b: &mut Self) -> Self {
         debug_assert_eq!(a.repr.len(), b.repr.len());
         let mut repr = vec![Bool::constant(protocol, false); a.repr.len()];
         util::full_mul(protocol, &mut repr, &a.repr, &b.repr);
         Self { repr }
     }
 
-    pub fn div(protocol: &mut Protocol, a: &mut Self, b: &mut Self) -> Self {
+    pub fn div(protocol: &mut Protocol<B>, a: &mut Self, b: &mut Self) -> Self {
         debug_assert_eq!(a.repr.len(), b.repr.len());
         let len = a.repr.len();
         let a_abs = Int::abs(protocol, a);
         let b_abs = Int::abs(protocol, b);
-        let sign = Bool::xor(protocol, &a.repr[len - 1], &b.repr[len - 1]);
+        let (a_sign, b_sign) = (a.sign(protocol), b.sign(protocol));
+        let sign = Bool::xor(protocol, &a_sign, &b_sign);
         let (mut repr, _) = util::full_div(protocol, &a_abs.repr, &b_abs.repr);
         unsafe {
             util::cond_neg(protocol, &sign, repr.as_mut_ptr(), repr.as_ptr(), len);
@@ -101,12 +366,12 @@ impl Int {
         Self { repr }
     }
 
-    pub fn modulo(protocol: &mut Protocol, a: &mut Self, b: &mut Self) -> Self {
+    pub fn modulo(protocol: &mut Protocol<B>, a: &mut Self, b: &mut Self) -> Self {
         debug_assert_eq!(a.repr.len(), b.repr.len());
         let len = a.repr.len();
         let a_abs = Int::abs(protocol, a);
         let b_abs = Int::abs(protocol, b);
-        let sign = a.repr[len - 1].clone();
+        let sign = a.sign(protocol);
         let (_, mut repr) = util::full_div(protocol, &a_abs.repr, &b_abs.repr);
         unsafe {
             util::cond_neg(protocol, &sign, repr.as_mut_ptr(), repr.as_ptr(), len);
@@ -114,7 +379,12 @@ impl Int {
         Self { repr }
     }
 
-    pub fn mux(protocol: &mut Protocol, guard: &mut Bool, t: &mut Self, f: &mut Self) -> Self {
+    pub fn mux(
+        protocol: &mut Protocol<B>,
+        guard: &mut Bool<B>,
+        t: &mut Self,
+        f: &mut Self,
+    ) -> Self {
         let repr = t
             .repr
             .iter()
@@ -124,69 +394,102 @@ impl Int {
         Self { repr }
     }
 
-    pub fn eq(protocol: &mut Protocol, a: &mut Self, b: &mut Self) -> Bool {
-        a.repr
+    pub fn eq(protocol: &mut Protocol<B>, a: &mut Self, b: &mut Self) -> Bool<B> {
+        debug_assert_eq!(a.repr.len(), b.repr.len());
+        let diff: Vec<Bool<B>> = a
+            .repr
             .iter()
             .zip(b.repr.iter())
-            .fold(Bool::constant(protocol, true), |acc, (a, b)| {
-                let eq = Bool::eq(protocol, a, b);
-                Bool::and(protocol, &acc, &eq)
-            })
+            .map(|(a, b)| Bool::xor(protocol, a, b))
+            .collect();
+        let ne = util::any(protocol, &diff);
+        Bool::not(protocol, &ne)
     }
 
-    pub fn gte(protocol: &mut Protocol, a: &mut Self, b: &mut Self) -> Bool {
+    pub fn gte(protocol: &mut Protocol<B>, a: &mut Self, b: &mut Self) -> Bool<B> {
         debug_assert_eq!(a.repr.len(), b.repr.len());
         let len = a.repr.len();
-        let mut a_ext = a.clone();
-        a_ext.repr.push(a_ext.repr[len - 1].clone());
-
-        let mut b_ext = b.clone();
-        b_ext.repr.push(b_ext.repr[len - 1].clone());
+        let mut a_ext = Int::sign_extend(protocol, a, len + 1);
+        let mut b_ext = Int::sign_extend(protocol, b, len + 1);
 
         let difference = Int::sub(protocol, &mut a_ext, &mut b_ext);
         Bool::not(protocol, &difference.repr[difference.repr.len() - 1])
     }
 
-    pub fn lt(protocol: &mut Protocol, a: &mut Self, b: &mut Self) -> Bool {
+    pub fn lt(protocol: &mut Protocol<B>, a: &mut Self, b: &mut Self) -> Bool<B> {
         let tmp = Int::gte(protocol, a, b);
         Bool::not(protocol, &tmp)
     }
 
-    pub fn lte(protocol: &mut Protocol, a: &mut Self, b: &mut Self) -> Bool {
+    pub fn lte(protocol: &mut Protocol<B>, a: &mut Self, b: &mut Self) -> Bool<B> {
         Int::gte(protocol, b, a)
     }
 
-    pub fn gt(protocol: &mut Protocol, a: &mut Self, b: &mut Self) -> Bool {
+    pub fn gt(protocol: &mut Protocol<B>, a: &mut Self, b: &mut Self) -> Bool<B> {
         let tmp = Int::lte(protocol, a, b);
         Bool::not(protocol, &tmp)
     }
 
-    pub fn get(protocol: &mut Protocol, share: &mut Self) -> Vec<u8> {
-        let bits: Vec<bool> = share
+    /// This party's share, in little-endian bytes with the sign bit repeated into any unused high bits. Since
+    /// the sign bits of the shares combine into the sign bit of the value, the shares combine into the value
+    /// extended to whole bytes.
+    pub fn get(protocol: &mut Protocol<B>, share: &mut Self) -> Result<Vec<u8>> {
+        Ok(sign_extend(Self::get_bits(protocol, share)?))
+    }
+
+    pub fn get_bits(protocol: &mut Protocol<B>, share: &mut Self) -> Result<Vec<bool>> {
+        share
             .repr
             .iter_mut()
             .map(|b| Bool::get(protocol, b))
-            .collect();
-        util::from_bits(&bits)
+            .collect()
     }
 }
 
 pub mod ffi {
     use super::*;
+    use crate::error::{self, Status};
     use scuttlebutt::AesRng;
 
+    /// A share of a `bits`-bit number, read from the `(bits + 7) / 8` little-endian bytes at `share`.
     #[no_mangle]
-    pub unsafe extern "C" fn gmw_int32_new(protocol: *mut Protocol, share: i32) -> *mut Int {
-        let ret = Int::new(&mut *protocol, &share.to_le_bytes());
+    pub unsafe extern "C" fn gmw_int_new(
+        protocol: *mut Protocol,
+        share: *const u8,
+        bits: usize,
+    ) -> *mut Int {
+        let share = std::slice::from_raw_parts(share, byte_len(bits));
+        let ret = Int::new_bits(&mut *protocol, share, bits);
         Box::into_raw(Box::new(ret))
     }
 
     #[no_mangle]
-    pub unsafe extern "C" fn gmw_int32_constant(protocol: *mut Protocol, value: i32) -> *mut Int {
-        let ret = Int::constant(&mut *protocol, &value.to_le_bytes());
+    pub unsafe extern "C" fn gmw_int_constant(
+        protocol: *mut Protocol,
+        value: *const u8,
+        bits: usize,
+    ) -> *mut Int {
+        let value = std::slice::from_raw_parts(value, byte_len(bits));
+        let ret = Int::constant_bits(&mut *protocol, value, bits);
         Box::into_raw(Box::new(ret))
     }
 
+    /// Writes this party's share to the `(bits + 7) / 8` bytes at `out`, failing if it is not `bits` bits wide.
+    #[no_mangle]
+    pub unsafe extern "C" fn gmw_int_get(
+        protocol: *mut Protocol,
+        share: *mut Int,
+        out: *mut u8,
+        bits: usize,
+    ) -> Status {
+        let ret = Int::get_bits(&mut *protocol, &mut *share).and_then(|share_bits| {
+            check_width(&share_bits, bits)?;
+            Ok(sign_extend(share_bits))
+        });
+        let out = std::slice::from_raw_parts_mut(out, byte_len(bits));
+        error::status(ret.map(|bytes| out.copy_from_slice(&bytes)))
+    }
+
     #[no_mangle]
     pub unsafe extern "C" fn gmw_int_add(
         protocol: *mut Protocol,
@@ -237,10 +540,215 @@ pub mod ffi {
         Box::into_raw(Box::new(ret))
     }
 
+    #[no_mangle]
+    pub unsafe extern "C" fn gmw_int_and(
+        protocol: *mut Protocol,
+        a: *mut Int,
+        b: *mut Int,
+    ) -> *mut Int {
+        let ret = Int::and(&mut *protocol, &mut *a, &mut *b);
+        Box::into_raw(Box::new(ret))
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn gmw_int_or(
+        protocol: *mut Protocol,
+        a: *mut Int,
+        b: *mut Int,
+    ) -> *mut Int {
+        let ret = Int::or(&mut *protocol, &mut *a, &mut *b);
+        Box::into_raw(Box::new(ret))
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn gmw_int_xor(
+        protocol: *mut Protocol,
+        a: *mut Int,
+        b: *mut Int,
+    ) -> *mut Int {
+        let ret = Int::xor(&mut *protocol, &mut *a, &mut *b);
+        Box::into_raw(Box::new(ret))
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn gmw_int_not(protocol: *mut Protocol, a: *mut Int) -> *mut Int {
+        let ret = Int::not(&mut *protocol, &mut *a);
+        Box::into_raw(Box::new(ret))
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn gmw_int_neg(protocol: *mut Protocol, a: *mut Int) -> *mut Int {
+        let ret = Int::neg(&mut *protocol, &mut *a);
+        Box::into_raw(Box::new(ret))
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn gmw_int_shl(
+        protocol: *mut Protocol,
+        a: *mut Int,
+        n: usize,
+    ) -> *mut Int {
+        let ret = Int::shl(&mut *protocol, &mut *a, n);
+        Box::into_raw(Box::new(ret))
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn gmw_int_shr(
+        protocol: *mut Protocol,
+        a: *mut Int,
+        n: usize,
+    ) -> *mut Int {
+        let ret = Int::shr(&mut *protocol, &mut *a, n);
+        Box::into_raw(Box::new(ret))
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn gmw_int_shr_logical(
+        protocol: *mut Protocol,
+        a: *mut Int,
+        n: usize,
+    ) -> *mut Int {
+        let ret = Int::shr_logical(&mut *protocol, &mut *a, n);
+        Box::into_raw(Box::new(ret))
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn gmw_int_rotl(
+        protocol: *mut Protocol,
+        a: *mut Int,
+        n: usize,
+    ) -> *mut Int {
+        let ret = Int::rotl(&mut *protocol, &mut *a, n);
+        Box::into_raw(Box::new(ret))
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn gmw_int_rotr(
+        protocol: *mut Protocol,
+        a: *mut Int,
+        n: usize,
+    ) -> *mut Int {
+        let ret = Int::rotr(&mut *protocol, &mut *a, n);
+        Box::into_raw(Box::new(ret))
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn gmw_int_shl_by(
+        protocol: *mut Protocol,
+        a: *mut Int,
+        amount: *mut Int,
+    ) -> *mut Int {
+        let ret = Int::shl_by(&mut *protocol, &mut *a, &mut *amount);
+        Box::into_raw(Box::new(ret))
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn gmw_int_shr_by(
+        protocol: *mut Protocol,
+        a: *mut Int,
+        amount: *mut Int,
+    ) -> *mut Int {
+        let ret = Int::shr_by(&mut *protocol, &mut *a, &mut *amount);
+        Box::into_raw(Box::new(ret))
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn gmw_int_shr_logical_by(
+        protocol: *mut Protocol,
+        a: *mut Int,
+        amount: *mut Int,
+    ) -> *mut Int {
+        let ret = Int::shr_logical_by(&mut *protocol, &mut *a, &mut *amount);
+        Box::into_raw(Box::new(ret))
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn gmw_int_rotl_by(
+        protocol: *mut Protocol,
+        a: *mut Int,
+        amount: *mut Int,
+    ) -> *mut Int {
+        let ret = Int::rotl_by(&mut *protocol, &mut *a, &mut *amount);
+        Box::into_raw(Box::new(ret))
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn gmw_int_rotr_by(
+        protocol: *mut Protocol,
+        a: *mut Int,
+        amount: *mut Int,
+    ) -> *mut Int {
+        let ret = Int::rotr_by(&mut *protocol, &mut *a, &mut *amount);
+        Box::into_raw(Box::new(ret))
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn gmw_int_sign_extend(
+        protocol: *mut Protocol,
+        a: *mut Int,
+        bits: usize,
+    ) -> *mut Int {
+        let ret = Int::sign_extend(&mut *protocol, &mut *a, bits);
+        Box::into_raw(Box::new(ret))
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn gmw_int_zero_extend(
+        protocol: *mut Protocol,
+        a: *mut Int,
+        bits: usize,
+    ) -> *mut Int {
+        let ret = Int::zero_extend(&mut *protocol, &mut *a, bits);
+        Box::into_raw(Box::new(ret))
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn gmw_int_truncate(
+        protocol: *mut Protocol,
+        a: *mut Int,
+        bits: usize,
+    ) -> *mut Int {
+        let ret = Int::truncate(&mut *protocol, &mut *a, bits);
+        Box::into_raw(Box::new(ret))
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn gmw_int_from_nat(protocol: *mut Protocol, a: *mut Nat) -> *mut Int {
+        let ret = Int::from_nat(&mut *protocol, &*a);
+        Box::into_raw(Box::new(ret))
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn gmw_int_to_nat(protocol: *mut Protocol, a: *mut Int) -> *mut Nat {
+        let ret = Int::to_nat(&mut *protocol, &mut *a);
+        Box::into_raw(Box::new(ret))
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn gmw_int_from_bool(
+        protocol: *mut Protocol,
+        b_raw: *const Mutex<CachedBool>,
+        bits: usize,
+    ) -> *mut Int {
+        let b = Bool::from_raw(b_raw);
+        let ret = Int::from_bool(&mut *protocol, &b, bits);
+        Bool::into_raw(b);
+        Box::into_raw(Box::new(ret))
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn gmw_int_is_nonzero(
+        protocol: *mut Protocol,
+        a: *mut Int,
+    ) -> *const Mutex<CachedBool> {
+        let ret = Int::is_nonzero(&mut *protocol, &mut *a);
+        Bool::into_raw(ret)
+    }
+
     #[no_mangle]
     pub unsafe extern "C" fn gmw_int_mux(
         protocol: *mut Protocol,
-        guard_raw: *const RefCell<CachedBool>,
+        guard_raw: *const Mutex<CachedBool>,
         t: *mut Int,
         f: *mut Int,
     ) -> *mut Int {
@@ -255,7 +763,7 @@ pub mod ffi {
         protocol: *mut Protocol,
         a: *mut Int,
         b: *mut Int,
-    ) -> *const RefCell<CachedBool> {
+    ) -> *const Mutex<CachedBool> {
         let ret = Int::eq(&mut *protocol, &mut *a, &mut *b);
         Bool::into_raw(ret)
     }
@@ -265,7 +773,7 @@ pub mod ffi {
         protocol: *mut Protocol,
         a: *mut Int,
         b: *mut Int,
-    ) -> *const RefCell<CachedBool> {
+    ) -> *const Mutex<CachedBool> {
         let ret = Int::lt(&mut *protocol, &mut *a, &mut *b);
         Bool::into_raw(ret)
     }
@@ -275,16 +783,11 @@ pub mod ffi {
         protocol: *mut Protocol,
         a: *mut Int,
         b: *mut Int,
-    ) -> *const RefCell<CachedBool> {
+    ) -> *const Mutex<CachedBool> {
         let ret = Int::lte(&mut *protocol, &mut *a, &mut *b);
         Bool::into_raw(ret)
     }
 
-    #[no_mangle]
-    pub unsafe extern "C" fn gmw_int32_get(protocol: *mut Protocol, share: *mut Int) -> i32 {
-        i32::from_le_bytes(Int::get(&mut *protocol, &mut *share).try_into().unwrap())
-    }
-
     #[no_mangle]
     pub unsafe extern "C" fn gmw_int_drop(share: *mut Int) {
         Box::from_raw(share);
@@ -293,40 +796,341 @@ pub mod ffi {
     // Convenience
 
     #[no_mangle]
-    pub unsafe extern "C" fn gmw_share_send_int32(
+    pub unsafe extern "C" fn gmw_share_send_int(
         prg: *mut AesRng,
         channels: *mut *mut Channel,
         channels_len: usize,
-        clear: i32,
-    ) {
+        clear: *const u8,
+        bits: usize,
+    ) -> Status {
         let channels: &mut [&mut Channel] =
             std::mem::transmute(std::slice::from_raw_parts_mut(channels, channels_len));
-        share_send(&mut *prg, channels, &clear.to_le_bytes())
+        let clear = std::slice::from_raw_parts(clear, byte_len(bits));
+        error::status(share_send(&mut *prg, channels, clear))
     }
 
     #[no_mangle]
-    pub unsafe extern "C" fn gmw_share_recv_int32(channel: *mut Channel) -> i32 {
-        let channel = &mut *channel;
-        let mut buf = [0u8; 4];
-        channel.read_exact(&mut buf).expect("TODO");
-        i32::from_le_bytes(buf)
+    pub unsafe extern "C" fn gmw_share_recv_int(
+        channel: *mut Channel,
+        out: *mut u8,
+        bits: usize,
+    ) -> Status {
+        let out = std::slice::from_raw_parts_mut(out, byte_len(bits));
+        error::status((&mut *channel).read_exact(out).map_err(Into::into))
     }
 
     #[no_mangle]
-    pub unsafe extern "C" fn gmw_reveal_send_int32(channel: *mut Channel, share: i32) {
-        let channel = &mut *channel;
-        channel.write_all(&share.to_le_bytes()).expect("TODO")
+    pub unsafe extern "C" fn gmw_reveal_send_int(
+        channel: *mut Channel,
+        share: *const u8,
+        bits: usize,
+    ) -> Status {
+        let share = std::slice::from_raw_parts(share, byte_len(bits));
+        error::status((&mut *channel).write_all(share).map_err(Into::into))
     }
 
     #[no_mangle]
-    pub unsafe extern "C" fn gmw_reveal_recv_int32(
+    pub unsafe extern "C" fn gmw_reveal_recv_int(
         channels: *mut *mut Channel,
         channels_len: usize,
-    ) -> i32 {
+        out: *mut u8,
+        bits: usize,
+    ) -> Status {
         let channels: &mut [&mut Channel] =
             std::mem::transmute(std::slice::from_raw_parts_mut(channels, channels_len));
-        let mut buf = [0u8; 4];
-        reveal_recv(channels, &mut buf);
-        i32::from_le_bytes(buf)
+        let out = std::slice::from_raw_parts_mut(out, byte_len(bits));
+        error::status(reveal_recv(channels, out))
+    }
+
+    fixed_width_ffi!(
+        Int,
+        i8,
+        gmw_int8_new,
+        gmw_int8_constant,
+        gmw_int8_get,
+        gmw_share_send_int8,
+        gmw_share_recv_int8,
+        gmw_reveal_send_int8,
+        gmw_reveal_recv_int8
+    );
+
+    fixed_width_ffi!(
+        Int,
+        i16,
+        gmw_int16_new,
+        gmw_int16_constant,
+        gmw_int16_get,
+        gmw_share_send_int16,
+        gmw_share_recv_int16,
+        gmw_reveal_send_int16,
+        gmw_reveal_recv_int16
+    );
+
+    fixed_width_ffi!(
+        Int,
+        i32,
+        gmw_int32_new,
+        gmw_int32_constant,
+        gmw_int32_get,
+        gmw_share_send_int32,
+        gmw_share_recv_int32,
+        gmw_reveal_send_int32,
+        gmw_reveal_recv_int32
+    );
+
+    fixed_width_ffi!(
+        Int,
+        i64,
+        gmw_int64_new,
+        gmw_int64_constant,
+        gmw_int64_get,
+        gmw_share_send_int64,
+        gmw_share_recv_int64,
+        gmw_reveal_send_int64,
+        gmw_reveal_recv_int64
+    );
+
+    fixed_width_ffi!(
+        Int,
+        i128,
+        gmw_int128_new,
+        gmw_int128_constant,
+        gmw_int128_get,
+        gmw_share_send_int128,
+        gmw_share_recv_int128,
+        gmw_reveal_send_int128,
+        gmw_reveal_recv_int128
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLES: [i8; 10] = [-128, -127, -5, -1, 0, 1, 2, 7, 100, 127];
+
+    fn int(protocol: &mut Protocol<Plain>, value: i8) -> Int<Plain> {
+        Int::new(protocol, &value.to_le_bytes())
+    }
+
+    fn value(protocol: &mut Protocol<Plain>, mut share: Int<Plain>) -> i8 {
+        i8::from_le_bytes(Int::get(protocol, &mut share).unwrap().try_into().unwrap())
+    }
+
+    #[test]
+    fn arithmetic() {
+        let p = &mut Protocol::with_backend(Plain::new(2));
+        for a in SAMPLES {
+            for b in SAMPLES {
+                let (mut x, mut y) = (int(p, a), int(p, b));
+                let results = [
+                    (Int::add(p, &mut x, &mut y), a.wrapping_add(b)),
+                    (Int::sub(p, &mut x, &mut y), a.wrapping_sub(b)),
+                    (Int::mul(p, &mut x, &mut y), a.wrapping_mul(b)),
+                    (Int::xor(p, &mut x, &mut y), a ^ b),
+                    (Int::abs(p, &mut x), a.wrapping_abs()),
+                ];
+                for (share, expected) in results {
+                    assert_eq!(value(p, share), expected, "{} {}", a, b);
+                }
+
+                if b != 0 {
+                    let quot = Int::div(p, &mut x, &mut y);
+                    let rem = Int::modulo(p, &mut x, &mut y);
+                    assert_eq!(value(p, quot), a.wrapping_div(b), "{} / {}", a, b);
+                    assert_eq!(value(p, rem), a.wrapping_rem(b), "{} % {}", a, b);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn widths() {
+        let p = &mut Protocol::with_backend(Plain::new(2));
+        // 12-bit values wrap at 2048 and are read back sign-extended to two bytes.
+        let mut x = Int::new_bits(p, &(-5i16).to_le_bytes(), 12);
+        let mut y = Int::constant_bits(p, &3i16.to_le_bytes(), 12);
+        let mut sum = Int::add(p, &mut x, &mut y);
+        assert_eq!(Int::get(p, &mut sum).unwrap(), (-2i16).to_le_bytes());
+        let mut big = Int::constant_bits(p, &2047i16.to_le_bytes(), 12);
+        let mut wrapped = Int::add(p, &mut big, &mut y);
+        assert_eq!(Int::get(p, &mut wrapped).unwrap(), (-2046i16).to_le_bytes());
+        // Narrower input is sign-extended to the width.
+        let mut x = Int::new_bits(p, &(-1i8).to_le_bytes(), 16);
+        let mut y = Int::constant_bits(p, &100i8.to_le_bytes(), 16);
+        let mut sum = Int::add(p, &mut x, &mut y);
+        assert_eq!(Int::get(p, &mut sum).unwrap(), 99i16.to_le_bytes());
+        assert_eq!(Int::get(p, &mut x).unwrap(), (-1i16).to_le_bytes());
+
+        let (a, b) = (i64::MIN + 7, -3i64);
+        let (mut x, mut y) = (Int::new(p, &a.to_le_bytes()), Int::new(p, &b.to_le_bytes()));
+        let mut quot = Int::div(p, &mut x, &mut y);
+        let bytes = Int::get(p, &mut quot).unwrap();
+        assert_eq!(i64::from_le_bytes(bytes.try_into().unwrap()), a / b);
+    }
+
+    #[test]
+    fn bitwise() {
+        let p = &mut Protocol::with_backend(Plain::new(2));
+        for a in SAMPLES {
+            for b in SAMPLES {
+                let (mut x, mut y) = (int(p, a), int(p, b));
+                let results = [
+                    (Int::and(p, &mut x, &mut y), a & b),
+                    (Int::or(p, &mut x, &mut y), a | b),
+                    (Int::not(p, &mut x), !a),
+                    (Int::neg(p, &mut x), a.wrapping_neg()),
+                ];
+                for (share, expected) in results {
+                    assert_eq!(value(p, share), expected, "{} {}", a, b);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn shifts_and_rotates() {
+        let p = &mut Protocol::with_backend(Plain::new(2));
+        for a in SAMPLES {
+            // Amounts past the width shift everything out, and negative secret amounts count as large ones.
+            for n in [0i8, 1, 3, 7, 8, 11, -1] {
+                let m = n as u8 as u32;
+                let shl = a.checked_shl(m).unwrap_or(0);
+                let shr = a.checked_shr(m).unwrap_or(a >> 7);
+                let shr_logical = (a as u8).checked_shr(m).unwrap_or(0) as i8;
+                let (rotl, rotr) = (a.rotate_left(m), a.rotate_right(m));
+
+                let mut x = int(p, a);
+                let n = n as u8 as usize;
+                let public = [
+                    (Int::shl(p, &mut x, n), shl),
+                    (Int::shr(p, &mut x, n), shr),
+                    (Int::shr_logical(p, &mut x, n), shr_logical),
+                    (Int::rotl(p, &mut x, n), rotl),
+                    (Int::rotr(p, &mut x, n), rotr),
+                ];
+                for (share, expected) in public {
+                    assert_eq!(value(p, share), expected, "{} by {}", a, n);
+                }
+
+                let mut amount = int(p, n as i8);
+                let secret = [
+                    (Int::shl_by(p, &mut x, &mut amount), shl),
+                    (Int::shr_by(p, &mut x, &mut amount), shr),
+                    (Int::shr_logical_by(p, &mut x, &mut amount), shr_logical),
+                    (Int::rotl_by(p, &mut x, &mut amount), rotl),
+                    (Int::rotr_by(p, &mut x, &mut amount), rotr),
+                ];
+                for (share, expected) in secret {
+                    assert_eq!(value(p, share), expected, "{} by secret {}", a, n);
+                }
+            }
+        }
+
+        // A 128-bit amount has stages past 2^64, which rotates must still take modulo the width.
+        let a = 0x0123_4567_89ab_cdef_fedc_ba98_7654_3210i128;
+        let get = |p: &mut Protocol<Plain>, mut share: Int<Plain>| {
+            i128::from_le_bytes(Int::get(p, &mut share).unwrap().try_into().unwrap())
+        };
+        for n in [1i128, 64, 100, -1] {
+            let m = (n as u128 % 128) as u32;
+            let mut x = Int::new(p, &a.to_le_bytes());
+            let mut amount = Int::new(p, &n.to_le_bytes());
+            let results = [
+                (
+                    Int::shl_by(p, &mut x, &mut amount),
+                    a.checked_shl(n as u32).unwrap_or(0),
+                ),
+                (Int::rotl_by(p, &mut x, &mut amount), a.rotate_left(m)),
+                (Int::rotr_by(p, &mut x, &mut amount), a.rotate_right(m)),
+            ];
+            for (share, expected) in results {
+                assert_eq!(get(p, share), expected, "by secret {}", n);
+            }
+        }
+    }
+
+    #[test]
+    fn zero_width() {
+        let p = &mut Protocol::with_backend(Plain::new(2));
+        let mut x = Int::new_bits(p, &[], 0);
+        let mut y = x.clone();
+        let mut amount = int(p, 3);
+        let results = [
+            Int::shr(p, &mut x, 3),
+            Int::rotr(p, &mut x, 3),
+            Int::shr_by(p, &mut x, &mut amount),
+            Int::rotr_by(p, &mut x, &mut amount),
+            Int::neg(p, &mut x),
+            Int::div(p, &mut x, &mut y),
+        ];
+        for mut share in results {
+            assert!(Int::get(p, &mut share).unwrap().is_empty());
+        }
+        let mut wide = Int::sign_extend(p, &mut x, 8);
+        assert_eq!(Int::get(p, &mut wide).unwrap(), [0]);
+    }
+
+    #[test]
+    fn casts() {
+        let p = &mut Protocol::with_backend(Plain::new(2));
+        for a in SAMPLES {
+            let mut x = int(p, a);
+            let mut wide = Int::sign_extend(p, &mut x, 16);
+            let mut unsigned = Int::zero_extend(p, &mut x, 16);
+            let mut narrow = Int::truncate(p, &mut wide, 4);
+            let bytes = Int::get(p, &mut wide).unwrap();
+            assert_eq!(i16::from_le_bytes(bytes.try_into().unwrap()), a as i16);
+            let bytes = Int::get(p, &mut unsigned).unwrap();
+            assert_eq!(
+                i16::from_le_bytes(bytes.try_into().unwrap()),
+                a as u8 as i16
+            );
+            // The 4-bit value is read back sign-extended.
+            let expected = ((a << 4) as i8) >> 4;
+            assert_eq!(Int::get(p, &mut narrow).unwrap(), [expected as u8]);
+
+            let nat = Int::to_nat(p, &mut x);
+            assert_eq!(Nat::get(p, &nat).unwrap(), [a as u8]);
+            let back = Int::from_nat(p, &nat);
+            assert_eq!(value(p, back), a);
+
+            let nonzero = Int::is_nonzero(p, &mut x);
+            assert_eq!(Bool::get(p, &nonzero).unwrap(), a != 0);
+            let one = Int::from_bool(p, &nonzero, 8);
+            assert_eq!(value(p, one), (a != 0) as i8);
+        }
+    }
+
+    #[test]
+    fn comparisons() {
+        let p = &mut Protocol::with_backend(Plain::new(2));
+        for a in SAMPLES {
+            for b in SAMPLES {
+                let (mut x, mut y) = (int(p, a), int(p, b));
+                let results = [
+                    (Int::eq(p, &mut x, &mut y), a == b),
+                    (Int::lt(p, &mut x, &mut y), a < b),
+                    (Int::lte(p, &mut x, &mut y), a <= b),
+                    (Int::gt(p, &mut x, &mut y), a > b),
+                    (Int::gte(p, &mut x, &mut y), a >= b),
+                ];
+                for (share, expected) in results {
+                    assert_eq!(Bool::get(p, &share).unwrap(), expected, "{} {}", a, b);
+                }
+
+                let mut g = Bool::new(p, a < b);
+                let min = Int::mux(p, &mut g, &mut x, &mut y);
+                assert_eq!(value(p, min), a.min(b));
+            }
+        }
+
+        // Equality of 64-bit values takes 6 rounds, not 64.
+        let p = &mut Protocol::with_backend(Plain::new(2));
+        let mut x = Int::new(p, &(-5i64).to_le_bytes());
+        let mut y = Int::new(p, &(-5i64).to_le_bytes());
+        let eq = Int::eq(p, &mut x, &mut y);
+        assert!(Bool::get(p, &eq).unwrap());
+        assert_eq!(p.backend().cost().rounds, 6);
     }
 }
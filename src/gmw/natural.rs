@@ -3,32 +3,48 @@ use rand::{CryptoRng, Rng};
 use std::borrow::Borrow;
 use std::io::{Read, Write};
 
+use crate::error::Result;
 use crate::gmw::Bool;
 use crate::gmw::Protocol;
 use crate::gmw::*;
 use crate::util;
 use crate::util::Channel;
 
-use crate::motion;
+use std::sync::Arc;
+use std::sync::Mutex;
 
-use std::cell::RefCell;
-use std::rc::Rc;
-
-#[derive(Debug, Clone)]
-pub enum CachedNat {
+pub enum CachedNat<B: Backend = DefaultBackend> {
     Value(Vec<bool>),
-    Expr(motion::Nat),
+    Expr(B::Nat),
 }
 
-impl CachedNat {
-    fn into_expr(self, protocol: &mut Protocol) -> motion::Nat {
+impl<B: Backend> Clone for CachedNat<B> {
+    fn clone(&self) -> Self {
         match self {
-            CachedNat::Value(share) => motion::Nat::new(&mut protocol.party, share),
+            CachedNat::Value(share) => CachedNat::Value(share.clone()),
+            CachedNat::Expr(e) => CachedNat::Expr(e.clone()),
+        }
+    }
+}
+
+impl<B: Backend> std::fmt::Debug for CachedNat<B> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CachedNat::Value(share) => f.debug_tuple("Value").field(share).finish(),
+            CachedNat::Expr(e) => f.debug_tuple("Expr").field(e).finish(),
+        }
+    }
+}
+
+impl<B: Backend> CachedNat<B> {
+    fn into_expr(self, protocol: &mut Protocol<B>) -> B::Nat {
+        match self {
+            CachedNat::Value(share) => protocol.backend.nat_new(share),
             CachedNat::Expr(e) => e,
         }
     }
 
-    fn value(&self, _protocol: &mut Protocol) -> Option<Vec<bool>> {
+    fn value(&self, _protocol: &mut Protocol<B>) -> Option<Vec<bool>> {
         match self {
             CachedNat::Value(share) => Some(share.clone()),
             CachedNat::Expr(_) => None,
@@ -36,118 +52,306 @@ impl CachedNat {
     }
 }
 
-#[derive(Clone, Debug)]
-pub struct Nat {
-    repr: Rc<RefCell<CachedNat>>,
+pub struct Nat<B: Backend = DefaultBackend> {
+    repr: Arc<Mutex<CachedNat<B>>>,
+}
+
+impl<B: Backend> Clone for Nat<B> {
+    fn clone(&self) -> Self {
+        Self {
+            repr: self.repr.clone(),
+        }
+    }
+}
+
+impl<B: Backend> std::fmt::Debug for Nat<B> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Nat").field("repr", &self.repr).finish()
+    }
 }
 
-impl Nat {
-    pub fn from_expr(protocol: &mut Protocol, expr: motion::Nat) -> Self {
-        let repr = Rc::new(RefCell::new(CachedNat::Expr(expr)));
+impl<B: Backend> Nat<B> {
+    pub fn from_expr(protocol: &mut Protocol<B>, expr: B::Nat) -> Self {
+        let repr = Arc::new(Mutex::new(CachedNat::Expr(expr)));
         protocol.delayed_nat.push(repr.clone());
         Self { repr }
     }
 
-    pub fn to_expr(protocol: &mut Protocol, share: &Self) -> motion::Nat {
-        (*share.repr).borrow().clone().into_expr(protocol)
+    pub fn to_expr(protocol: &mut Protocol<B>, share: &Self) -> B::Nat {
+        share.repr.lock().unwrap().clone().into_expr(protocol)
+    }
+
+    pub fn new(protocol: &mut Protocol<B>, share: &[u8]) -> Self {
+        Self::new_bits(protocol, share, share.len() * 8)
     }
 
-    pub fn new(protocol: &mut Protocol, share: &[u8]) -> Self {
-        let expr = motion::Nat::new(&mut protocol.party, util::to_bits(share));
+    pub fn constant(protocol: &mut Protocol<B>, value: &[u8]) -> Self {
+        Self::constant_bits(protocol, value, value.len() * 8)
+    }
+
+    /// A `width`-bit share, taken from the low bits of the little-endian `share`.
+    pub fn new_bits(protocol: &mut Protocol<B>, share: &[u8], width: usize) -> Self {
+        let expr = protocol.backend.nat_new(util::to_bits_width(share, width));
         Self::from_expr(protocol, expr)
     }
 
-    pub fn constant(protocol: &mut Protocol, value: &[u8]) -> Self {
-        let expr = motion::Nat::constant(&mut protocol.party, util::to_bits(value));
+    pub fn constant_bits(protocol: &mut Protocol<B>, value: &[u8], width: usize) -> Self {
+        let expr = protocol
+            .backend
+            .nat_constant(util::to_bits_width(value, width));
         Self::from_expr(protocol, expr)
     }
 
-    pub fn add(protocol: &mut Protocol, a: &Self, b: &Self) -> Self {
+    pub fn add(protocol: &mut Protocol<B>, a: &Self, b: &Self) -> Self {
         let expr_a = Self::to_expr(protocol, a);
         let expr_b = Self::to_expr(protocol, b);
-        let expr = expr_a.add(&expr_b);
+        let expr = protocol.backend.nat_add(&expr_a, &expr_b);
         Self::from_expr(protocol, expr)
     }
 
-    pub fn sub(protocol: &mut Protocol, a: &Self, b: &Self) -> Self {
+    pub fn sub(protocol: &mut Protocol<B>, a: &Self, b: &Self) -> Self {
         let expr_a = Self::to_expr(protocol, a);
         let expr_b = Self::to_expr(protocol, b);
-        let expr = expr_a.sub(&expr_b);
+        let expr = protocol.backend.nat_sub(&expr_a, &expr_b);
         Self::from_expr(protocol, expr)
     }
 
-    pub fn mul(protocol: &mut Protocol, a: &Self, b: &Self) -> Self {
+    pub fn mul(protocol: &mut Protocol<B>, a: &Self, b: &Self) -> Self {
         let expr_a = Self::to_expr(protocol, a);
         let expr_b = Self::to_expr(protocol, b);
-        let expr = expr_a.mul(&expr_b);
+        let expr = protocol.backend.nat_mul(&expr_a, &expr_b);
         Self::from_expr(protocol, expr)
     }
 
-    pub fn mux(protocol: &mut Protocol, g: &Bool, a: &Self, b: &Self) -> Self {
+    pub fn mux(protocol: &mut Protocol<B>, g: &Bool<B>, a: &Self, b: &Self) -> Self {
         let expr_g = Bool::to_expr(protocol, g);
         let expr_a = Self::to_expr(protocol, a);
         let expr_b = Self::to_expr(protocol, b);
-        let expr = motion::Nat::mux(&expr_g, &expr_a, &expr_b);
+        let expr = protocol.backend.nat_mux(&expr_g, &expr_a, &expr_b);
         Self::from_expr(protocol, expr)
     }
 
-    pub fn eq(protocol: &mut Protocol, a: &Self, b: &Self) -> Bool {
+    pub fn eq(protocol: &mut Protocol<B>, a: &Self, b: &Self) -> Bool<B> {
         let expr_a = Self::to_expr(protocol, a);
         let expr_b = Self::to_expr(protocol, b);
-        let expr = expr_a.eq(&expr_b);
+        let expr = protocol.backend.nat_eq(&expr_a, &expr_b);
         Bool::from_expr(protocol, expr)
     }
 
-    pub fn gt(protocol: &mut Protocol, a: &Self, b: &Self) -> Bool {
+    pub fn gt(protocol: &mut Protocol<B>, a: &Self, b: &Self) -> Bool<B> {
         let expr_a = Self::to_expr(protocol, a);
         let expr_b = Self::to_expr(protocol, b);
-        let expr = expr_a.gt(&expr_b);
+        let expr = protocol.backend.nat_gt(&expr_a, &expr_b);
         Bool::from_expr(protocol, expr)
     }
 
-    pub fn lt(protocol: &mut Protocol, a: &Self, b: &Self) -> Bool {
+    pub fn lt(protocol: &mut Protocol<B>, a: &Self, b: &Self) -> Bool<B> {
         Self::gt(protocol, b, a)
     }
 
-    pub fn gte(protocol: &mut Protocol, a: &Self, b: &Self) -> Bool {
+    pub fn gte(protocol: &mut Protocol<B>, a: &Self, b: &Self) -> Bool<B> {
         let altb = Self::lt(protocol, a, b);
         Bool::not(protocol, &altb)
     }
 
-    pub fn lte(protocol: &mut Protocol, a: &Self, b: &Self) -> Bool {
+    pub fn lte(protocol: &mut Protocol<B>, a: &Self, b: &Self) -> Bool<B> {
         let agtb = Self::gt(protocol, a, b);
         Bool::not(protocol, &agtb)
     }
 
-    pub fn get(protocol: &mut Protocol, share: &Self) -> Vec<u8> {
-        let cached = (*share.repr).borrow().value(protocol);
-        let bits = match cached {
+    pub fn min(protocol: &mut Protocol<B>, a: &Self, b: &Self) -> Self {
+        let altb = Self::lt(protocol, a, b);
+        Self::mux(protocol, &altb, a, b)
+    }
+
+    pub fn max(protocol: &mut Protocol<B>, a: &Self, b: &Self) -> Self {
+        let agtb = Self::gt(protocol, a, b);
+        Self::mux(protocol, &agtb, a, b)
+    }
+
+    // The operations below are boolean circuits over the individual bits.
+
+    /// The bits of `a`, least significant first.
+    pub fn to_bits(protocol: &mut Protocol<B>, a: &Self) -> Vec<Bool<B>> {
+        let expr = Self::to_expr(protocol, a);
+        let bits = protocol.backend.nat_bits(&expr);
+        bits.into_iter()
+            .map(|b| Bool::from_expr(protocol, b))
+            .collect()
+    }
+
+    pub fn from_bits(protocol: &mut Protocol<B>, bits: &[Bool<B>]) -> Self {
+        let exprs: Vec<B::Bool> = bits.iter().map(|b| Bool::to_expr(protocol, b)).collect();
+        let expr = protocol.backend.nat_from_bits(&exprs);
+        Self::from_expr(protocol, expr)
+    }
+
+    /// Widens `a` to `width` bits by filling with zeros. A narrower `width` truncates.
+    pub fn zero_extend(protocol: &mut Protocol<B>, a: &Self, width: usize) -> Self {
+        let zero = Bool::constant(protocol, false);
+        let mut bits = Self::to_bits(protocol, a);
+        bits.resize(width, zero);
+        Self::from_bits(protocol, &bits)
+    }
+
+    /// Keeps the low `width` bits of `a`. A wider `width` leaves `a` unchanged.
+    pub fn truncate(protocol: &mut Protocol<B>, a: &Self, width: usize) -> Self {
+        let mut bits = Self::to_bits(protocol, a);
+        bits.truncate(width);
+        Self::from_bits(protocol, &bits)
+    }
+
+    /// A `width`-bit 0 or 1.
+    pub fn from_bool(protocol: &mut Protocol<B>, b: &Bool<B>, width: usize) -> Self {
+        let zero = Bool::constant(protocol, false);
+        let mut bits = vec![b.clone()];
+        bits.resize(width, zero);
+        Self::from_bits(protocol, &bits)
+    }
+
+    pub fn is_nonzero(protocol: &mut Protocol<B>, a: &Self) -> Bool<B> {
+        let bits = Self::to_bits(protocol, a);
+        util::any(protocol, &bits)
+    }
+
+    fn bitwise<F>(protocol: &mut Protocol<B>, a: &Self, b: &Self, op: F) -> Self
+    where
+        F: Fn(&mut Protocol<B>, &Bool<B>, &Bool<B>) -> Bool<B>,
+    {
+        let a = Self::to_bits(protocol, a);
+        let b = Self::to_bits(protocol, b);
+        debug_assert_eq!(a.len(), b.len());
+        let bits: Vec<Bool<B>> = a.iter().zip(&b).map(|(a, b)| op(protocol, a, b)).collect();
+        Self::from_bits(protocol, &bits)
+    }
+
+    pub fn and(protocol: &mut Protocol<B>, a: &Self, b: &Self) -> Self {
+        Self::bitwise(protocol, a, b, Bool::and)
+    }
+
+    pub fn or(protocol: &mut Protocol<B>, a: &Self, b: &Self) -> Self {
+        Self::bitwise(protocol, a, b, Bool::or)
+    }
+
+    pub fn xor(protocol: &mut Protocol<B>, a: &Self, b: &Self) -> Self {
+        Self::bitwise(protocol, a, b, Bool::xor)
+    }
+
+    pub fn not(protocol: &mut Protocol<B>, a: &Self) -> Self {
+        let bits: Vec<Bool<B>> = Self::to_bits(protocol, a)
+            .iter()
+            .map(|b| Bool::not(protocol, b))
+            .collect();
+        Self::from_bits(protocol, &bits)
+    }
+
+    /// Shifts `a` left by the public amount `n`, dropping the bits shifted out.
+    pub fn shl(protocol: &mut Protocol<B>, a: &Self, n: usize) -> Self {
+        let mut bits = Self::to_bits(protocol, a);
+        let len = bits.len();
+        let n = n.min(len);
+        bits.truncate(len - n);
+        let mut shifted = vec![Bool::constant(protocol, false); n];
+        shifted.extend(bits);
+        Self::from_bits(protocol, &shifted)
+    }
+
+    /// Shifts `a` right by the public amount `n`, filling with zeros.
+    pub fn shr(protocol: &mut Protocol<B>, a: &Self, n: usize) -> Self {
+        let bits = Self::to_bits(protocol, a);
+        let n = n.min(bits.len());
+        let mut shifted = bits[n..].to_vec();
+        shifted.resize(bits.len(), Bool::constant(protocol, false));
+        Self::from_bits(protocol, &shifted)
+    }
+
+    /// The quotient, rounded down. Dividing by zero gives all ones.
+    pub fn div(protocol: &mut Protocol<B>, a: &Self, b: &Self) -> Self {
+        let a = Self::to_bits(protocol, a);
+        let b = Self::to_bits(protocol, b);
+        debug_assert_eq!(a.len(), b.len());
+        let (quot, _) = util::full_div(protocol, &a, &b);
+        Self::from_bits(protocol, &quot)
+    }
+
+    /// The remainder of `div`. The remainder of dividing by zero is `a`.
+    pub fn modulo(protocol: &mut Protocol<B>, a: &Self, b: &Self) -> Self {
+        let a = Self::to_bits(protocol, a);
+        let b = Self::to_bits(protocol, b);
+        debug_assert_eq!(a.len(), b.len());
+        let (_, rem) = util::full_div(protocol, &a, &b);
+        Self::from_bits(protocol, &rem)
+    }
+
+    /// The number of bits in `share`, without evaluating it.
+    pub fn width(protocol: &mut Protocol<B>, share: &Self) -> usize {
+        match &*share.repr.lock().unwrap() {
+            CachedNat::Value(bits) => bits.len(),
+            CachedNat::Expr(e) => protocol.backend.nat_width(e),
+        }
+    }
+
+    /// This party's share, in little-endian bytes with any unused high bits zero.
+    pub fn get(protocol: &mut Protocol<B>, share: &Self) -> Result<Vec<u8>> {
+        Ok(util::from_bits(&Self::get_bits(protocol, share)?))
+    }
+
+    pub fn get_bits(protocol: &mut Protocol<B>, share: &Self) -> Result<Vec<bool>> {
+        let cached = share.repr.lock().unwrap().value(protocol);
+        match cached {
             None => {
-                protocol.run();
-                (*share.repr).borrow().value(protocol).unwrap()
+                protocol.run()?;
+                Ok(share.repr.lock().unwrap().value(protocol).unwrap())
             }
-            Some(share) => share,
-        };
-        util::from_bits(&bits)
+            Some(share) => Ok(share),
+        }
     }
 }
 
 pub mod ffi {
     use super::*;
+    use crate::error::{self, Status};
     use scuttlebutt::AesRng;
 
+    /// A share of a `bits`-bit number, read from the `(bits + 7) / 8` little-endian bytes at `share`.
     #[no_mangle]
-    pub unsafe extern "C" fn gmw_nat32_new(protocol: *mut Protocol, share: u32) -> *mut Nat {
-        let ret = Nat::new(&mut *protocol, &share.to_le_bytes());
+    pub unsafe extern "C" fn gmw_nat_new(
+        protocol: *mut Protocol,
+        share: *const u8,
+        bits: usize,
+    ) -> *mut Nat {
+        let share = std::slice::from_raw_parts(share, byte_len(bits));
+        let ret = Nat::new_bits(&mut *protocol, share, bits);
         Box::into_raw(Box::new(ret))
     }
 
     #[no_mangle]
-    pub unsafe extern "C" fn gmw_nat32_constant(protocol: *mut Protocol, value: u32) -> *mut Nat {
-        let ret = Nat::constant(&mut *protocol, &value.to_le_bytes());
+    pub unsafe extern "C" fn gmw_nat_constant(
+        protocol: *mut Protocol,
+        value: *const u8,
+        bits: usize,
+    ) -> *mut Nat {
+        let value = std::slice::from_raw_parts(value, byte_len(bits));
+        let ret = Nat::constant_bits(&mut *protocol, value, bits);
         Box::into_raw(Box::new(ret))
     }
 
+    /// Writes this party's share to the `(bits + 7) / 8` bytes at `out`, failing if it is not `bits` bits wide.
+    #[no_mangle]
+    pub unsafe extern "C" fn gmw_nat_get(
+        protocol: *mut Protocol,
+        share: *mut Nat,
+        out: *mut u8,
+        bits: usize,
+    ) -> Status {
+        let ret = Nat::get_bits(&mut *protocol, &*share).and_then(|share_bits| {
+            check_width(&share_bits, bits)?;
+            Ok(util::from_bits(&share_bits))
+        });
+        let out = std::slice::from_raw_parts_mut(out, byte_len(bits));
+        error::status(ret.map(|bytes| out.copy_from_slice(&bytes)))
+    }
+
     #[no_mangle]
     pub unsafe extern "C" fn gmw_nat_add(
         protocol: *mut Protocol,
@@ -158,6 +362,16 @@ pub mod ffi {
         Box::into_raw(Box::new(ret))
     }
 
+    #[no_mangle]
+    pub unsafe extern "C" fn gmw_nat_sub(
+        protocol: *mut Protocol,
+        a: *mut Nat,
+        b: *mut Nat,
+    ) -> *mut Nat {
+        let ret = Nat::sub(&mut *protocol, &*a, &*b);
+        Box::into_raw(Box::new(ret))
+    }
+
     #[no_mangle]
     pub unsafe extern "C" fn gmw_nat_mul(
         protocol: *mut Protocol,
@@ -168,10 +382,147 @@ pub mod ffi {
         Box::into_raw(Box::new(ret))
     }
 
+    #[no_mangle]
+    pub unsafe extern "C" fn gmw_nat_div(
+        protocol: *mut Protocol,
+        a: *mut Nat,
+        b: *mut Nat,
+    ) -> *mut Nat {
+        let ret = Nat::div(&mut *protocol, &*a, &*b);
+        Box::into_raw(Box::new(ret))
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn gmw_nat_mod(
+        protocol: *mut Protocol,
+        a: *mut Nat,
+        b: *mut Nat,
+    ) -> *mut Nat {
+        let ret = Nat::modulo(&mut *protocol, &*a, &*b);
+        Box::into_raw(Box::new(ret))
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn gmw_nat_and(
+        protocol: *mut Protocol,
+        a: *mut Nat,
+        b: *mut Nat,
+    ) -> *mut Nat {
+        let ret = Nat::and(&mut *protocol, &*a, &*b);
+        Box::into_raw(Box::new(ret))
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn gmw_nat_or(
+        protocol: *mut Protocol,
+        a: *mut Nat,
+        b: *mut Nat,
+    ) -> *mut Nat {
+        let ret = Nat::or(&mut *protocol, &*a, &*b);
+        Box::into_raw(Box::new(ret))
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn gmw_nat_xor(
+        protocol: *mut Protocol,
+        a: *mut Nat,
+        b: *mut Nat,
+    ) -> *mut Nat {
+        let ret = Nat::xor(&mut *protocol, &*a, &*b);
+        Box::into_raw(Box::new(ret))
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn gmw_nat_not(protocol: *mut Protocol, a: *mut Nat) -> *mut Nat {
+        let ret = Nat::not(&mut *protocol, &*a);
+        Box::into_raw(Box::new(ret))
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn gmw_nat_shl(
+        protocol: *mut Protocol,
+        a: *mut Nat,
+        n: usize,
+    ) -> *mut Nat {
+        let ret = Nat::shl(&mut *protocol, &*a, n);
+        Box::into_raw(Box::new(ret))
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn gmw_nat_shr(
+        protocol: *mut Protocol,
+        a: *mut Nat,
+        n: usize,
+    ) -> *mut Nat {
+        let ret = Nat::shr(&mut *protocol, &*a, n);
+        Box::into_raw(Box::new(ret))
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn gmw_nat_min(
+        protocol: *mut Protocol,
+        a: *mut Nat,
+        b: *mut Nat,
+    ) -> *mut Nat {
+        let ret = Nat::min(&mut *protocol, &*a, &*b);
+        Box::into_raw(Box::new(ret))
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn gmw_nat_max(
+        protocol: *mut Protocol,
+        a: *mut Nat,
+        b: *mut Nat,
+    ) -> *mut Nat {
+        let ret = Nat::max(&mut *protocol, &*a, &*b);
+        Box::into_raw(Box::new(ret))
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn gmw_nat_zero_extend(
+        protocol: *mut Protocol,
+        a: *mut Nat,
+        bits: usize,
+    ) -> *mut Nat {
+        let ret = Nat::zero_extend(&mut *protocol, &*a, bits);
+        Box::into_raw(Box::new(ret))
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn gmw_nat_truncate(
+        protocol: *mut Protocol,
+        a: *mut Nat,
+        bits: usize,
+    ) -> *mut Nat {
+        let ret = Nat::truncate(&mut *protocol, &*a, bits);
+        Box::into_raw(Box::new(ret))
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn gmw_nat_from_bool(
+        protocol: *mut Protocol,
+        b_raw: *const Mutex<CachedBool>,
+        bits: usize,
+    ) -> *mut Nat {
+        let b = Bool::from_raw(b_raw);
+        let ret = Nat::from_bool(&mut *protocol, &b, bits);
+        Bool::into_raw(b);
+        Box::into_raw(Box::new(ret))
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn gmw_nat_is_nonzero(
+        protocol: *mut Protocol,
+        a: *mut Nat,
+    ) -> *const Mutex<CachedBool> {
+        let ret = Nat::is_nonzero(&mut *protocol, &*a);
+        Bool::into_raw(ret)
+    }
+
     #[no_mangle]
     pub unsafe extern "C" fn gmw_nat_mux(
         protocol: *mut Protocol,
-        guard_raw: *const RefCell<CachedBool>,
+        guard_raw: *const Mutex<CachedBool>,
         t: *mut Nat,
         f: *mut Nat,
     ) -> *mut Nat {
@@ -186,24 +537,49 @@ pub mod ffi {
         protocol: *mut Protocol,
         a: *mut Nat,
         b: *mut Nat,
-    ) -> *const RefCell<CachedBool> {
+    ) -> *const Mutex<CachedBool> {
         let ret = Nat::eq(&mut *protocol, &*a, &*b);
         Bool::into_raw(ret)
     }
 
+    #[no_mangle]
+    pub unsafe extern "C" fn gmw_nat_lt(
+        protocol: *mut Protocol,
+        a: *mut Nat,
+        b: *mut Nat,
+    ) -> *const Mutex<CachedBool> {
+        let ret = Nat::lt(&mut *protocol, &*a, &*b);
+        Bool::into_raw(ret)
+    }
+
     #[no_mangle]
     pub unsafe extern "C" fn gmw_nat_lte(
         protocol: *mut Protocol,
         a: *mut Nat,
         b: *mut Nat,
-    ) -> *const RefCell<CachedBool> {
+    ) -> *const Mutex<CachedBool> {
         let ret = Nat::lte(&mut *protocol, &*a, &*b);
         Bool::into_raw(ret)
     }
 
     #[no_mangle]
-    pub unsafe extern "C" fn gmw_nat32_get(protocol: *mut Protocol, share: *mut Nat) -> u32 {
-        u32::from_le_bytes(Nat::get(&mut *protocol, &*share).try_into().unwrap())
+    pub unsafe extern "C" fn gmw_nat_gt(
+        protocol: *mut Protocol,
+        a: *mut Nat,
+        b: *mut Nat,
+    ) -> *const Mutex<CachedBool> {
+        let ret = Nat::gt(&mut *protocol, &*a, &*b);
+        Bool::into_raw(ret)
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn gmw_nat_gte(
+        protocol: *mut Protocol,
+        a: *mut Nat,
+        b: *mut Nat,
+    ) -> *const Mutex<CachedBool> {
+        let ret = Nat::gte(&mut *protocol, &*a, &*b);
+        Bool::into_raw(ret)
     }
 
     #[no_mangle]
@@ -214,40 +590,109 @@ pub mod ffi {
     // Convenience
 
     #[no_mangle]
-    pub unsafe extern "C" fn gmw_share_send_nat32(
+    pub unsafe extern "C" fn gmw_share_send_nat(
         prg: *mut AesRng,
         channels: *mut *mut Channel,
         channels_len: usize,
-        clear: u32,
-    ) {
+        clear: *const u8,
+        bits: usize,
+    ) -> Status {
         let channels: &mut [&mut Channel] =
             std::mem::transmute(std::slice::from_raw_parts_mut(channels, channels_len));
-        share_send(&mut *prg, channels, &clear.to_le_bytes())
+        let clear = std::slice::from_raw_parts(clear, byte_len(bits));
+        error::status(share_send(&mut *prg, channels, clear))
     }
 
     #[no_mangle]
-    pub unsafe extern "C" fn gmw_share_recv_nat32(channel: *mut Channel) -> u32 {
-        let channel = &mut *channel;
-        let mut buf = [0u8; 4];
-        channel.read_exact(&mut buf).expect("TODO");
-        u32::from_le_bytes(buf)
+    pub unsafe extern "C" fn gmw_share_recv_nat(
+        channel: *mut Channel,
+        out: *mut u8,
+        bits: usize,
+    ) -> Status {
+        let out = std::slice::from_raw_parts_mut(out, byte_len(bits));
+        error::status((&mut *channel).read_exact(out).map_err(Into::into))
     }
 
     #[no_mangle]
-    pub unsafe extern "C" fn gmw_reveal_send_nat32(channel: *mut Channel, share: u32) {
-        let channel = &mut *channel;
-        channel.write_all(&share.to_le_bytes()).expect("TODO")
+    pub unsafe extern "C" fn gmw_reveal_send_nat(
+        channel: *mut Channel,
+        share: *const u8,
+        bits: usize,
+    ) -> Status {
+        let share = std::slice::from_raw_parts(share, byte_len(bits));
+        error::status((&mut *channel).write_all(share).map_err(Into::into))
     }
 
     #[no_mangle]
-    pub unsafe extern "C" fn gmw_reveal_recv_nat32(
+    pub unsafe extern "C" fn gmw_reveal_recv_nat(
         channels: *mut *mut Channel,
         channels_len: usize,
-    ) -> u32 {
+        out: *mut u8,
+        bits: usize,
+    ) -> Status {
         let channels: &mut [&mut Channel] =
             std::mem::transmute(std::slice::from_raw_parts_mut(channels, channels_len));
-        let mut buf = [0u8; 4];
-        reveal_recv(channels, &mut buf);
-        u32::from_le_bytes(buf)
+        let out = std::slice::from_raw_parts_mut(out, byte_len(bits));
+        error::status(reveal_recv(channels, out))
     }
+
+    fixed_width_ffi!(
+        Nat,
+        u8,
+        gmw_nat8_new,
+        gmw_nat8_constant,
+        gmw_nat8_get,
+        gmw_share_send_nat8,
+        gmw_share_recv_nat8,
+        gmw_reveal_send_nat8,
+        gmw_reveal_recv_nat8
+    );
+
+    fixed_width_ffi!(
+        Nat,
+        u16,
+        gmw_nat16_new,
+        gmw_nat16_constant,
+        gmw_nat16_get,
+        gmw_share_send_nat16,
+        gmw_share_recv_nat16,
+        gmw_reveal_send_nat16,
+        gmw_reveal_recv_nat16
+    );
+
+    fixed_width_ffi!(
+        Nat,
+        u32,
+        gmw_nat32_new,
+        gmw_nat32_constant,
+        gmw_nat32_get,
+        gmw_share_send_nat32,
+        gmw_share_recv_nat32,
+        gmw_reveal_send_nat32,
+        gmw_reveal_recv_nat32
+    );
+
+    fixed_width_ffi!(
+        Nat,
+        u64,
+        gmw_nat64_new,
+        gmw_nat64_constant,
+        gmw_nat64_get,
+        gmw_share_send_nat64,
+        gmw_share_recv_nat64,
+        gmw_reveal_send_nat64,
+        gmw_reveal_recv_nat64
+    );
+
+    fixed_width_ffi!(
+        Nat,
+        u128,
+        gmw_nat128_new,
+        gmw_nat128_constant,
+        gmw_nat128_get,
+        gmw_share_send_nat128,
+        gmw_share_recv_nat128,
+        gmw_reveal_send_nat128,
+        gmw_reveal_recv_nat128
+    );
 }
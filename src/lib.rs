@@ -1,5 +1,9 @@
 mod util;
 
+pub mod config;
+pub mod error;
 pub mod gmw;
+#[cfg(feature = "motion")]
 mod motion;
+mod native;
 pub mod yao;
@@ -1,25 +1,52 @@
+use crate::error::Result;
+use crate::gmw::{Backend, Preprocessing};
 use std::os::unix::io::RawFd;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// Serializes every call into one party's MOTION objects, which are not thread-safe. Share handles keep a clone,
+/// so that dropping one on another thread waits for the protocol instead of racing with it.
+#[derive(Debug, Clone, Default)]
+struct Lock(Arc<Mutex<()>>);
+
+impl Lock {
+    fn hold(&self) -> MutexGuard<'_, ()> {
+        // The lock protects no data, so a panic while holding it leaves nothing inconsistent.
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
 
 pub struct Transports {
     pub repr: *mut libc::c_void,
+    lock: Lock,
 }
 
 impl Transports {
-    pub fn new(my_id: usize, hosts: &[String], ports: &[u16]) -> Self {
-        let c_hosts: Vec<std::ffi::CString> = hosts
+    pub fn new(my_id: usize, hosts: &[String], ports: &[u16]) -> Result<Self> {
+        let c_hosts = hosts
             .iter()
-            .map(|h| std::ffi::CString::new(h.clone()).expect("TODO"))
-            .collect();
+            .map(|h| std::ffi::CString::new(h.clone()))
+            .collect::<std::result::Result<Vec<_>, _>>()?;
         let c_hosts_ptrs: Vec<*const libc::c_char> = c_hosts.iter().map(|h| h.as_ptr()).collect();
         let repr = unsafe {
             ffi::motion_transports_new(my_id, c_hosts_ptrs.as_ptr(), ports.as_ptr(), hosts.len())
         };
-        Self { repr }
+        Ok(Self {
+            repr,
+            lock: Lock::default(),
+        })
     }
 }
 
+// MOTION's objects are not tied to the thread that created them, and every call into them, including the deletion
+// when a handle is dropped, holds the party's `Lock`.
+unsafe impl Send for Transports {}
+unsafe impl Send for Party {}
+unsafe impl Send for Bool {}
+unsafe impl Send for Nat {}
+
 impl Drop for Transports {
     fn drop(&mut self) {
+        let _guard = self.lock.hold();
         unsafe { ffi::motion_transports_delete(self.repr) }
     }
 }
@@ -27,22 +54,29 @@ impl Drop for Transports {
 /// A wrapper around the [MOTION](https://github.com/encryptogroup/MOTION) `Party` object.
 pub struct Party {
     repr: *mut libc::c_void,
+    lock: Lock,
 }
 
 impl Party {
     pub fn new(my_id: usize, transports: &Transports) -> Self {
         let start = std::time::Instant::now();
-        let repr = unsafe { ffi::motion_party_new(my_id, transports.repr) };
-        Self { repr }
+        let lock = transports.lock.clone();
+        let repr = {
+            let _guard = lock.hold();
+            unsafe { ffi::motion_party_new(my_id, transports.repr) }
+        };
+        Self { repr, lock }
     }
 
     pub fn run(&self) {
+        let _guard = self.lock.hold();
         unsafe { ffi::motion_party_run(self.repr) }
     }
 }
 
 impl Drop for Party {
     fn drop(&mut self) {
+        let _guard = self.lock.hold();
         unsafe { ffi::motion_party_delete(self.repr) }
     }
 }
@@ -51,53 +85,77 @@ impl Drop for Party {
 #[derive(Debug)]
 pub struct Bool {
     repr: *mut libc::c_void,
+    lock: Lock,
 }
 
 impl Clone for Bool {
     fn clone(&self) -> Self {
+        let _guard = self.lock.hold();
         let repr = unsafe { ffi::motion_gmw_bool_copy(self.repr) };
-        Self { repr }
+        self.with(repr)
     }
 }
 
 impl Bool {
+    /// A handle to `repr`, under the same lock as `self`.
+    fn with(&self, repr: *mut libc::c_void) -> Self {
+        Self {
+            repr,
+            lock: self.lock.clone(),
+        }
+    }
+
     pub fn new(party: &mut Party, share: bool) -> Self {
+        let _guard = party.lock.hold();
         let repr = unsafe { ffi::motion_gmw_bool_new(party.repr, share) };
-        Self { repr }
+        Self {
+            repr,
+            lock: party.lock.clone(),
+        }
     }
 
     pub fn constant(party: &mut Party, value: bool) -> Self {
+        let _guard = party.lock.hold();
         let repr = unsafe { ffi::motion_gmw_bool_constant(party.repr, value) };
-        Self { repr }
+        Self {
+            repr,
+            lock: party.lock.clone(),
+        }
     }
 
     pub fn inv(&self) -> Self {
+        let _guard = self.lock.hold();
         let repr = unsafe { ffi::motion_gmw_bool_inv(self.repr) };
-        Self { repr }
+        self.with(repr)
     }
 
     pub fn xor(&self, other: &Self) -> Self {
+        let _guard = self.lock.hold();
         let repr = unsafe { ffi::motion_gmw_bool_xor(self.repr, other.repr) };
-        Self { repr }
+        self.with(repr)
     }
 
     pub fn mux(g: &Self, a: &Self, b: &Self) -> Self {
+        let _guard = g.lock.hold();
         let repr = unsafe { ffi::motion_gmw_bool_mux(g.repr, a.repr, b.repr) };
-        Self { repr }
+        g.with(repr)
     }
 
     pub fn and(&self, other: &Self) -> Self {
+        let _guard = self.lock.hold();
         let repr = unsafe { ffi::motion_gmw_bool_and(self.repr, other.repr) };
-        Self { repr }
+        self.with(repr)
     }
 
     pub fn get(&self) -> bool {
+        let _guard = self.lock.hold();
         unsafe { ffi::motion_gmw_bool_get(self.repr) }
     }
 }
 
 impl Drop for Bool {
     fn drop(&mut self) {
+        let _guard = self.lock.hold();
         unsafe { ffi::motion_gmw_bool_delete(self.repr) }
     }
 }
@@ -106,74 +164,105 @@ impl Drop for Bool {
 pub struct Nat {
     precision: usize,
     repr: *mut libc::c_void,
+    lock: Lock,
 }
 
 impl Clone for Nat {
     fn clone(&self) -> Self {
+        let _guard = self.lock.hold();
         let repr = unsafe { ffi::motion_gmw_nat_copy(self.repr) };
+        self.with(self.precision, repr)
+    }
+}
+
+impl Nat {
+    /// A handle to `repr`, under the same lock as `self`.
+    fn with(&self, precision: usize, repr: *mut libc::c_void) -> Self {
         Self {
-            precision: self.precision,
+            precision,
             repr,
+            lock: self.lock.clone(),
         }
     }
-}
 
-impl Nat {
     pub fn new(party: &mut Party, share: Vec<bool>) -> Self {
         let precision = share.len();
+        let _guard = party.lock.hold();
         let repr = unsafe { ffi::motion_gmw_nat_new(party.repr, share.as_ptr(), precision) };
-        Self { precision, repr }
+        Self {
+            precision,
+            repr,
+            lock: party.lock.clone(),
+        }
     }
 
     pub fn constant(party: &mut Party, value: Vec<bool>) -> Self {
         let precision = value.len();
+        let _guard = party.lock.hold();
         let repr = unsafe { ffi::motion_gmw_nat_constant(party.repr, value.as_ptr(), precision) };
-        Self { precision, repr }
+        Self {
+            precision,
+            repr,
+            lock: party.lock.clone(),
+        }
     }
 
     pub fn add(&self, other: &Self) -> Self {
         debug_assert_eq!(self.precision, other.precision);
-        let precision = self.precision;
+        let _guard = self.lock.hold();
         let repr = unsafe { ffi::motion_gmw_nat_add(self.repr, other.repr) };
-        Self { precision, repr }
+        self.with(self.precision, repr)
     }
 
     pub fn sub(&self, other: &Self) -> Self {
         debug_assert_eq!(self.precision, other.precision);
-        let precision = self.precision;
+        let _guard = self.lock.hold();
         let repr = unsafe { ffi::motion_gmw_nat_sub(self.repr, other.repr) };
-        Self { precision, repr }
+        self.with(self.precision, repr)
     }
 
     pub fn mul(&self, other: &Self) -> Self {
         debug_assert_eq!(self.precision, other.precision);
-        let precision = self.precision;
+        let _guard = self.lock.hold();
         let repr = unsafe { ffi::motion_gmw_nat_mul(self.repr, other.repr) };
-        Self { precision, repr }
+        self.with(self.precision, repr)
     }
 
     pub fn mux(g: &Bool, a: &Self, b: &Self) -> Self {
         debug_assert_eq!(a.precision, b.precision);
-        let precision = a.precision;
+        let _guard = a.lock.hold();
         let repr = unsafe { ffi::motion_gmw_nat_mux(g.repr, a.repr, b.repr) };
-        Self { precision, repr }
+        a.with(a.precision, repr)
     }
 
     pub fn eq(&self, other: &Self) -> Bool {
         debug_assert_eq!(self.precision, other.precision);
+        let _guard = self.lock.hold();
         let repr = unsafe { ffi::motion_gmw_nat_eq(self.repr, other.repr) };
-        Bool { repr }
+        Bool {
+            repr,
+            lock: self.lock.clone(),
+        }
     }
 
     pub fn gt(&self, other: &Self) -> Bool {
         debug_assert_eq!(self.precision, other.precision);
+        let _guard = self.lock.hold();
         let repr = unsafe { ffi::motion_gmw_nat_gt(self.repr, other.repr) };
-        Bool { repr }
+        Bool {
+            repr,
+            lock: self.lock.clone(),
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.precision
     }
 
     pub fn get(&self) -> Vec<bool> {
         let precision = self.precision;
         let mut ret = Vec::with_capacity(precision);
+        let _guard = self.lock.hold();
         unsafe {
             ffi::motion_gmw_nat_get(self.repr, ret.as_mut_ptr(), precision);
             ret.set_len(precision);
@@ -184,10 +273,156 @@ impl Nat {
 
 impl Drop for Nat {
     fn drop(&mut self) {
+        let _guard = self.lock.hold();
         unsafe { ffi::motion_gmw_nat_delete(self.repr) }
     }
 }
 
+/// Drives GMW circuits through MOTION, for use as a `gmw::Protocol` backend.
+pub struct Engine {
+    my_id: usize,
+    parties: usize,
+    party: Party,
+    transports: Transports,
+}
+
+impl Backend for Engine {
+    type Bool = Bool;
+    type Nat = Nat;
+
+    fn new(my_id: usize, hosts: &[String], ports: &[u16]) -> Result<Self> {
+        let transports = Transports::new(my_id, hosts, ports)?;
+        let party = Party::new(my_id, &transports);
+        Ok(Self {
+            my_id,
+            parties: hosts.len(),
+            party,
+            transports,
+        })
+    }
+
+    fn parties(&self) -> usize {
+        self.parties
+    }
+
+    fn bool_new(&mut self, share: bool) -> Bool {
+        Bool::new(&mut self.party, share)
+    }
+
+    fn bool_constant(&mut self, value: bool) -> Bool {
+        Bool::constant(&mut self.party, value)
+    }
+
+    fn bool_inv(&mut self, a: &Bool) -> Bool {
+        a.inv()
+    }
+
+    fn bool_xor(&mut self, a: &Bool, b: &Bool) -> Bool {
+        a.xor(b)
+    }
+
+    fn bool_and(&mut self, a: &Bool, b: &Bool) -> Bool {
+        a.and(b)
+    }
+
+    fn bool_mux(&mut self, g: &Bool, a: &Bool, b: &Bool) -> Bool {
+        Bool::mux(g, a, b)
+    }
+
+    fn bool_get(&mut self, share: &Bool) -> bool {
+        share.get()
+    }
+
+    fn nat_new(&mut self, share: Vec<bool>) -> Nat {
+        Nat::new(&mut self.party, share)
+    }
+
+    fn nat_constant(&mut self, value: Vec<bool>) -> Nat {
+        Nat::constant(&mut self.party, value)
+    }
+
+    fn nat_add(&mut self, a: &Nat, b: &Nat) -> Nat {
+        a.add(b)
+    }
+
+    fn nat_sub(&mut self, a: &Nat, b: &Nat) -> Nat {
+        a.sub(b)
+    }
+
+    fn nat_mul(&mut self, a: &Nat, b: &Nat) -> Nat {
+        a.mul(b)
+    }
+
+    fn nat_mux(&mut self, g: &Bool, a: &Nat, b: &Nat) -> Nat {
+        Nat::mux(g, a, b)
+    }
+
+    fn nat_eq(&mut self, a: &Nat, b: &Nat) -> Bool {
+        a.eq(b)
+    }
+
+    fn nat_gt(&mut self, a: &Nat, b: &Nat) -> Bool {
+        a.gt(b)
+    }
+
+    fn nat_get(&mut self, share: &Nat) -> Vec<bool> {
+        share.get()
+    }
+
+    fn nat_width(&mut self, share: &Nat) -> usize {
+        share.width()
+    }
+
+    /// MOTION has no gate that splits a share, so the bits are peeled off from the top: bit `i` is set exactly when
+    /// what remains of `a` exceeds `2^i - 1`, and is then subtracted.
+    fn nat_bits(&mut self, a: &Nat) -> Vec<Bool> {
+        let width = a.width();
+        let zero = Nat::constant(&mut self.party, vec![false; width]);
+        let mut rest = a.clone();
+        let mut bits = Vec::with_capacity(width);
+        for i in (0..width).rev() {
+            let below = Nat::constant(&mut self.party, (0..width).map(|j| j < i).collect());
+            let bit = rest.gt(&below);
+            let power = Nat::constant(&mut self.party, (0..width).map(|j| j == i).collect());
+            rest = rest.sub(&Nat::mux(&bit, &power, &zero));
+            bits.push(bit);
+        }
+        bits.reverse();
+        bits
+    }
+
+    /// Sums `2^i` for every set bit `i`, as MOTION has no gate that joins shares.
+    fn nat_from_bits(&mut self, bits: &[Bool]) -> Nat {
+        let width = bits.len();
+        let zero = Nat::constant(&mut self.party, vec![false; width]);
+        let mut sum = zero.clone();
+        for (i, bit) in bits.iter().enumerate() {
+            let power = Nat::constant(&mut self.party, (0..width).map(|j| j == i).collect());
+            sum = sum.add(&Nat::mux(bit, &power, &zero));
+        }
+        sum
+    }
+
+    /// MOTION generates its own triples as part of `run`.
+    fn preprocess(&mut self, _triples: usize, _bits: usize) -> Result<()> {
+        Ok(())
+    }
+
+    fn preprocessing(&mut self) -> Option<&mut Preprocessing> {
+        None
+    }
+
+    /// MOTION aborts on network failures itself, so errors never reach this point.
+    fn run(&mut self) -> Result<()> {
+        self.party.run();
+        Ok(())
+    }
+
+    fn reset(&mut self) {
+        self.party = Party::new(self.my_id, &self.transports);
+    }
+}
+
 mod ffi {
     use super::*;
 
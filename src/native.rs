@@ -0,0 +1,680 @@
+//! A pure-Rust GMW engine, available as the `gmw::Native` backend alongside
+//! [MOTION](https://github.com/encryptogroup/MOTION), and the default backend when the `motion` feature is disabled.
+//! Its API mirrors the `motion` wrapper so that `gmw` can use either one.
+
+use crate::config::Config;
+use crate::error::{Error, Result};
+use crate::gmw::Backend;
+use crate::util;
+#[cfg(feature = "async")]
+use crate::util::AsyncChannel;
+use crate::util::{Channel, Mesh};
+use ocelot::ot::{AlszReceiver, AlszSender};
+use rand::Rng;
+use scuttlebutt::AesRng;
+use std::io::{Read, Write};
+use std::sync::Arc;
+use std::sync::Mutex;
+
+mod circuit;
+#[cfg(feature = "async")]
+use circuit::Evaluation;
+use circuit::{Circuit, Gate};
+
+mod triples;
+pub use triples::generate_arith;
+
+mod simulator;
+pub use simulator::{Cost, Simulator};
+
+mod preprocessing;
+pub use preprocessing::Preprocessing;
+
+/// The pairwise channels between this party and every other party, along with per-peer OT state.
+pub struct Peers {
+    channels: Vec<Option<Channel>>,
+    senders: Vec<Option<AlszSender>>,
+    receivers: Vec<Option<AlszReceiver>>,
+    rng: AesRng,
+}
+
+impl Peers {
+    pub fn new(channels: Vec<Option<Channel>>) -> Self {
+        let len = channels.len();
+        Self {
+            channels,
+            senders: (0..len).map(|_| None).collect(),
+            receivers: (0..len).map(|_| None).collect(),
+            rng: AesRng::new(),
+        }
+    }
+
+    pub fn rng(&mut self) -> &mut AesRng {
+        &mut self.rng
+    }
+
+    /// Reconstructs XOR-shared bits by exchanging this party's shares with every other party.
+    pub fn open(&mut self, shares: &[bool]) -> Result<Vec<bool>> {
+        let mut padded = shares.to_vec();
+        padded.resize(((shares.len() + 7) / 8) * 8, false);
+        let mut opened = util::from_bits(&padded);
+
+        for c in self.channels.iter_mut().flatten() {
+            c.write_all(&opened)?;
+            c.flush()?;
+        }
+
+        let mut buf = vec![0u8; opened.len()];
+        for c in self.channels.iter_mut().flatten() {
+            c.read_exact(&mut buf)?;
+            util::xor_inplace(&mut opened, &buf);
+        }
+
+        let mut ret = util::to_bits(&opened);
+        ret.truncate(shares.len());
+        Ok(ret)
+    }
+
+    /// Like `open`, but over the channels of a `gmw::AsyncProtocol`, waiting on the peers without blocking the
+    /// thread.
+    #[cfg(feature = "async")]
+    pub(crate) async fn open_async(&mut self, shares: &[bool]) -> Result<Vec<bool>> {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let mut padded = shares.to_vec();
+        padded.resize(shares.len().div_ceil(8) * 8, false);
+        let mut opened = util::from_bits(&padded);
+
+        for c in self.async_channels() {
+            c.write_all(&opened).await?;
+            c.flush().await?;
+        }
+
+        let mut buf = vec![0u8; opened.len()];
+        for c in self.async_channels() {
+            c.read_exact(&mut buf).await?;
+            util::xor_inplace(&mut opened, &buf);
+        }
+
+        let mut ret = util::to_bits(&opened);
+        ret.truncate(shares.len());
+        Ok(ret)
+    }
+
+    /// The async channels beneath this party's channels, all of which must be `Channel::Async`.
+    #[cfg(feature = "async")]
+    pub(crate) fn async_channels(&mut self) -> impl Iterator<Item = &mut AsyncChannel> {
+        self.channels.iter_mut().flatten().map(unbridge)
+    }
+
+    /// The async channel to party `id`, if there is one.
+    #[cfg(feature = "async")]
+    pub(crate) fn async_channel(&mut self, id: usize) -> Option<&mut AsyncChannel> {
+        self.channels.get_mut(id)?.as_mut().map(unbridge)
+    }
+
+    /// Reconstructs additively shared words modulo `2^64` by exchanging this party's shares with every other
+    /// party.
+    pub fn open_words(&mut self, shares: &[u64]) -> Result<Vec<u64>> {
+        let bytes: Vec<u8> = shares.iter().flat_map(|s| s.to_le_bytes()).collect();
+        for c in self.channels.iter_mut().flatten() {
+            c.write_all(&bytes)?;
+            c.flush()?;
+        }
+
+        let mut opened = shares.to_vec();
+        let mut buf = vec![0u8; bytes.len()];
+        for c in self.channels.iter_mut().flatten() {
+            c.read_exact(&mut buf)?;
+            for (o, b) in opened.iter_mut().zip(buf.chunks(8)) {
+                *o = o.wrapping_add(u64::from_le_bytes(b.try_into().unwrap()));
+            }
+        }
+
+        Ok(opened)
+    }
+}
+
+#[cfg(feature = "async")]
+fn unbridge(channel: &mut Channel) -> &mut AsyncChannel {
+    match channel {
+        Channel::Async(bridge) => bridge.get_mut(),
+        _ => unreachable!("an async session only has async channels"),
+    }
+}
+
+pub struct Transports {
+    peers: Arc<Mutex<Peers>>,
+}
+
+impl Transports {
+    pub fn new(my_id: usize, hosts: &[String], ports: &[u16]) -> Result<Self> {
+        Ok(Self::from_channels(
+            Mesh::new(my_id, hosts, ports).connect()?,
+        ))
+    }
+
+    /// Uses already-established channels, where `channels[i]` connects to party `i` and this party's own entry
+    /// is `None`.
+    pub fn from_channels(channels: Vec<Option<Channel>>) -> Self {
+        Self {
+            peers: Arc::new(Mutex::new(Peers::new(channels))),
+        }
+    }
+}
+
+/// A GMW party evaluating a single circuit. A new `Party` is created for each batch of delayed shares.
+pub struct Party {
+    my_id: usize,
+    circuit: Arc<Mutex<Circuit>>,
+    peers: Arc<Mutex<Peers>>,
+}
+
+impl Party {
+    pub fn new(my_id: usize, transports: &Transports) -> Self {
+        Self {
+            my_id,
+            circuit: Arc::new(Mutex::new(Circuit::new())),
+            peers: transports.peers.clone(),
+        }
+    }
+
+    /// Evaluates the circuit, consuming preprocessed triples from `store` before generating any more.
+    pub fn run(&self, store: &mut Preprocessing) -> Result<()> {
+        let circuit = &mut *self.circuit.lock().unwrap();
+        let peers = &mut *self.peers.lock().unwrap();
+        let count = circuit.and_count();
+        let mut triples = store.take_triples(count);
+        let fresh = triples::generate(self.my_id, peers, count - triples.len())?;
+        triples.extend(fresh);
+        circuit.evaluate(self.my_id, peers, triples)
+    }
+}
+
+/// Generates `triples` multiplication triples and `bits` random bits with the other parties.
+pub(crate) fn preprocess(
+    my_id: usize,
+    peers: &mut Peers,
+    triples: usize,
+    bits: usize,
+) -> Result<Preprocessing> {
+    let mut store = Preprocessing::new();
+    store.push_triples(triples::generate(my_id, peers, triples)?);
+    store.push_bits((0..bits).map(|_| peers.rng.gen()).collect());
+    Ok(store)
+}
+
+/// A party's circuit part-way through evaluation, for a caller that opens each round's masked values itself, as
+/// `gmw::AsyncProtocol` does.
+#[cfg(feature = "async")]
+pub(crate) struct Rounds {
+    circuit: Arc<Mutex<Circuit>>,
+    evaluation: Evaluation,
+}
+
+#[cfg(feature = "async")]
+impl Rounds {
+    /// Evaluates up to the next round, returning the masked values to open, or `None` once the circuit is done.
+    pub fn masked(&mut self) -> Option<Vec<bool>> {
+        self.evaluation.masked(&self.circuit.lock().unwrap())
+    }
+
+    pub fn resume(&mut self, opened: &[bool]) {
+        self.evaluation
+            .resume(&self.circuit.lock().unwrap(), opened);
+    }
+
+    /// Stores the evaluated shares, where `Bool::get` and `Nat::get` read them.
+    pub fn finish(self) {
+        self.circuit.lock().unwrap().finish(self.evaluation);
+    }
+}
+
+/// A boolean GMW share, represented by a wire in the party's circuit.
+#[derive(Clone)]
+pub struct Bool {
+    circuit: Arc<Mutex<Circuit>>,
+    wire: usize,
+}
+
+impl std::fmt::Debug for Bool {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Bool").field("wire", &self.wire).finish()
+    }
+}
+
+impl Bool {
+    fn gate(circuit: &Arc<Mutex<Circuit>>, gate: Gate) -> Self {
+        let wire = circuit.lock().unwrap().push(gate);
+        Self {
+            circuit: circuit.clone(),
+            wire,
+        }
+    }
+
+    pub fn new(party: &mut Party, share: bool) -> Self {
+        Self::gate(&party.circuit, Gate::Input(share))
+    }
+
+    pub fn constant(party: &mut Party, value: bool) -> Self {
+        Self::gate(&party.circuit, Gate::Constant(value))
+    }
+
+    pub fn inv(&self) -> Self {
+        Self::gate(&self.circuit, Gate::Inv(self.wire))
+    }
+
+    pub fn xor(&self, other: &Self) -> Self {
+        Self::gate(&self.circuit, Gate::Xor(self.wire, other.wire))
+    }
+
+    pub fn mux(g: &Self, a: &Self, b: &Self) -> Self {
+        let wire = g.circuit.lock().unwrap().mux(g.wire, a.wire, b.wire);
+        Self {
+            circuit: g.circuit.clone(),
+            wire,
+        }
+    }
+
+    pub fn and(&self, other: &Self) -> Self {
+        Self::gate(&self.circuit, Gate::And(self.wire, other.wire))
+    }
+
+    pub fn get(&self) -> bool {
+        self.circuit.lock().unwrap().share(self.wire)
+    }
+}
+
+/// A natural number GMW share, represented by its wires in the party's circuit, least significant bit first.
+#[derive(Clone)]
+pub struct Nat {
+    circuit: Arc<Mutex<Circuit>>,
+    wires: Vec<usize>,
+}
+
+impl std::fmt::Debug for Nat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Nat").field("wires", &self.wires).finish()
+    }
+}
+
+impl Nat {
+    fn from_wires(circuit: &Arc<Mutex<Circuit>>, wires: Vec<usize>) -> Self {
+        Self {
+            circuit: circuit.clone(),
+            wires,
+        }
+    }
+
+    pub fn new(party: &mut Party, share: Vec<bool>) -> Self {
+        let wires = {
+            let circuit = &mut *party.circuit.lock().unwrap();
+            share
+                .into_iter()
+                .map(|b| circuit.push(Gate::Input(b)))
+                .collect()
+        };
+        Self::from_wires(&party.circuit, wires)
+    }
+
+    pub fn constant(party: &mut Party, value: Vec<bool>) -> Self {
+        let wires = {
+            let circuit = &mut *party.circuit.lock().unwrap();
+            value.into_iter().map(|b| circuit.constant(b)).collect()
+        };
+        Self::from_wires(&party.circuit, wires)
+    }
+
+    pub fn add(&self, other: &Self) -> Self {
+        debug_assert_eq!(self.wires.len(), other.wires.len());
+        let wires = self.circuit.lock().unwrap().add(&self.wires, &other.wires);
+        Self::from_wires(&self.circuit, wires)
+    }
+
+    pub fn sub(&self, other: &Self) -> Self {
+        debug_assert_eq!(self.wires.len(), other.wires.len());
+        let (wires, _) = self.circuit.lock().unwrap().sub(&self.wires, &other.wires);
+        Self::from_wires(&self.circuit, wires)
+    }
+
+    pub fn mul(&self, other: &Self) -> Self {
+        debug_assert_eq!(self.wires.len(), other.wires.len());
+        let wires = self.circuit.lock().unwrap().mul(&self.wires, &other.wires);
+        Self::from_wires(&self.circuit, wires)
+    }
+
+    pub fn mux(g: &Bool, a: &Self, b: &Self) -> Self {
+        debug_assert_eq!(a.wires.len(), b.wires.len());
+        let wires = {
+            let circuit = &mut *a.circuit.lock().unwrap();
+            a.wires
+                .iter()
+                .zip(b.wires.iter())
+                .map(|(&a, &b)| circuit.mux(g.wire, a, b))
+                .collect()
+        };
+        Self::from_wires(&a.circuit, wires)
+    }
+
+    pub fn eq(&self, other: &Self) -> Bool {
+        debug_assert_eq!(self.wires.len(), other.wires.len());
+        let wire = self.circuit.lock().unwrap().eq(&self.wires, &other.wires);
+        Bool {
+            circuit: self.circuit.clone(),
+            wire,
+        }
+    }
+
+    pub fn gt(&self, other: &Self) -> Bool {
+        debug_assert_eq!(self.wires.len(), other.wires.len());
+        let wire = self.circuit.lock().unwrap().gt(&self.wires, &other.wires);
+        Bool {
+            circuit: self.circuit.clone(),
+            wire,
+        }
+    }
+
+    pub fn get(&self) -> Vec<bool> {
+        let circuit = self.circuit.lock().unwrap();
+        self.wires.iter().map(|&w| circuit.share(w)).collect()
+    }
+
+    pub fn width(&self) -> usize {
+        self.wires.len()
+    }
+
+    pub fn bits(&self) -> Vec<Bool> {
+        self.wires
+            .iter()
+            .map(|&wire| Bool {
+                circuit: self.circuit.clone(),
+                wire,
+            })
+            .collect()
+    }
+}
+
+/// Drives GMW circuits through the pure-Rust engine, for use as a `gmw::Protocol` backend.
+pub struct Engine {
+    my_id: usize,
+    party: Party,
+    transports: Transports,
+    store: Preprocessing,
+    /// Whether another owner of the channels runs the interactive steps, so that the engine itself must not.
+    detached: bool,
+}
+
+impl Engine {
+    /// Creates an engine over already-established channels, where `channels[i]` connects to party `i` and this
+    /// party's own entry is `None`.
+    pub fn from_channels(my_id: usize, channels: Vec<Option<Channel>>) -> Self {
+        let transports = Transports::from_channels(channels);
+        let party = Party::new(my_id, &transports);
+        Self {
+            my_id,
+            party,
+            transports,
+            store: Preprocessing::new(),
+            detached: false,
+        }
+    }
+
+    /// Creates an engine that only builds circuits, whose preprocessing and evaluation go through `Rounds` and
+    /// `preprocess` instead. Running it directly fails rather than evaluating without its peers.
+    #[cfg(feature = "async")]
+    pub(crate) fn detached(my_id: usize, parties: usize) -> Self {
+        let mut engine = Self::from_channels(my_id, (0..parties).map(|_| None).collect());
+        engine.detached = true;
+        engine
+    }
+
+    /// How many more triples the current circuit needs than are preprocessed.
+    #[cfg(feature = "async")]
+    pub(crate) fn missing_triples(&self) -> usize {
+        let count = self.party.circuit.lock().unwrap().and_count();
+        count.saturating_sub(self.store.triples())
+    }
+
+    /// Starts evaluating the current circuit on preprocessed triples, of which there must be enough.
+    #[cfg(feature = "async")]
+    pub(crate) fn rounds(&mut self) -> Rounds {
+        let circuit = self.party.circuit.clone();
+        let evaluation = {
+            let circuit = circuit.lock().unwrap();
+            let triples = self.store.take_triples(circuit.and_count());
+            circuit.start(self.my_id, triples)
+        };
+        Rounds {
+            circuit,
+            evaluation,
+        }
+    }
+
+    fn check_attached(&self) -> Result<()> {
+        if self.detached {
+            return Err(Error::Unsupported(
+                "this engine's channels belong to an AsyncProtocol, which must evaluate it",
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl Backend for Engine {
+    type Bool = Bool;
+    type Nat = Nat;
+
+    fn new(my_id: usize, hosts: &[String], ports: &[u16]) -> Result<Self> {
+        let transports = Transports::new(my_id, hosts, ports)?;
+        let party = Party::new(my_id, &transports);
+        Ok(Self {
+            my_id,
+            party,
+            transports,
+            store: Preprocessing::new(),
+            detached: false,
+        })
+    }
+
+    fn from_config(config: &Config) -> Result<Self> {
+        let mut mesh =
+            Mesh::new(config.id, &config.hosts, &config.ports).options(config.timeouts.options());
+        if let Some(identity) = &config.tls {
+            mesh = mesh.tls(identity);
+        }
+        let engine = Self::from_channels(config.id, mesh.connect()?);
+        engine.transports.peers.lock().unwrap().rng = config.seed.rng(config.id);
+        Ok(engine)
+    }
+
+    fn parties(&self) -> usize {
+        self.transports.peers.lock().unwrap().channels.len()
+    }
+
+    fn bool_new(&mut self, share: bool) -> Bool {
+        Bool::new(&mut self.party, share)
+    }
+
+    fn bool_constant(&mut self, value: bool) -> Bool {
+        Bool::constant(&mut self.party, value)
+    }
+
+    fn bool_inv(&mut self, a: &Bool) -> Bool {
+        a.inv()
+    }
+
+    fn bool_xor(&mut self, a: &Bool, b: &Bool) -> Bool {
+        a.xor(b)
+    }
+
+    fn bool_and(&mut self, a: &Bool, b: &Bool) -> Bool {
+        a.and(b)
+    }
+
+    fn bool_mux(&mut self, g: &Bool, a: &Bool, b: &Bool) -> Bool {
+        Bool::mux(g, a, b)
+    }
+
+    fn bool_get(&mut self, share: &Bool) -> bool {
+        share.get()
+    }
+
+    fn nat_new(&mut self, share: Vec<bool>) -> Nat {
+        Nat::new(&mut self.party, share)
+    }
+
+    fn nat_constant(&mut self, value: Vec<bool>) -> Nat {
+        Nat::constant(&mut self.party, value)
+    }
+
+    fn nat_add(&mut self, a: &Nat, b: &Nat) -> Nat {
+        a.add(b)
+    }
+
+    fn nat_sub(&mut self, a: &Nat, b: &Nat) -> Nat {
+        a.sub(b)
+    }
+
+    fn nat_mul(&mut self, a: &Nat, b: &Nat) -> Nat {
+        a.mul(b)
+    }
+
+    fn nat_mux(&mut self, g: &Bool, a: &Nat, b: &Nat) -> Nat {
+        Nat::mux(g, a, b)
+    }
+
+    fn nat_eq(&mut self, a: &Nat, b: &Nat) -> Bool {
+        a.eq(b)
+    }
+
+    fn nat_gt(&mut self, a: &Nat, b: &Nat) -> Bool {
+        a.gt(b)
+    }
+
+    fn nat_get(&mut self, share: &Nat) -> Vec<bool> {
+        share.get()
+    }
+
+    fn nat_width(&mut self, share: &Nat) -> usize {
+        share.width()
+    }
+
+    fn nat_bits(&mut self, a: &Nat) -> Vec<Bool> {
+        a.bits()
+    }
+
+    fn nat_from_bits(&mut self, bits: &[Bool]) -> Nat {
+        let wires = bits.iter().map(|b| b.wire).collect();
+        Nat::from_wires(&self.party.circuit, wires)
+    }
+
+    fn preprocess(&mut self, triples: usize, bits: usize) -> Result<()> {
+        self.check_attached()?;
+        let peers = &mut *self.transports.peers.lock().unwrap();
+        let fresh = preprocess(self.my_id, peers, triples, bits)?;
+        self.store.append(fresh);
+        Ok(())
+    }
+
+    fn preprocessing(&mut self) -> Option<&mut Preprocessing> {
+        Some(&mut self.store)
+    }
+
+    fn run(&mut self) -> Result<()> {
+        self.check_attached()?;
+        self.party.run(&mut self.store)
+    }
+
+    fn reset(&mut self) {
+        self.party = Party::new(self.my_id, &self.transports);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::harness;
+    use std::thread;
+
+    /// Runs `f` as each of `n` parties, connected over loopback TCP, and XORs the resulting shares together.
+    fn reconstruct<F>(n: usize, f: F) -> Vec<bool>
+    where
+        F: Fn(&mut Party) -> Vec<Box<dyn Fn() -> Vec<bool>>> + Send + Sync + Copy + 'static,
+    {
+        let hosts = vec!["127.0.0.1".to_owned(); n];
+        let ports = harness::free_ports(n);
+        let handles: Vec<_> = (0..n)
+            .map(|id| {
+                let (hosts, ports) = (hosts.clone(), ports.clone());
+                thread::spawn(move || {
+                    let transports = Transports::new(id, &hosts, &ports).unwrap();
+                    let mut party = Party::new(id, &transports);
+                    let outputs = f(&mut party);
+                    party.run(&mut Preprocessing::new()).unwrap();
+                    outputs.iter().flat_map(|o| o()).collect::<Vec<bool>>()
+                })
+            })
+            .collect();
+
+        handles
+            .into_iter()
+            .map(|h| h.join().unwrap())
+            .reduce(|acc, shares| acc.iter().zip(shares).map(|(a, b)| a ^ b).collect())
+            .unwrap()
+    }
+
+    #[test]
+    fn bool_gates() {
+        for n in [2, 3] {
+            let out = reconstruct(n, |party| {
+                // Party 0 contributes the inputs, the others contribute zero shares.
+                let leader = party.my_id == 0;
+                let t = Bool::new(party, leader);
+                let f = Bool::new(party, false);
+                let c = Bool::constant(party, true);
+                let gates = vec![
+                    t.and(&c),
+                    t.and(&f),
+                    t.xor(&c),
+                    f.inv(),
+                    Bool::mux(&f, &t, &f),
+                ];
+                gates
+                    .into_iter()
+                    .map(|g| Box::new(move || vec![g.get()]) as Box<dyn Fn() -> Vec<bool>>)
+                    .collect()
+            });
+            assert_eq!(out, vec![true, false, false, true, false]);
+        }
+    }
+
+    #[test]
+    fn nat_arithmetic() {
+        let a: u8 = 200;
+        let b: u8 = 27;
+        let out = reconstruct(3, move |party| {
+            let my_id = party.my_id;
+            let a_bits = util::to_bits(&[if my_id == 0 { a } else { 0 }]);
+            let b_bits = util::to_bits(&[if my_id == 0 { b } else { 0 }]);
+            let x = Nat::new(party, a_bits);
+            let y = Nat::new(party, b_bits);
+            let results = vec![x.add(&y), x.sub(&y), x.mul(&y)];
+            let flags = vec![x.eq(&y), x.gt(&y), y.gt(&x)];
+            let mut outputs: Vec<Box<dyn Fn() -> Vec<bool>>> = Vec::new();
+            for r in results {
+                outputs.push(Box::new(move || r.get()));
+            }
+            for f in flags {
+                outputs.push(Box::new(move || vec![f.get()]));
+            }
+            outputs
+        });
+
+        let bytes = util::from_bits(&out[..24]);
+        assert_eq!(
+            bytes,
+            vec![a.wrapping_add(b), a.wrapping_sub(b), a.wrapping_mul(b)]
+        );
+        assert_eq!(&out[24..], &[false, true, false]);
+    }
+}
@@ -0,0 +1,312 @@
+use crate::error::Result;
+use crate::native::triples::Triple;
+use crate::native::Peers;
+
+/// A single gate in a GMW circuit. Operands refer to the output wires of earlier gates.
+#[derive(Debug, Clone, Copy)]
+pub enum Gate {
+    Input(bool),
+    Constant(bool),
+    Inv(usize),
+    Xor(usize, usize),
+    And(usize, usize),
+}
+
+/// A boolean circuit over XOR-shared wires, built lazily and evaluated on `run`.
+///
+/// Gates are appended in topological order, so the index of a gate is also the index of its output wire.
+#[derive(Debug, Default)]
+pub struct Circuit {
+    gates: Vec<Gate>,
+    shares: Vec<bool>,
+}
+
+impl Circuit {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, gate: Gate) -> usize {
+        self.gates.push(gate);
+        self.gates.len() - 1
+    }
+
+    pub fn and_count(&self) -> usize {
+        self.gates
+            .iter()
+            .filter(|g| matches!(g, Gate::And(_, _)))
+            .count()
+    }
+
+    pub fn share(&self, wire: usize) -> bool {
+        self.shares[wire]
+    }
+
+    pub fn constant(&mut self, value: bool) -> usize {
+        self.push(Gate::Constant(value))
+    }
+
+    pub fn inv(&mut self, a: usize) -> usize {
+        self.push(Gate::Inv(a))
+    }
+
+    pub fn xor(&mut self, a: usize, b: usize) -> usize {
+        self.push(Gate::Xor(a, b))
+    }
+
+    pub fn and(&mut self, a: usize, b: usize) -> usize {
+        self.push(Gate::And(a, b))
+    }
+
+    pub fn mux(&mut self, g: usize, a: usize, b: usize) -> usize {
+        let axb = self.xor(a, b);
+        let t = self.and(g, axb);
+        self.xor(b, t)
+    }
+
+    // SOURCE (lightly modified): https://github.com/emp-toolkit/emp-tool
+    pub fn add(&mut self, a: &[usize], b: &[usize]) -> Vec<usize> {
+        debug_assert_eq!(a.len(), b.len());
+        let size = a.len();
+        let mut dest = Vec::with_capacity(size);
+        if size == 0 {
+            return dest;
+        }
+
+        let mut carry = self.constant(false);
+
+        for i in 0..(size - 1) {
+            let axc = self.xor(a[i], carry);
+            let bxc = self.xor(b[i], carry);
+            dest.push(self.xor(a[i], bxc));
+            let t = self.and(axc, bxc);
+            carry = self.xor(carry, t);
+        }
+
+        let axb = self.xor(a[size - 1], b[size - 1]);
+        dest.push(self.xor(carry, axb));
+        dest
+    }
+
+    /// Computes `a - b`, returning the difference and the borrow out of the most significant bit.
+    pub fn sub(&mut self, a: &[usize], b: &[usize]) -> (Vec<usize>, usize) {
+        debug_assert_eq!(a.len(), b.len());
+        let mut dest = Vec::with_capacity(a.len());
+        let mut borrow = self.constant(false);
+
+        for i in 0..a.len() {
+            let bxa = self.xor(a[i], b[i]);
+            let bxc = self.xor(borrow, b[i]);
+            dest.push(self.xor(bxa, borrow));
+            let t = self.and(bxa, bxc);
+            borrow = self.xor(borrow, t);
+        }
+
+        (dest, borrow)
+    }
+
+    pub fn mul(&mut self, a: &[usize], b: &[usize]) -> Vec<usize> {
+        debug_assert_eq!(a.len(), b.len());
+        let size = a.len();
+        let zero = self.constant(false);
+        let mut dest = vec![zero; size];
+
+        for i in 0..size {
+            let partial: Vec<usize> = (0..(size - i)).map(|j| self.and(a[j], b[i])).collect();
+            let sum = self.add(&dest[i..], &partial);
+            dest[i..].copy_from_slice(&sum);
+        }
+
+        dest
+    }
+
+    pub fn eq(&mut self, a: &[usize], b: &[usize]) -> usize {
+        debug_assert_eq!(a.len(), b.len());
+        let mut layer: Vec<usize> = a
+            .iter()
+            .zip(b.iter())
+            .map(|(&a, &b)| {
+                let axb = self.xor(a, b);
+                self.inv(axb)
+            })
+            .collect();
+
+        if layer.is_empty() {
+            return self.constant(true);
+        }
+
+        // Reduce pairwise so the AND depth is logarithmic in the precision.
+        while layer.len() > 1 {
+            layer = layer
+                .chunks(2)
+                .map(|pair| match *pair {
+                    [x, y] => self.and(x, y),
+                    [x] => x,
+                    _ => unreachable!(),
+                })
+                .collect();
+        }
+
+        layer[0]
+    }
+
+    pub fn gt(&mut self, a: &[usize], b: &[usize]) -> usize {
+        let (_, borrow) = self.sub(b, a);
+        borrow
+    }
+
+    /// Groups wires by multiplicative depth. Every AND gate in a level depends only on earlier levels, and the
+    /// wires within a level remain in topological order.
+    fn levels(&self) -> Vec<Vec<usize>> {
+        let mut depth = vec![0usize; self.gates.len()];
+        for (w, g) in self.gates.iter().enumerate() {
+            depth[w] = match *g {
+                Gate::Input(_) | Gate::Constant(_) => 0,
+                Gate::Inv(a) => depth[a],
+                Gate::Xor(a, b) => depth[a].max(depth[b]),
+                Gate::And(a, b) => depth[a].max(depth[b]) + 1,
+            };
+        }
+
+        let max_depth = depth.iter().copied().max().unwrap_or(0);
+        let mut levels = vec![Vec::new(); max_depth + 1];
+        for (w, d) in depth.into_iter().enumerate() {
+            levels[d].push(w);
+        }
+
+        levels
+    }
+
+    /// Evaluates every gate in the clear, treating inputs as plaintext values rather than shares. Returns the
+    /// number of AND gates in each round that `evaluate` would need.
+    pub fn simulate(&mut self) -> Vec<usize> {
+        let mut values: Vec<bool> = Vec::with_capacity(self.gates.len());
+        for g in self.gates.iter() {
+            let value = match *g {
+                Gate::Input(value) | Gate::Constant(value) => value,
+                Gate::Inv(a) => !values[a],
+                Gate::Xor(a, b) => values[a] ^ values[b],
+                Gate::And(a, b) => values[a] & values[b],
+            };
+            values.push(value);
+        }
+        self.shares = values;
+
+        self.levels()
+            .iter()
+            .map(|level| {
+                level
+                    .iter()
+                    .filter(|&&w| matches!(self.gates[w], Gate::And(_, _)))
+                    .count()
+            })
+            .filter(|&ands| ands > 0)
+            .collect()
+    }
+
+    /// Evaluates every gate, processing all AND gates of equal multiplicative depth in a single round.
+    pub fn evaluate(
+        &mut self,
+        my_id: usize,
+        peers: &mut Peers,
+        triples: Vec<Triple>,
+    ) -> Result<()> {
+        let mut evaluation = self.start(my_id, triples);
+        while let Some(masked) = evaluation.masked(self) {
+            let opened = peers.open(&masked)?;
+            evaluation.resume(self, &opened);
+        }
+        self.finish(evaluation);
+        Ok(())
+    }
+
+    /// Starts evaluating the circuit one round at a time, for callers that open the masked values themselves.
+    pub fn start(&self, my_id: usize, triples: Vec<Triple>) -> Evaluation {
+        debug_assert!(triples.len() >= self.and_count());
+        Evaluation {
+            leader: my_id == 0,
+            levels: self.levels().into_iter(),
+            shares: vec![false; self.gates.len()],
+            triples,
+            next_triple: 0,
+            level: Vec::new(),
+            ands: Vec::new(),
+        }
+    }
+
+    /// Keeps the shares of a completed evaluation, for `share` to read.
+    pub fn finish(&mut self, evaluation: Evaluation) {
+        self.shares = evaluation.shares;
+    }
+}
+
+/// A circuit part-way through evaluation, paused before each round of AND gates.
+#[derive(Debug)]
+pub struct Evaluation {
+    leader: bool,
+    levels: std::vec::IntoIter<Vec<usize>>,
+    shares: Vec<bool>,
+    triples: Vec<Triple>,
+    next_triple: usize,
+    /// The level waiting on its AND gates, and those gates as `(output, left, right)`.
+    level: Vec<usize>,
+    ands: Vec<(usize, usize, usize)>,
+}
+
+impl Evaluation {
+    /// Evaluates up to the next round of AND gates, returning the masked values that round must open, or `None`
+    /// once every gate is evaluated.
+    pub fn masked(&mut self, circuit: &Circuit) -> Option<Vec<bool>> {
+        loop {
+            self.level = self.levels.next()?;
+            self.ands = self
+                .level
+                .iter()
+                .filter_map(|&w| match circuit.gates[w] {
+                    Gate::And(a, b) => Some((w, a, b)),
+                    _ => None,
+                })
+                .collect();
+
+            if self.ands.is_empty() {
+                self.local(circuit);
+                continue;
+            }
+
+            let triples = &self.triples[self.next_triple..(self.next_triple + self.ands.len())];
+            let mut masked = Vec::with_capacity(2 * self.ands.len());
+            for (&(_, a, b), t) in self.ands.iter().zip(triples) {
+                masked.push(self.shares[a] ^ t.a);
+                masked.push(self.shares[b] ^ t.b);
+            }
+            return Some(masked);
+        }
+    }
+
+    /// Completes the round that `masked` started, given the opened values.
+    pub fn resume(&mut self, circuit: &Circuit, opened: &[bool]) {
+        let triples = &self.triples[self.next_triple..(self.next_triple + self.ands.len())];
+        for (k, (&(w, _, _), t)) in self.ands.iter().zip(triples).enumerate() {
+            let d = opened[2 * k];
+            let e = opened[2 * k + 1];
+            self.shares[w] = t.c ^ (d & t.b) ^ (e & t.a) ^ (self.leader & d & e);
+        }
+        self.next_triple += self.ands.len();
+        self.local(circuit);
+    }
+
+    /// Evaluates the gates of the current level that need no interaction.
+    fn local(&mut self, circuit: &Circuit) {
+        let leader = self.leader;
+        let shares = &mut self.shares;
+        for &w in &self.level {
+            match circuit.gates[w] {
+                Gate::Input(share) => shares[w] = share,
+                Gate::Constant(value) => shares[w] = leader & value,
+                Gate::Inv(a) => shares[w] = shares[a] ^ leader,
+                Gate::Xor(a, b) => shares[w] = shares[a] ^ shares[b],
+                Gate::And(_, _) => (),
+            }
+        }
+    }
+}
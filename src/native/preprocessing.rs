@@ -0,0 +1,171 @@
+use crate::native::triples::Triple;
+use crate::util;
+use integer_encoding::{VarIntReader, VarIntWriter};
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+/// Correlated randomness generated ahead of the online phase: boolean Beaver triples, consumed by AND gates,
+/// and random bits, consumed by `Bool::random`.
+///
+/// Every party's material is only meaningful alongside that of the other parties from the same preprocessing
+/// session, so all parties must consume it in lockstep, and saved material must be loaded by the same party
+/// against the same peers.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Preprocessing {
+    triples: VecDeque<Triple>,
+    bits: VecDeque<bool>,
+}
+
+impl Preprocessing {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The number of triples not yet consumed.
+    pub fn triples(&self) -> usize {
+        self.triples.len()
+    }
+
+    /// The number of random bits not yet consumed.
+    pub fn bits(&self) -> usize {
+        self.bits.len()
+    }
+
+    pub(crate) fn push_triples(&mut self, triples: Vec<Triple>) {
+        self.triples.extend(triples);
+    }
+
+    pub(crate) fn push_bits(&mut self, bits: Vec<bool>) {
+        self.bits.extend(bits);
+    }
+
+    /// Adds `other`'s material after this material.
+    pub(crate) fn append(&mut self, mut other: Preprocessing) {
+        self.triples.append(&mut other.triples);
+        self.bits.append(&mut other.bits);
+    }
+
+    /// Removes up to `count` triples, oldest first.
+    pub(crate) fn take_triples(&mut self, count: usize) -> Vec<Triple> {
+        let count = count.min(self.triples.len());
+        self.triples.drain(..count).collect()
+    }
+
+    pub(crate) fn take_bit(&mut self) -> Option<bool> {
+        self.bits.pop_front()
+    }
+
+    /// Writes the material as a varint count of triples, one byte per triple (`a | b << 1 | c << 2`), and a
+    /// varint count of bits followed by the bits packed eight to a byte.
+    pub fn write_to<W: Write>(&self, w: &mut W) -> std::io::Result<()> {
+        w.write_varint(self.triples.len())?;
+        let triples: Vec<u8> = self
+            .triples
+            .iter()
+            .map(|t| t.a as u8 | (t.b as u8) << 1 | (t.c as u8) << 2)
+            .collect();
+        w.write_all(&triples)?;
+
+        w.write_varint(self.bits.len())?;
+        let mut bits: Vec<bool> = self.bits.iter().copied().collect();
+        bits.resize(((bits.len() + 7) / 8) * 8, false);
+        w.write_all(&util::from_bits(&bits))
+    }
+
+    pub fn read_from<R: Read>(r: &mut R) -> std::io::Result<Self> {
+        let len: usize = r.read_varint()?;
+        let triples = read_bytes(r, len)?
+            .into_iter()
+            .map(|t| Triple {
+                a: t & 1 != 0,
+                b: t & 2 != 0,
+                c: t & 4 != 0,
+            })
+            .collect();
+
+        let len: usize = r.read_varint()?;
+        let mut bits = util::to_bits(&read_bytes(r, len.div_ceil(8))?);
+        bits.truncate(len);
+
+        Ok(Self {
+            triples,
+            bits: bits.into(),
+        })
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> std::io::Result<()> {
+        let mut w = BufWriter::new(File::create(path)?);
+        self.write_to(&mut w)?;
+        w.flush()
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> std::io::Result<Self> {
+        Self::read_from(&mut BufReader::new(File::open(path)?))
+    }
+}
+
+/// Reads exactly `len` bytes, growing the buffer only as data arrives, so that a corrupt length cannot
+/// request an arbitrarily large allocation up front.
+fn read_bytes<R: Read>(r: &mut R, len: usize) -> io::Result<Vec<u8>> {
+    let mut buf = Vec::new();
+    r.take(len as u64).read_to_end(&mut buf)?;
+    if buf.len() != len {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "expected {} bytes of preprocessing, but found {}",
+                len,
+                buf.len()
+            ),
+        ));
+    }
+    Ok(buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn write_read_round_trip() {
+        let mut material = Preprocessing::new();
+        material.push_triples(
+            (0..21)
+                .map(|i| {
+                    let (a, b) = (i % 2 == 0, i % 3 == 0);
+                    Triple { a, b, c: a & b }
+                })
+                .collect(),
+        );
+        material.push_bits((0..13).map(|i| i % 5 < 2).collect());
+
+        let mut buf = Vec::new();
+        material.write_to(&mut buf).unwrap();
+        let read = Preprocessing::read_from(&mut buf.as_slice()).unwrap();
+        assert_eq!(read, material);
+
+        let mut taken = read.clone();
+        assert_eq!(taken.take_triples(30).len(), 21);
+        assert_eq!(taken.triples(), 0);
+        assert_eq!(taken.take_bit(), Some(true));
+        assert_eq!(taken.bits(), 12);
+    }
+
+    #[test]
+    fn rejects_truncated_input() {
+        let mut buf = Vec::new();
+        buf.write_varint(usize::MAX).unwrap();
+        buf.extend_from_slice(&[7; 3]);
+        let err = Preprocessing::read_from(&mut buf.as_slice()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        let mut buf = Vec::new();
+        Preprocessing::new().write_to(&mut buf).unwrap();
+        buf.pop();
+        buf.write_varint(1usize << 40).unwrap();
+        let err = Preprocessing::read_from(&mut buf.as_slice()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,182 @@
+use crate::error::Result;
+use crate::gmw::Backend;
+use crate::native::circuit::{Circuit, Gate};
+use crate::native::triples::Triple;
+use crate::native::{Bool, Nat, Preprocessing};
+use std::sync::Arc;
+use std::sync::Mutex;
+
+/// The cost of the circuits evaluated so far, as the native engine would incur them in its online phase.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Cost {
+    pub and_gates: usize,
+    pub rounds: usize,
+    /// Bytes sent by each party.
+    pub bytes: usize,
+}
+
+/// Evaluates GMW circuits in the clear within a single process, for testing circuit logic.
+///
+/// Inputs are plaintext values rather than shares, and the "share" returned for each output is its value. The
+/// circuits built are exactly those of the native engine, so `cost` reflects what a real run would cost.
+pub struct Simulator {
+    parties: usize,
+    circuit: Arc<Mutex<Circuit>>,
+    cost: Cost,
+    store: Preprocessing,
+}
+
+impl Simulator {
+    pub fn new(parties: usize) -> Self {
+        Self {
+            parties,
+            circuit: Arc::new(Mutex::new(Circuit::new())),
+            cost: Cost::default(),
+            store: Preprocessing::new(),
+        }
+    }
+
+    pub fn cost(&self) -> Cost {
+        self.cost
+    }
+
+    pub fn reset_cost(&mut self) {
+        self.cost = Cost::default();
+    }
+}
+
+impl Backend for Simulator {
+    type Bool = Bool;
+    type Nat = Nat;
+
+    fn new(_my_id: usize, hosts: &[String], _ports: &[u16]) -> Result<Self> {
+        Ok(Self::new(hosts.len()))
+    }
+
+    fn parties(&self) -> usize {
+        self.parties
+    }
+
+    fn bool_new(&mut self, share: bool) -> Bool {
+        Bool::gate(&self.circuit, Gate::Input(share))
+    }
+
+    fn bool_constant(&mut self, value: bool) -> Bool {
+        Bool::gate(&self.circuit, Gate::Constant(value))
+    }
+
+    fn bool_inv(&mut self, a: &Bool) -> Bool {
+        a.inv()
+    }
+
+    fn bool_xor(&mut self, a: &Bool, b: &Bool) -> Bool {
+        a.xor(b)
+    }
+
+    fn bool_and(&mut self, a: &Bool, b: &Bool) -> Bool {
+        a.and(b)
+    }
+
+    fn bool_mux(&mut self, g: &Bool, a: &Bool, b: &Bool) -> Bool {
+        Bool::mux(g, a, b)
+    }
+
+    fn bool_get(&mut self, share: &Bool) -> bool {
+        share.get()
+    }
+
+    fn nat_new(&mut self, share: Vec<bool>) -> Nat {
+        let wires = {
+            let circuit = &mut *self.circuit.lock().unwrap();
+            share
+                .into_iter()
+                .map(|b| circuit.push(Gate::Input(b)))
+                .collect()
+        };
+        Nat::from_wires(&self.circuit, wires)
+    }
+
+    fn nat_constant(&mut self, value: Vec<bool>) -> Nat {
+        let wires = {
+            let circuit = &mut *self.circuit.lock().unwrap();
+            value.into_iter().map(|b| circuit.constant(b)).collect()
+        };
+        Nat::from_wires(&self.circuit, wires)
+    }
+
+    fn nat_add(&mut self, a: &Nat, b: &Nat) -> Nat {
+        a.add(b)
+    }
+
+    fn nat_sub(&mut self, a: &Nat, b: &Nat) -> Nat {
+        a.sub(b)
+    }
+
+    fn nat_mul(&mut self, a: &Nat, b: &Nat) -> Nat {
+        a.mul(b)
+    }
+
+    fn nat_mux(&mut self, g: &Bool, a: &Nat, b: &Nat) -> Nat {
+        Nat::mux(g, a, b)
+    }
+
+    fn nat_eq(&mut self, a: &Nat, b: &Nat) -> Bool {
+        a.eq(b)
+    }
+
+    fn nat_gt(&mut self, a: &Nat, b: &Nat) -> Bool {
+        a.gt(b)
+    }
+
+    fn nat_get(&mut self, share: &Nat) -> Vec<bool> {
+        share.get()
+    }
+
+    fn nat_width(&mut self, share: &Nat) -> usize {
+        share.width()
+    }
+
+    fn nat_bits(&mut self, a: &Nat) -> Vec<Bool> {
+        a.bits()
+    }
+
+    fn nat_from_bits(&mut self, bits: &[Bool]) -> Nat {
+        let wires = bits.iter().map(|b| b.wire).collect();
+        Nat::from_wires(&self.circuit, wires)
+    }
+
+    /// Since shares are plaintext values, the all-zero triple is valid, and random bits need not be shared.
+    fn preprocess(&mut self, triples: usize, bits: usize) -> Result<()> {
+        let zero = Triple {
+            a: false,
+            b: false,
+            c: false,
+        };
+        self.store.push_triples(vec![zero; triples]);
+        self.store
+            .push_bits((0..bits).map(|_| rand::random()).collect());
+        Ok(())
+    }
+
+    fn preprocessing(&mut self) -> Option<&mut Preprocessing> {
+        Some(&mut self.store)
+    }
+
+    fn run(&mut self) -> Result<()> {
+        let ands = self.circuit.lock().unwrap().and_count();
+        self.store.take_triples(ands);
+        let rounds = self.circuit.lock().unwrap().simulate();
+        let peers = self.parties.saturating_sub(1);
+        for ands in rounds {
+            // Each party opens two masked bits per AND gate to every other party.
+            self.cost.and_gates += ands;
+            self.cost.rounds += 1;
+            self.cost.bytes += ((2 * ands + 7) / 8) * peers;
+        }
+        Ok(())
+    }
+
+    fn reset(&mut self) {
+        self.circuit = Arc::new(Mutex::new(Circuit::new()));
+    }
+}
@@ -0,0 +1,188 @@
+use crate::error::{Error, Result};
+use crate::native::Peers;
+use crate::util::SwankyChannel;
+use ocelot::ot::{AlszReceiver, AlszSender, Receiver, Sender};
+use rand::Rng;
+use scuttlebutt::Block;
+
+/// A share of a boolean multiplication (Beaver) triple, `c = a & b`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Triple {
+    pub a: bool,
+    pub b: bool,
+    pub c: bool,
+}
+
+/// An additive share of a multiplication triple over `Z_2^64`, `c = a * b`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ArithTriple {
+    pub a: u64,
+    pub b: u64,
+    pub c: u64,
+}
+
+/// Generates `count` boolean Beaver triples with every other party.
+///
+/// Each cross term `a_i & b_j` is computed with one oblivious transfer from party `i` to party `j`.
+pub fn generate(my_id: usize, peers: &mut Peers, count: usize) -> Result<Vec<Triple>> {
+    let mut triples: Vec<Triple> = (0..count)
+        .map(|_| {
+            let a = peers.rng.gen();
+            let b = peers.rng.gen();
+            Triple { a, b, c: a & b }
+        })
+        .collect();
+
+    if count == 0 {
+        return Ok(triples);
+    }
+
+    pairwise(
+        my_id,
+        peers,
+        &mut triples,
+        |peers, peer, triples| {
+            let inputs: Vec<(Block, Block)> = triples
+                .iter_mut()
+                .map(|t| {
+                    let mask: bool = peers.rng.gen();
+                    t.c ^= mask;
+                    (block(mask as u128), block((mask ^ t.a) as u128))
+                })
+                .collect();
+            ot_send(peers, peer, &inputs)
+        },
+        |peers, peer, triples| {
+            let choices: Vec<bool> = triples.iter().map(|t| t.b).collect();
+            let outputs = ot_receive(peers, peer, &choices)?;
+            for (t, o) in triples.iter_mut().zip(outputs) {
+                t.c ^= o.lsb();
+            }
+            Ok(())
+        },
+    )?;
+
+    Ok(triples)
+}
+
+/// Generates `count` arithmetic Beaver triples over `Z_2^64` with every other party.
+///
+/// Each cross term `a_i * b_j` is computed by Gilboa's multiplication: one oblivious transfer per bit of `b_j`,
+/// in which party `j` learns either `r` or `r + (a_i << k)` according to bit `k`, and party `i` keeps `-r`.
+pub fn generate_arith(my_id: usize, peers: &mut Peers, count: usize) -> Result<Vec<ArithTriple>> {
+    let mut triples: Vec<ArithTriple> = (0..count)
+        .map(|_| {
+            let a: u64 = peers.rng.gen();
+            let b: u64 = peers.rng.gen();
+            ArithTriple {
+                a,
+                b,
+                c: a.wrapping_mul(b),
+            }
+        })
+        .collect();
+
+    if count == 0 {
+        return Ok(triples);
+    }
+
+    pairwise(
+        my_id,
+        peers,
+        &mut triples,
+        |peers, peer, triples| {
+            let mut inputs = Vec::with_capacity(64 * triples.len());
+            for t in triples.iter_mut() {
+                for k in 0..64 {
+                    let mask: u64 = peers.rng.gen();
+                    t.c = t.c.wrapping_sub(mask);
+                    let product = mask.wrapping_add(t.a << k);
+                    inputs.push((block(mask as u128), block(product as u128)));
+                }
+            }
+            ot_send(peers, peer, &inputs)
+        },
+        |peers, peer, triples| {
+            let choices: Vec<bool> = triples
+                .iter()
+                .flat_map(|t| (0..64).map(move |k| (t.b >> k) & 1 == 1))
+                .collect();
+            let outputs = ot_receive(peers, peer, &choices)?;
+            for (t, o) in triples.iter_mut().zip(outputs.chunks(64)) {
+                for o in o {
+                    t.c = t.c.wrapping_add(u128::from(*o) as u64);
+                }
+            }
+            Ok(())
+        },
+    )?;
+
+    Ok(triples)
+}
+
+/// Runs `send` and `receive` with every other party. Pairs are processed in the same global order by every
+/// party, which keeps the pairwise transfers from deadlocking.
+fn pairwise<T, S, R>(
+    my_id: usize,
+    peers: &mut Peers,
+    triples: &mut [T],
+    mut send: S,
+    mut receive: R,
+) -> Result<()>
+where
+    S: FnMut(&mut Peers, usize, &mut [T]) -> Result<()>,
+    R: FnMut(&mut Peers, usize, &mut [T]) -> Result<()>,
+{
+    let parties = peers.channels.len();
+    for lo in 0..parties {
+        for hi in (lo + 1)..parties {
+            if my_id == lo {
+                send(peers, hi, triples)?;
+                receive(peers, hi, triples)?;
+            } else if my_id == hi {
+                receive(peers, lo, triples)?;
+                send(peers, lo, triples)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn ot_send(peers: &mut Peers, peer: usize, inputs: &[(Block, Block)]) -> Result<()> {
+    let Peers {
+        channels,
+        senders,
+        rng,
+        ..
+    } = peers;
+    let channel = channels[peer].as_mut().ok_or(Error::Disconnected)?;
+    let mut channel = SwankyChannel::new(channel);
+
+    if senders[peer].is_none() {
+        senders[peer] = Some(AlszSender::init(&mut channel, rng)?);
+    }
+    let sender = senders[peer].as_mut().unwrap();
+    sender.send(&mut channel, inputs, rng)?;
+    Ok(())
+}
+
+fn ot_receive(peers: &mut Peers, peer: usize, choices: &[bool]) -> Result<Vec<Block>> {
+    let Peers {
+        channels,
+        receivers,
+        rng,
+        ..
+    } = peers;
+    let channel = channels[peer].as_mut().ok_or(Error::Disconnected)?;
+    let mut channel = SwankyChannel::new(channel);
+
+    if receivers[peer].is_none() {
+        receivers[peer] = Some(AlszReceiver::init(&mut channel, rng)?);
+    }
+    let receiver = receivers[peer].as_mut().unwrap();
+    Ok(receiver.receive(&mut channel, choices, rng)?)
+}
+
+fn block(value: u128) -> Block {
+    Block::from(value)
+}
@@ -8,9 +8,26 @@ pub use prg::*;
 mod channel;
 pub use channel::*;
 
+mod tls;
+pub use tls::*;
+
+mod psk;
+pub use psk::*;
+
+mod mesh;
+pub use mesh::Mesh;
+
+#[cfg(feature = "async")]
+mod async_channel;
+#[cfg(feature = "async")]
+pub use async_channel::*;
+
 mod bitvec;
 pub use self::bitvec::*;
 
+#[cfg(test)]
+pub mod harness;
+
 pub use scuttlebutt::utils::xor_inplace;
 
 pub fn byte_to_bits(mut byte: u8) -> Vec<bool> {
@@ -24,13 +41,13 @@ pub fn byte_to_bits(mut byte: u8) -> Vec<bool> {
     ret
 }
 
+/// Packs up to eight bits, least significant first, into a byte. Missing high bits are zero.
 pub fn byte_from_bits(bits: &[bool]) -> u8 {
     let mut ret = 0;
-    let len = u8::BITS as usize;
 
-    for i in 0..len {
+    for bit in bits.iter().rev() {
         ret <<= 1;
-        ret |= bits[len - i - 1] as u8;
+        ret |= *bit as u8;
     }
 
     ret
@@ -40,80 +57,122 @@ pub fn to_bits(bytes: &[u8]) -> Vec<bool> {
     bytes.iter().flat_map(|b| byte_to_bits(*b)).collect()
 }
 
+/// The low `width` bits of the little-endian `bytes`, padded with zeros if `bytes` is shorter.
+pub fn to_bits_width(bytes: &[u8], width: usize) -> Vec<bool> {
+    let mut bits = to_bits(bytes);
+    bits.resize(width, false);
+    bits
+}
+
+/// The low `width` bits of the two's complement little-endian `bytes`, repeating their sign bit if `bytes` is
+/// shorter.
+pub fn to_bits_signed(bytes: &[u8], width: usize) -> Vec<bool> {
+    let mut bits = to_bits(bytes);
+    let sign = bits.last().copied().unwrap_or(false);
+    bits.resize(width, sign);
+    bits
+}
+
 pub fn from_bits(bits: &[bool]) -> Vec<u8> {
     bits.chunks(8).map(byte_from_bits).collect()
 }
 
 mod arith {
-    use std::borrow::{Borrow, BorrowMut};
+    /// The boolean gates from which the circuits below are built, so that they can be shared by every protocol.
+    pub trait Gates {
+        type Bool: Clone;
+
+        fn constant(&mut self, value: bool) -> Self::Bool;
+
+        fn xor(&mut self, a: &Self::Bool, b: &Self::Bool) -> Self::Bool;
 
-    use crate::gmw::*;
+        fn and(&mut self, a: &Self::Bool, b: &Self::Bool) -> Self::Bool;
+
+        fn not(&mut self, a: &Self::Bool) -> Self::Bool;
+
+        fn or(&mut self, a: &Self::Bool, b: &Self::Bool) -> Self::Bool {
+            let ab = self.and(a, b);
+            let axb = self.xor(a, b);
+            self.xor(&ab, &axb)
+        }
+
+        fn mux(&mut self, g: &Self::Bool, a: &Self::Bool, b: &Self::Bool) -> Self::Bool {
+            let axb = self.xor(a, b);
+            let t = self.and(g, &axb);
+            self.xor(b, &t)
+        }
+    }
 
     // SOURCE (lightly modified): https://github.com/emp-toolkit/emp-tool
-    pub unsafe fn full_add(
-        protocol: &mut Protocol,
-        dest: *mut Bool,
-        a: *const Bool,
-        b: *const Bool,
+    pub unsafe fn full_add<G: Gates>(
+        protocol: &mut G,
+        dest: *mut G::Bool,
+        a: *const G::Bool,
+        b: *const G::Bool,
         size: usize,
     ) {
         if size == 0 {
             return;
         }
 
-        let mut carry = Bool::constant(protocol, false);
+        let mut carry = protocol.constant(false);
 
         for i in 0..(size - 1) {
-            let axc = Bool::xor(protocol, &*a.add(i), &carry);
-            let bxc = Bool::xor(protocol, &*b.add(i), &carry);
-            *dest.add(i) = Bool::xor(protocol, &*a.add(i), &bxc);
-            let t = Bool::and(protocol, &axc, &bxc);
-            carry = Bool::xor(protocol, &carry, &t);
+            let axc = protocol.xor(&*a.add(i), &carry);
+            let bxc = protocol.xor(&*b.add(i), &carry);
+            *dest.add(i) = protocol.xor(&*a.add(i), &bxc);
+            let t = protocol.and(&axc, &bxc);
+            carry = protocol.xor(&carry, &t);
         }
 
-        let axb = Bool::xor(protocol, &*a.add(size - 1), &*b.add(size - 1));
-        *dest.add(size - 1) = Bool::xor(protocol, &carry, &axb);
+        let axb = protocol.xor(&*a.add(size - 1), &*b.add(size - 1));
+        *dest.add(size - 1) = protocol.xor(&carry, &axb);
     }
 
-    pub unsafe fn full_sub(
-        protocol: &mut Protocol,
-        dest: *mut Bool,
-        borrow_out: *mut Bool,
-        a: *const Bool,
-        b: *const Bool,
+    pub unsafe fn full_sub<G: Gates>(
+        protocol: &mut G,
+        dest: *mut G::Bool,
+        borrow_out: *mut G::Bool,
+        a: *const G::Bool,
+        b: *const G::Bool,
         size: usize,
     ) {
         if size == 0 {
             return;
         }
 
-        let mut borrow = Bool::constant(protocol, false);
+        let mut borrow = protocol.constant(false);
 
         for i in 0..(size - if borrow_out.is_null() { 1 } else { 0 }) {
-            let bxa = Bool::xor(protocol, &*a.add(i), &*b.add(i));
-            let bxc = Bool::xor(protocol, &borrow, &*b.add(i));
-            *dest.add(i) = Bool::xor(protocol, &bxa, &borrow);
-            let t = Bool::and(protocol, &bxa, &bxc);
-            borrow = Bool::xor(protocol, &borrow, &t);
+            let bxa = protocol.xor(&*a.add(i), &*b.add(i));
+            let bxc = protocol.xor(&borrow, &*b.add(i));
+            *dest.add(i) = protocol.xor(&bxa, &borrow);
+            let t = protocol.and(&bxa, &bxc);
+            borrow = protocol.xor(&borrow, &t);
         }
 
         if borrow_out.is_null() {
-            let bxa = Bool::xor(protocol, &*a.add(size - 1), &*b.add(size - 1));
-            *dest.add(size - 1) = Bool::xor(protocol, &bxa, &borrow);
+            let bxa = protocol.xor(&*a.add(size - 1), &*b.add(size - 1));
+            *dest.add(size - 1) = protocol.xor(&bxa, &borrow);
         } else {
             *borrow_out = borrow;
         }
     }
 
-    pub fn full_mul(protocol: &mut Protocol, dest: &mut [Bool], a: &[Bool], b: &[Bool]) {
+    pub fn full_mul<G: Gates>(
+        protocol: &mut G,
+        dest: &mut [G::Bool],
+        a: &[G::Bool],
+        b: &[G::Bool],
+    ) {
         debug_assert_eq!(a.len(), b.len());
         debug_assert_eq!(dest.len(), a.len());
 
         let size = dest.len();
-        let mut temp = vec![Bool::constant(protocol, false); size];
+        let mut temp = vec![protocol.constant(false); size];
         for i in 0..size {
             for j in 0..(size - i) {
-                temp[j] = Bool::and(protocol, &a[j], &b[i])
+                temp[j] = protocol.and(&a[j], &b[i])
             }
             unsafe {
                 full_add(
@@ -127,59 +186,91 @@ mod arith {
         }
     }
 
-    pub fn full_div(protocol: &mut Protocol, a: &[Bool], b: &[Bool]) -> (Vec<Bool>, Vec<Bool>) {
+    pub fn full_div<G: Gates>(
+        protocol: &mut G,
+        a: &[G::Bool],
+        b: &[G::Bool],
+    ) -> (Vec<G::Bool>, Vec<G::Bool>) {
         let len = a.len();
+        if len == 0 {
+            return (Vec::new(), Vec::new());
+        }
 
-        let mut overflow = vec![Bool::constant(protocol, false); len];
-        overflow[0] = Bool::constant(protocol, false);
+        let mut overflow = vec![protocol.constant(false); len];
+        overflow[0] = protocol.constant(false);
         for i in 1..len {
-            overflow[i] = Bool::or(protocol, &overflow[i - 1], &b[len - i]);
+            overflow[i] = protocol.or(&overflow[i - 1], &b[len - i]);
         }
 
-        let mut temp = vec![Bool::constant(protocol, false); len];
-        let mut quot = vec![Bool::constant(protocol, false); len];
+        let mut temp = vec![protocol.constant(false); len];
+        let mut quot = vec![protocol.constant(false); len];
         let mut rem = a.to_vec();
-        let mut borrow = Bool::constant(protocol, false);
+        let mut borrow = protocol.constant(false);
 
         for i in (0..len).rev() {
             unsafe {
                 full_sub(
                     protocol,
                     temp.as_mut_ptr(),
-                    &mut borrow as *mut Bool,
+                    &mut borrow as *mut G::Bool,
                     rem[i..].as_ptr(),
                     b.as_ptr(),
                     len - i,
                 );
             }
-            borrow = Bool::or(protocol, &borrow, &overflow[i]);
+            borrow = protocol.or(&borrow, &overflow[i]);
             for j in 0..(len - i) {
-                rem[i + j] = Bool::mux(protocol, &borrow, &rem[i + j], &temp[j]);
+                rem[i + j] = protocol.mux(&borrow, &rem[i + j], &temp[j]);
             }
-            quot[i] = Bool::not(protocol, &borrow);
+            quot[i] = protocol.not(&borrow);
         }
 
         (quot, rem)
     }
 
-    pub unsafe fn cond_neg(
-        protocol: &mut Protocol,
-        sign: &Bool,
-        dest: *mut Bool,
-        src: *const Bool,
+    /// Whether any of `bits` is set, reduced pairwise so that the depth is logarithmic in their number.
+    pub fn any<G: Gates>(protocol: &mut G, bits: &[G::Bool]) -> G::Bool {
+        if bits.is_empty() {
+            return protocol.constant(false);
+        }
+
+        let mut level = bits.to_vec();
+        while level.len() > 1 {
+            level = level
+                .chunks(2)
+                .map(|pair| match pair {
+                    [a, b] => protocol.or(a, b),
+                    [a] => a.clone(),
+                    _ => unreachable!(),
+                })
+                .collect();
+        }
+
+        level.pop().unwrap()
+    }
+
+    pub unsafe fn cond_neg<G: Gates>(
+        protocol: &mut G,
+        sign: &G::Bool,
+        dest: *mut G::Bool,
+        src: *const G::Bool,
         size: usize,
     ) {
+        if size == 0 {
+            return;
+        }
+
         let mut c = sign.clone();
 
         for i in 0..(size - 1) {
-            *dest.add(i) = Bool::xor(protocol, &*src.add(i), sign);
-            let t = Bool::xor(protocol, &*dest.add(i), &c);
-            c = Bool::and(protocol, &c, &*dest.add(i));
+            *dest.add(i) = protocol.xor(&*src.add(i), sign);
+            let t = protocol.xor(&*dest.add(i), &c);
+            c = protocol.and(&c, &*dest.add(i));
             *dest.add(i) = t;
         }
 
-        let t = Bool::xor(protocol, sign, &c);
-        *dest.add(size - 1) = Bool::xor(protocol, &t, &*src.add(size - 1));
+        let t = protocol.xor(sign, &c);
+        *dest.add(size - 1) = protocol.xor(&t, &*src.add(size - 1));
     }
 }
 
@@ -201,6 +292,7 @@ pub mod ffi {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::gmw::{Bool, Plain, Protocol};
 
     #[test]
     fn byte_to_bits_sanity() {
@@ -209,6 +301,98 @@ mod tests {
         assert_eq!(byte_from_bits(&byte_to_bits(0x04)), 0x04);
     }
 
+    fn protocol() -> Protocol<Plain> {
+        Protocol::with_backend(Plain::new(2))
+    }
+
+    fn bits(protocol: &mut Protocol<Plain>, value: u8) -> Vec<Bool<Plain>> {
+        to_bits(&[value])
+            .into_iter()
+            .map(|b| Bool::new(protocol, b))
+            .collect()
+    }
+
+    fn value(protocol: &mut Protocol<Plain>, bits: &[Bool<Plain>]) -> u8 {
+        let bits: Vec<bool> = bits
+            .iter()
+            .map(|b| Bool::get(protocol, b).unwrap())
+            .collect();
+        byte_from_bits(&bits)
+    }
+
+    const SAMPLES: [u8; 8] = [0, 1, 2, 7, 100, 127, 128, 255];
+
+    #[test]
+    fn full_add_sub_mul() {
+        let p = &mut protocol();
+        for a in SAMPLES {
+            for b in SAMPLES {
+                let x = bits(p, a);
+                let y = bits(p, b);
+                let mut sum = x.clone();
+                let mut diff = x.clone();
+                let mut borrow = Bool::constant(p, false);
+                let mut prod = vec![Bool::constant(p, false); 8];
+                unsafe {
+                    full_add(p, sum.as_mut_ptr(), x.as_ptr(), y.as_ptr(), 8);
+                    full_sub(p, diff.as_mut_ptr(), &mut borrow, x.as_ptr(), y.as_ptr(), 8);
+                }
+                full_mul(p, &mut prod, &x, &y);
+
+                assert_eq!(value(p, &sum), a.wrapping_add(b));
+                assert_eq!(value(p, &diff), a.wrapping_sub(b));
+                assert_eq!(Bool::get(p, &borrow).unwrap(), a < b);
+                assert_eq!(value(p, &prod), a.wrapping_mul(b));
+            }
+        }
+    }
+
+    #[test]
+    fn full_div_sanity() {
+        let p = &mut protocol();
+        for a in SAMPLES {
+            for b in SAMPLES.into_iter().filter(|&b| b != 0) {
+                let x = bits(p, a);
+                let y = bits(p, b);
+                let (quot, rem) = full_div(p, &x, &y);
+                assert_eq!(value(p, &quot), a / b);
+                assert_eq!(value(p, &rem), a % b);
+            }
+        }
+    }
+
+    #[test]
+    fn cond_neg_sanity() {
+        let p = &mut protocol();
+        for a in SAMPLES {
+            for sign in [false, true] {
+                let x = bits(p, a);
+                let s = Bool::new(p, sign);
+                let mut dest = x.clone();
+                unsafe { cond_neg(p, &s, dest.as_mut_ptr(), x.as_ptr(), 8) };
+                let expected = if sign { a.wrapping_neg() } else { a };
+                assert_eq!(value(p, &dest), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn any_sanity() {
+        let p = &mut protocol();
+        for a in SAMPLES {
+            let x = bits(p, a);
+            let nonzero = any(p, &x);
+            assert_eq!(Bool::get(p, &nonzero).unwrap(), a != 0);
+        }
+
+        // 64 bits take 6 rounds, not 64.
+        let p = &mut protocol();
+        let x: Vec<Bool<Plain>> = (0..64).map(|i| Bool::new(p, i == 63)).collect();
+        let nonzero = any(p, &x);
+        assert!(Bool::get(p, &nonzero).unwrap());
+        assert_eq!(p.backend().cost().rounds, 6);
+    }
+
     #[test]
     fn to_bits_sanity() {
         let input: i32 = 1;
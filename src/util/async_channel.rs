@@ -0,0 +1,246 @@
+//! Channels for async code, built on tokio, so that services can connect to and talk with other parties without
+//! blocking a thread on each connection. `gmw::AsyncProtocol` evaluates circuits over them directly. Code that only
+//! speaks blocking I/O can borrow a channel through a `BlockingChannel`, or take it over with
+//! `AsyncChannel::into_blocking`.
+
+use crate::error::Result;
+use crate::util::{millis, Attempts, Channel, ConnectOptions, TcpChannel, Traffic, UnixChannel};
+use std::io;
+use std::io::{Read, Write};
+use std::path::Path;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, ReadBuf};
+use tokio::net::{TcpListener, TcpStream, UnixListener, UnixStream};
+use tokio::runtime::Handle;
+
+/// A connection to another party, read and written through tokio's `AsyncReadExt` and `AsyncWriteExt`. Unlike
+/// `Channel`, writes are not buffered.
+///
+/// Once connected, a channel can be handed to the blocking engines with `into_blocking`.
+#[derive(Debug)]
+pub enum AsyncChannel {
+    Tcp(TcpStream),
+    Unix(UnixStream),
+}
+
+impl AsyncChannel {
+    /// Dials `host:port` as `connect` does, but waits between attempts without blocking the runtime. The read and
+    /// write timeouts in `options` are not applied.
+    pub async fn connect(host: &str, port: u16, options: &ConnectOptions) -> Result<Self> {
+        let target = format!("{}:{}", host, port);
+        let mut attempts = Attempts::new(&target, options);
+        loop {
+            attempts.start()?;
+            let attempt = TcpStream::connect((host, port));
+            let result = match millis(options.connect_timeout_ms) {
+                Some(timeout) => tokio::time::timeout(timeout, attempt)
+                    .await
+                    .unwrap_or_else(|_| Err(io::ErrorKind::TimedOut.into())),
+                None => attempt.await,
+            };
+            match result {
+                Ok(stream) => return Ok(AsyncChannel::Tcp(stream)),
+                Err(e) => tokio::time::sleep(attempts.failed(e)?).await,
+            }
+        }
+    }
+
+    /// Listens on `host:port` for a single connection.
+    pub async fn accept(host: &str, port: u16) -> Result<Self> {
+        let listener = TcpListener::bind((host, port)).await?;
+        Ok(AsyncChannel::Tcp(listener.accept().await?.0))
+    }
+
+    /// Like `connect`, but to the Unix domain socket at `path`.
+    pub async fn connect_unix<P: AsRef<Path>>(path: P, options: &ConnectOptions) -> Result<Self> {
+        let path = path.as_ref();
+        let target = path.display().to_string();
+        let mut attempts = Attempts::new(&target, options);
+        loop {
+            attempts.start()?;
+            match UnixStream::connect(path).await {
+                Ok(stream) => return Ok(AsyncChannel::Unix(stream)),
+                Err(e) => tokio::time::sleep(attempts.failed(e)?).await,
+            }
+        }
+    }
+
+    /// Listens at `path` for a single connection, replacing and then removing the socket as `UnixChannel::accept`
+    /// does.
+    pub async fn accept_unix<P: AsRef<Path>>(path: P) -> Result<Self> {
+        use std::os::unix::fs::FileTypeExt;
+
+        let path = path.as_ref();
+        if let Ok(metadata) = std::fs::symlink_metadata(path) {
+            if metadata.file_type().is_socket() {
+                std::fs::remove_file(path)?;
+            }
+        }
+        let listener = UnixListener::bind(path)?;
+        let stream = listener.accept().await?.0;
+        std::fs::remove_file(path)?;
+        Ok(AsyncChannel::Unix(stream))
+    }
+
+    /// Two connected ends, for parties served by the same process. Must be called within a runtime.
+    pub fn pair() -> Result<(Self, Self)> {
+        let (a, b) = UnixStream::pair()?;
+        Ok((AsyncChannel::Unix(a), AsyncChannel::Unix(b)))
+    }
+
+    /// Converts this channel into a blocking `Channel`, without read or write timeouts. Reads and writes on the
+    /// result block the calling thread, so it should only be used off the runtime, e.g. on tokio's blocking pool.
+    pub fn into_blocking(self) -> Result<Channel> {
+        Ok(match self {
+            AsyncChannel::Tcp(stream) => {
+                let stream = stream.into_std()?;
+                stream.set_nonblocking(false)?;
+                Channel::Tcp(TcpChannel::new(stream)?)
+            }
+            AsyncChannel::Unix(stream) => {
+                let stream = stream.into_std()?;
+                stream.set_nonblocking(false)?;
+                Channel::Unix(UnixChannel::new(stream)?)
+            }
+        })
+    }
+}
+
+impl AsyncRead for AsyncChannel {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            AsyncChannel::Tcp(tcp) => Pin::new(tcp).poll_read(cx, buf),
+            AsyncChannel::Unix(unix) => Pin::new(unix).poll_read(cx, buf),
+        }
+    }
+}
+
+impl AsyncWrite for AsyncChannel {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        match self.get_mut() {
+            AsyncChannel::Tcp(tcp) => Pin::new(tcp).poll_write(cx, buf),
+            AsyncChannel::Unix(unix) => Pin::new(unix).poll_write(cx, buf),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            AsyncChannel::Tcp(tcp) => Pin::new(tcp).poll_flush(cx),
+            AsyncChannel::Unix(unix) => Pin::new(unix).poll_flush(cx),
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            AsyncChannel::Tcp(tcp) => Pin::new(tcp).poll_shutdown(cx),
+            AsyncChannel::Unix(unix) => Pin::new(unix).poll_shutdown(cx),
+        }
+    }
+}
+
+/// An `AsyncChannel` used from blocking code, which waits on the current runtime for each read and flush. Writes
+/// are buffered until the next flush.
+///
+/// It must be used on a thread that may block but can reach the runtime, such as one from
+/// `tokio::task::spawn_blocking`. On a current-thread runtime, another thread must be inside `Runtime::block_on`
+/// meanwhile, since only that drives the runtime's I/O.
+pub struct BlockingChannel {
+    channel: AsyncChannel,
+    output: Vec<u8>,
+    pub(crate) traffic: Traffic,
+}
+
+impl BlockingChannel {
+    pub fn new(channel: AsyncChannel) -> Self {
+        Self {
+            channel,
+            output: Vec::new(),
+            traffic: Traffic::default(),
+        }
+    }
+
+    /// The channel beneath, for async code to use directly. Anything written but not yet flushed has not reached
+    /// it.
+    pub fn get_mut(&mut self) -> &mut AsyncChannel {
+        &mut self.channel
+    }
+
+    pub fn into_inner(self) -> AsyncChannel {
+        self.channel
+    }
+}
+
+impl Read for BlockingChannel {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        Handle::current().block_on(self.channel.read(buf))
+    }
+}
+
+impl Write for BlockingChannel {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.output.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        let (channel, output) = (&mut self.channel, &self.output);
+        Handle::current().block_on(async {
+            channel.write_all(output).await?;
+            channel.flush().await
+        })?;
+        self.output.clear();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    #[test]
+    fn async_round_trip() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            let listener = std::net::TcpListener::bind(("127.0.0.1", 0)).unwrap();
+            let port = listener.local_addr().unwrap().port();
+            drop(listener);
+
+            let server = tokio::spawn(async move {
+                let mut channel = AsyncChannel::accept("127.0.0.1", port).await.unwrap();
+                let mut buf = [0; 5];
+                channel.read_exact(&mut buf).await.unwrap();
+                channel.write_all(&buf).await.unwrap();
+                channel
+            });
+            let mut client = AsyncChannel::connect("127.0.0.1", port, &ConnectOptions::default())
+                .await
+                .unwrap();
+            client.write_all(b"hello").await.unwrap();
+            let mut buf = [0; 5];
+            client.read_exact(&mut buf).await.unwrap();
+            assert_eq!(&buf, b"hello");
+
+            // The same connection keeps working once handed to blocking code.
+            let mut server = server.await.unwrap().into_blocking().unwrap();
+            let mut client = client.into_blocking().unwrap();
+            client.write_all(b"again").unwrap();
+            client.flush().unwrap();
+            server.read_exact(&mut buf).unwrap();
+            assert_eq!(&buf, b"again");
+        });
+    }
+}
@@ -7,42 +7,180 @@ use std::io::Cursor;
 use std::io::Read;
 use std::io::Seek;
 use std::io::Write;
-use std::net::TcpStream;
+use std::net::{TcpStream, ToSocketAddrs};
+use std::os::unix::fs::FileTypeExt;
 use std::os::unix::io::RawFd;
+use std::os::unix::net::{UnixListener, UnixStream};
 use std::os::unix::prelude::IntoRawFd;
+use std::path::Path;
 use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
 
+use scuttlebutt::AbstractChannel;
+
+use crate::error::{Error, Result};
+#[cfg(feature = "async")]
+use crate::util::BlockingChannel;
+use crate::util::{PskChannel, TlsChannel};
+
+/// The bytes in flight from one end of a `LocalChannel` to the other.
 #[derive(Debug)]
-pub struct LocalChannel(VecDeque<u8>);
+struct Queue {
+    state: Mutex<QueueState>,
+    readable: Condvar,
+    writable: Condvar,
+    /// The most bytes in flight at once, or zero for no limit.
+    capacity: usize,
+}
+
+#[derive(Debug, Default)]
+struct QueueState {
+    bytes: VecDeque<u8>,
+    closed: bool,
+}
+
+impl Queue {
+    fn new(capacity: usize) -> Arc<Self> {
+        Arc::new(Self {
+            state: Mutex::new(QueueState::default()),
+            readable: Condvar::new(),
+            writable: Condvar::new(),
+            capacity,
+        })
+    }
+
+    /// Appends all of `data`, waiting for the reader to make room as needed.
+    fn push(&self, mut data: &[u8]) -> std::io::Result<()> {
+        let mut state = self.state.lock().unwrap();
+        while !data.is_empty() {
+            if state.closed {
+                return Err(std::io::ErrorKind::BrokenPipe.into());
+            }
+            let room = match self.capacity {
+                0 => data.len(),
+                capacity => capacity.saturating_sub(state.bytes.len()),
+            };
+            if room == 0 {
+                state = self.writable.wait(state).unwrap();
+                continue;
+            }
+            let (now, rest) = data.split_at(room.min(data.len()));
+            state.bytes.extend(now);
+            data = rest;
+            self.readable.notify_all();
+        }
+        std::io::Result::Ok(())
+    }
+
+    /// Moves as many bytes as are available into `buf`, waiting for some if `block` is set. Returns zero once the
+    /// queue is empty and either closed or not to be waited on.
+    fn pop(&self, buf: &mut [u8], block: bool) -> std::io::Result<usize> {
+        let mut state = self.state.lock().unwrap();
+        while state.bytes.is_empty() {
+            if state.closed || !block {
+                return std::io::Result::Ok(0);
+            }
+            state = self.readable.wait(state).unwrap();
+        }
+        let size = state.bytes.read(buf)?;
+        self.writable.notify_all();
+        std::io::Result::Ok(size)
+    }
+
+    fn close(&self) {
+        self.state.lock().unwrap().closed = true;
+        self.readable.notify_all();
+        self.writable.notify_all();
+    }
+}
+
+/// An in-memory channel. `new` gives a loopback, which reads back what it wrote, while `pair` and `bounded_pair`
+/// give two connected ends for parties running on different threads of the same process.
+///
+/// Writes are buffered until `flush`, which hands them to the other end as one message. Reads return what has
+/// arrived, waiting until something has, and report the end of the stream once the other end is dropped. A
+/// loopback has no one to wait for, so its reads deliver its own unflushed writes and never block.
+#[derive(Debug)]
+pub struct LocalChannel {
+    input: Arc<Queue>,
+    output: Arc<Queue>,
+    buffer: Vec<u8>,
+    traffic: Traffic,
+}
 
 impl LocalChannel {
     pub fn new() -> Self {
-        LocalChannel(VecDeque::new())
+        let queue = Queue::new(0);
+        Self::from_queues(queue.clone(), queue)
+    }
+
+    pub fn pair() -> (Self, Self) {
+        Self::bounded_pair(0)
+    }
+
+    /// Like `pair`, but with at most `capacity` bytes in flight each way, so that a fast writer waits for its
+    /// reader instead of buffering without bound. A capacity of zero means no limit. Two ends that both flush
+    /// more than `capacity` bytes before reading will wait on each other forever.
+    pub fn bounded_pair(capacity: usize) -> (Self, Self) {
+        let (there, back) = (Queue::new(capacity), Queue::new(capacity));
+        (
+            Self::from_queues(back.clone(), there.clone()),
+            Self::from_queues(there, back),
+        )
+    }
+
+    fn from_queues(input: Arc<Queue>, output: Arc<Queue>) -> Self {
+        Self {
+            input,
+            output,
+            buffer: Vec::new(),
+            traffic: Traffic::default(),
+        }
+    }
+
+    fn is_loopback(&self) -> bool {
+        Arc::ptr_eq(&self.input, &self.output)
+    }
+}
+
+impl Default for LocalChannel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for LocalChannel {
+    fn drop(&mut self) {
+        // Wakes the other end, whose reads then see the end of the stream and whose writes fail.
+        self.input.close();
+        self.output.close();
     }
 }
 
 impl Read for LocalChannel {
     fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
-        let size = buf.len();
-        for b in buf {
-            *b = self
-                .0
-                .pop_front()
-                .ok_or_else(|| std::io::Error::from(std::io::ErrorKind::UnexpectedEof))?;
+        if buf.is_empty() {
+            return std::io::Result::Ok(0);
         }
-        std::io::Result::Ok(size)
+        let loopback = self.is_loopback();
+        if loopback {
+            self.flush()?;
+        }
+        self.input.pop(buf, !loopback)
     }
 }
 
 impl Write for LocalChannel {
     fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
-        for b in buf {
-            self.0.push_back(*b)
-        }
+        self.buffer.extend_from_slice(buf);
         std::io::Result::Ok(buf.len())
     }
 
     fn flush(&mut self) -> std::io::Result<()> {
+        self.output.push(&self.buffer)?;
+        self.buffer.clear();
         std::io::Result::Ok(())
     }
 }
@@ -50,13 +188,18 @@ impl Write for LocalChannel {
 pub struct TcpChannel {
     input: BufReader<TcpStream>,
     output: BufWriter<TcpStream>,
+    traffic: Traffic,
 }
 
 impl TcpChannel {
-    pub fn new(stream: TcpStream) -> Self {
-        let input = BufReader::new(stream.try_clone().expect("TODO"));
+    pub fn new(stream: TcpStream) -> Result<Self> {
+        let input = BufReader::new(stream.try_clone()?);
         let output = BufWriter::new(stream);
-        Self { input, output }
+        Ok(Self {
+            input,
+            output,
+            traffic: Traffic::default(),
+        })
     }
 }
 
@@ -76,54 +219,434 @@ impl Write for TcpChannel {
     }
 }
 
+/// A connection to a party on the same host over a Unix domain socket, which skips the TCP stack.
+pub struct UnixChannel {
+    input: BufReader<UnixStream>,
+    output: BufWriter<UnixStream>,
+    traffic: Traffic,
+}
+
+impl UnixChannel {
+    pub fn new(stream: UnixStream) -> Result<Self> {
+        let input = BufReader::new(stream.try_clone()?);
+        let output = BufWriter::new(stream);
+        Ok(Self {
+            input,
+            output,
+            traffic: Traffic::default(),
+        })
+    }
+
+    /// Listens at `path` for a single connection. A socket left at `path` by an earlier run is replaced, and the
+    /// socket is removed again once the peer has connected.
+    pub fn accept<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        if let Ok(metadata) = std::fs::symlink_metadata(path) {
+            if metadata.file_type().is_socket() {
+                std::fs::remove_file(path)?;
+            }
+        }
+        let listener = UnixListener::bind(path)?;
+        let stream = listener.accept()?.0;
+        std::fs::remove_file(path)?;
+        Self::new(stream)
+    }
+}
+
+impl Read for UnixChannel {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.input.read(buf)
+    }
+}
+
+impl Write for UnixChannel {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.output.write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.output.flush()
+    }
+}
+
+/// How persistently to dial a peer, and how long to wait on it once connected. A timeout or attempt count of zero
+/// means no limit.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct ConnectOptions {
+    /// How long a single connection attempt may take.
+    pub connect_timeout_ms: u64,
+    /// The pause after the first failed attempt, doubling after each further failure. Pauses are never shorter
+    /// than a millisecond, so that a zero does not retry in a tight loop.
+    pub initial_backoff_ms: u64,
+    /// The longest pause between attempts.
+    pub max_backoff_ms: u64,
+    /// How many attempts to make before giving up.
+    pub max_attempts: u32,
+    /// How long a read on the established connection may block.
+    pub read_timeout_ms: u64,
+    /// How long a write on the established connection may block.
+    pub write_timeout_ms: u64,
+    /// If not null, checked before each attempt, giving up once another thread sets it.
+    pub cancel: *const AtomicBool,
+}
+
+// `cancel` is only ever read atomically, and whoever sets it must keep it alive while connecting, from whichever
+// thread the connection is made.
+unsafe impl Send for ConnectOptions {}
+unsafe impl Sync for ConnectOptions {}
+
+/// Retries forever, since parties are usually started at slightly different times, but backs off to one attempt
+/// per second rather than spinning.
+impl Default for ConnectOptions {
+    fn default() -> Self {
+        Self {
+            connect_timeout_ms: 5000,
+            initial_backoff_ms: 10,
+            max_backoff_ms: 1000,
+            max_attempts: 0,
+            read_timeout_ms: 0,
+            write_timeout_ms: 0,
+            cancel: std::ptr::null(),
+        }
+    }
+}
+
+impl ConnectOptions {
+    /// Applies the read and write timeouts to an established connection.
+    pub fn configure<S: Timeouts>(&self, stream: &S) -> Result<()> {
+        stream.set_read_timeout(millis(self.read_timeout_ms))?;
+        stream.set_write_timeout(millis(self.write_timeout_ms))?;
+        Ok(())
+    }
+}
+
+/// A socket whose reads and writes can be given timeouts.
+pub trait Timeouts {
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> std::io::Result<()>;
+
+    fn set_write_timeout(&self, timeout: Option<Duration>) -> std::io::Result<()>;
+}
+
+impl Timeouts for TcpStream {
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> std::io::Result<()> {
+        TcpStream::set_read_timeout(self, timeout)
+    }
+
+    fn set_write_timeout(&self, timeout: Option<Duration>) -> std::io::Result<()> {
+        TcpStream::set_write_timeout(self, timeout)
+    }
+}
+
+impl Timeouts for UnixStream {
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> std::io::Result<()> {
+        UnixStream::set_read_timeout(self, timeout)
+    }
+
+    fn set_write_timeout(&self, timeout: Option<Duration>) -> std::io::Result<()> {
+        UnixStream::set_write_timeout(self, timeout)
+    }
+}
+
+pub(crate) fn millis(ms: u64) -> Option<Duration> {
+    (ms > 0).then(|| Duration::from_millis(ms))
+}
+
+/// Dials `host:port` until it answers or `options` say to give up, then applies the read and write timeouts.
+pub fn connect(host: &str, port: u16, options: &ConnectOptions) -> Result<TcpStream> {
+    let stream = retry(&format!("{}:{}", host, port), options, || {
+        try_connect(host, port, millis(options.connect_timeout_ms))
+    })?;
+    options.configure(&stream)?;
+    Ok(stream)
+}
+
+/// Like `connect`, but to the Unix domain socket at `path`. Connecting to a local socket cannot hang, so the
+/// connect timeout does not apply.
+pub fn connect_unix<P: AsRef<Path>>(path: P, options: &ConnectOptions) -> Result<UnixStream> {
+    let path = path.as_ref();
+    let stream = retry(&path.display().to_string(), options, || {
+        UnixStream::connect(path)
+    })?;
+    options.configure(&stream)?;
+    Ok(stream)
+}
+
+/// Makes connection `attempt`s to `target`, backing off between them, until one succeeds or `options` say to
+/// give up.
+fn retry<T>(
+    target: &str,
+    options: &ConnectOptions,
+    mut attempt: impl FnMut() -> std::io::Result<T>,
+) -> Result<T> {
+    let mut attempts = Attempts::new(target, options);
+    loop {
+        attempts.start()?;
+        match attempt() {
+            Ok(stream) => return Ok(stream),
+            Err(e) => std::thread::sleep(attempts.failed(e)?),
+        }
+    }
+}
+
+/// Counts the attempts to reach a peer, for blocking and async connections alike.
+pub(crate) struct Attempts<'a> {
+    target: &'a str,
+    options: &'a ConnectOptions,
+    attempts: u32,
+    backoff: Duration,
+}
+
+impl<'a> Attempts<'a> {
+    pub fn new(target: &'a str, options: &'a ConnectOptions) -> Self {
+        Self {
+            target,
+            options,
+            attempts: 0,
+            backoff: Duration::from_millis(options.initial_backoff_ms.max(1)),
+        }
+    }
+
+    /// Counts a new attempt, unless the connection has been cancelled.
+    pub fn start(&mut self) -> Result<()> {
+        let cancel = self.options.cancel;
+        if !cancel.is_null() && unsafe { &*cancel }.load(Ordering::Relaxed) {
+            return Err(Error::Network(std::io::Error::new(
+                std::io::ErrorKind::Interrupted,
+                format!("connecting to {} was cancelled", self.target),
+            )));
+        }
+        self.attempts += 1;
+        Ok(())
+    }
+
+    /// Gives up after an attempt failed with `e`, or returns how long to wait before the next one.
+    pub fn failed(&mut self, e: std::io::Error) -> Result<Duration> {
+        if self.attempts == self.options.max_attempts {
+            return Err(Error::Network(std::io::Error::new(
+                e.kind(),
+                format!(
+                    "gave up connecting to {} after {} attempts: {}",
+                    self.target, self.attempts, e
+                ),
+            )));
+        }
+        let backoff = self.backoff;
+        let max_backoff = Duration::from_millis(self.options.max_backoff_ms.max(1));
+        self.backoff = (backoff * 2).min(max_backoff);
+        Ok(backoff)
+    }
+}
+
+/// Tries each address `host` resolves to once.
+fn try_connect(host: &str, port: u16, timeout: Option<Duration>) -> std::io::Result<TcpStream> {
+    let mut last = std::io::Error::new(
+        std::io::ErrorKind::NotFound,
+        format!("{} did not resolve to any address", host),
+    );
+    for addr in (host, port).to_socket_addrs()? {
+        let stream = match timeout {
+            Some(timeout) => TcpStream::connect_timeout(&addr, timeout),
+            None => TcpStream::connect(addr),
+        };
+        match stream {
+            Ok(stream) => return std::io::Result::Ok(stream),
+            Err(e) => last = e,
+        }
+    }
+    Err(last)
+}
+
+/// The traffic on a channel since it was created or its statistics were last reset. Bytes are counted as the
+/// caller reads and writes them, before any encryption or framing the channel adds.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ChannelStats {
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+    pub flushes: u64,
+    /// How often this end received data after having sent and flushed some, that is how many times it waited on
+    /// its peer.
+    pub rounds: u64,
+}
+
+/// Keeps a channel's `ChannelStats`.
+#[derive(Debug, Default)]
+pub(crate) struct Traffic {
+    stats: ChannelStats,
+    unflushed: bool,
+    awaiting: bool,
+}
+
+impl Traffic {
+    fn sent(&mut self, size: usize) {
+        self.stats.bytes_sent += size as u64;
+        self.unflushed |= size > 0;
+    }
+
+    fn flushed(&mut self) {
+        self.stats.flushes += 1;
+        if self.unflushed {
+            self.unflushed = false;
+            self.awaiting = true;
+        }
+    }
+
+    fn received(&mut self, size: usize) {
+        self.stats.bytes_received += size as u64;
+        if size > 0 && self.awaiting {
+            self.awaiting = false;
+            self.stats.rounds += 1;
+        }
+    }
+}
+
 pub enum Channel {
     Local(LocalChannel),
     Tcp(TcpChannel),
+    Unix(UnixChannel),
+    Tls(TlsChannel),
+    Psk(PskChannel),
+    /// An async connection used from blocking code, as `gmw::AsyncProtocol` does while preprocessing.
+    #[cfg(feature = "async")]
+    Async(BlockingChannel),
 }
 
 impl Channel {
+    /// The underlying socket, for engines that do their own networking. Encrypted channels have none, since writing
+    /// to the socket directly would bypass the encryption.
     pub fn try_get_socket(&self) -> Option<RawFd> {
         match self {
             Channel::Local(_) => None,
-            Channel::Tcp(channel) => Some(
-                channel
-                    .input
-                    .get_ref()
-                    .try_clone()
-                    .expect("TODO")
-                    .into_raw_fd(),
-            ),
+            Channel::Tls(_) => None,
+            Channel::Psk(_) => None,
+            #[cfg(feature = "async")]
+            Channel::Async(_) => None,
+            Channel::Tcp(channel) => channel
+                .input
+                .get_ref()
+                .try_clone()
+                .ok()
+                .map(|stream| stream.into_raw_fd()),
+            Channel::Unix(channel) => channel
+                .input
+                .get_ref()
+                .try_clone()
+                .ok()
+                .map(|stream| stream.into_raw_fd()),
+        }
+    }
+
+    /// The traffic on this channel so far. Encrypted channels count the plaintext, without the encryption
+    /// overhead.
+    pub fn stats(&self) -> ChannelStats {
+        let traffic = match self {
+            Channel::Local(local) => &local.traffic,
+            Channel::Tcp(tcp) => &tcp.traffic,
+            Channel::Unix(unix) => &unix.traffic,
+            Channel::Tls(tls) => &tls.traffic,
+            Channel::Psk(psk) => &psk.traffic,
+            #[cfg(feature = "async")]
+            Channel::Async(bridge) => &bridge.traffic,
+        };
+        traffic.stats
+    }
+
+    /// Starts counting afresh, for example between the programs run over the same channel.
+    pub fn reset_stats(&mut self) {
+        *self.traffic() = Traffic::default();
+    }
+
+    fn traffic(&mut self) -> &mut Traffic {
+        match self {
+            Channel::Local(local) => &mut local.traffic,
+            Channel::Tcp(tcp) => &mut tcp.traffic,
+            Channel::Unix(unix) => &mut unix.traffic,
+            Channel::Tls(tls) => &mut tls.traffic,
+            Channel::Psk(psk) => &mut psk.traffic,
+            #[cfg(feature = "async")]
+            Channel::Async(bridge) => &mut bridge.traffic,
         }
     }
 }
 
 impl Read for Channel {
     fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
-        match self {
+        let size = match self {
             Channel::Local(local) => local.read(buf),
             Channel::Tcp(tcp) => tcp.read(buf),
-        }
+            Channel::Unix(unix) => unix.read(buf),
+            Channel::Tls(tls) => tls.read(buf),
+            Channel::Psk(psk) => psk.read(buf),
+            #[cfg(feature = "async")]
+            Channel::Async(bridge) => bridge.read(buf),
+        }?;
+        self.traffic().received(size);
+        std::io::Result::Ok(size)
     }
 }
 
 impl Write for Channel {
     fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
-        match self {
+        let size = match self {
             Channel::Local(local) => local.write(buf),
             Channel::Tcp(tcp) => tcp.write(buf),
-        }
+            Channel::Unix(unix) => unix.write(buf),
+            Channel::Tls(tls) => tls.write(buf),
+            Channel::Psk(psk) => psk.write(buf),
+            #[cfg(feature = "async")]
+            Channel::Async(bridge) => bridge.write(buf),
+        }?;
+        self.traffic().sent(size);
+        std::io::Result::Ok(size)
     }
 
     fn flush(&mut self) -> std::io::Result<()> {
         match self {
             Channel::Local(local) => local.flush(),
             Channel::Tcp(tcp) => tcp.flush(),
-        }
+            Channel::Unix(unix) => unix.flush(),
+            Channel::Tls(tls) => tls.flush(),
+            Channel::Psk(psk) => psk.flush(),
+            #[cfg(feature = "async")]
+            Channel::Async(bridge) => bridge.flush(),
+        }?;
+        self.traffic().flushed();
+        std::io::Result::Ok(())
+    }
+}
+
+/// Adapts a borrowed `Channel` to swanky's `AbstractChannel`, so it can drive `ocelot` protocols.
+pub struct SwankyChannel<'a>(Rc<RefCell<&'a mut Channel>>);
+
+impl<'a> SwankyChannel<'a> {
+    pub fn new(channel: &'a mut Channel) -> Self {
+        Self(Rc::new(RefCell::new(channel)))
+    }
+}
+
+impl AbstractChannel for SwankyChannel<'_> {
+    fn read_bytes(&mut self, bytes: &mut [u8]) -> std::io::Result<()> {
+        self.0.borrow_mut().read_exact(bytes)
+    }
+
+    fn write_bytes(&mut self, bytes: &[u8]) -> std::io::Result<()> {
+        self.0.borrow_mut().write_all(bytes)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.0.borrow_mut().flush()
+    }
+
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
     }
 }
 
 pub mod ffi {
     use super::*;
+    use crate::error::{self, Status};
+    use crate::util::TlsIdentity;
     use libc::c_char;
     use std::ffi::CStr;
     use std::net::TcpListener;
@@ -134,18 +657,49 @@ pub mod ffi {
         Box::into_raw(Box::new(ret))
     }
 
+    /// Writes two connected in-memory channels to `a` and `b`, each with at most `capacity` bytes in flight, or
+    /// no limit if `capacity` is zero.
+    #[no_mangle]
+    pub unsafe extern "C" fn channel_new_local_pair(
+        capacity: usize,
+        a: *mut *mut Channel,
+        b: *mut *mut Channel,
+    ) {
+        let (left, right) = LocalChannel::bounded_pair(capacity);
+        *a = Box::into_raw(Box::new(Channel::Local(left)));
+        *b = Box::into_raw(Box::new(Channel::Local(right)));
+    }
+
+    /// The options `channel_new_tcp_client` uses, for callers to adjust before passing to
+    /// `channel_new_tcp_client_with`.
+    #[no_mangle]
+    pub extern "C" fn channel_connect_options_default() -> ConnectOptions {
+        ConnectOptions::default()
+    }
+
     #[no_mangle]
     pub unsafe extern "C" fn channel_new_tcp_client(
         host: *const c_char,
         port: u16,
     ) -> *mut Channel {
-        let host_str = CStr::from_ptr(host).to_str().expect("TODO");
-        let mut stream = None;
-        while stream.is_none() {
-            stream = TcpStream::connect((host_str, port)).ok();
-        }
-        let ret = Channel::Tcp(TcpChannel::new(stream.unwrap()));
-        Box::into_raw(Box::new(ret))
+        channel_new_tcp_client_with(host, port, &ConnectOptions::default())
+    }
+
+    /// Like `channel_new_tcp_client`, but retrying and timing out as `options` say. `options` may be null to use
+    /// the defaults.
+    #[no_mangle]
+    pub unsafe extern "C" fn channel_new_tcp_client_with(
+        host: *const c_char,
+        port: u16,
+        options: *const ConnectOptions,
+    ) -> *mut Channel {
+        let ret = (|| {
+            let host_str = CStr::from_ptr(host).to_str()?;
+            let options = options.as_ref().copied().unwrap_or_default();
+            let stream = connect(host_str, port, &options)?;
+            Ok(Channel::Tcp(TcpChannel::new(stream)?))
+        })();
+        error::boxed(ret)
     }
 
     #[no_mangle]
@@ -153,11 +707,108 @@ pub mod ffi {
         host: *const c_char,
         port: u16,
     ) -> *mut Channel {
-        let host_str = CStr::from_ptr(host).to_str().unwrap();
-        let listener = TcpListener::bind((host_str, port)).unwrap();
-        let stream = listener.accept().expect("TODO").0;
-        let ret = Channel::Tcp(TcpChannel::new(stream));
-        Box::into_raw(Box::new(ret))
+        let ret = (|| {
+            let host_str = CStr::from_ptr(host).to_str()?;
+            let listener = TcpListener::bind((host_str, port))?;
+            let stream = listener.accept()?.0;
+            Ok(Channel::Tcp(TcpChannel::new(stream)?))
+        })();
+        error::boxed(ret)
+    }
+
+    /// Connects to the party listening on the Unix domain socket at `path`, waiting for it to start listening as
+    /// `channel_new_tcp_client` does.
+    #[no_mangle]
+    pub unsafe extern "C" fn channel_new_unix_client(path: *const c_char) -> *mut Channel {
+        let ret = (|| {
+            let path = CStr::from_ptr(path).to_str()?;
+            let stream = connect_unix(path, &ConnectOptions::default())?;
+            Ok(Channel::Unix(UnixChannel::new(stream)?))
+        })();
+        error::boxed(ret)
+    }
+
+    /// Listens on a Unix domain socket at `path` for one party to connect.
+    #[no_mangle]
+    pub unsafe extern "C" fn channel_new_unix_server(path: *const c_char) -> *mut Channel {
+        let ret = (|| {
+            let path = CStr::from_ptr(path).to_str()?;
+            Ok(Channel::Unix(UnixChannel::accept(path)?))
+        })();
+        error::boxed(ret)
+    }
+
+    /// Like `channel_new_tcp_client`, but secured with TLS. `cert` and `key` are PEM files holding this party's
+    /// certificate chain and private key, and `ca` holds the authorities trusted to have issued the server's
+    /// certificate, which must be valid for `host`.
+    #[no_mangle]
+    pub unsafe extern "C" fn channel_new_tls_client(
+        host: *const c_char,
+        port: u16,
+        cert: *const c_char,
+        key: *const c_char,
+        ca: *const c_char,
+    ) -> *mut Channel {
+        let ret = (|| {
+            let host_str = CStr::from_ptr(host).to_str()?;
+            let identity = identity(cert, key, ca)?;
+            let stream = connect(host_str, port, &ConnectOptions::default())?;
+            let tls = TlsChannel::client(stream, host_str, &identity)?;
+            Ok(Channel::Tls(tls))
+        })();
+        error::boxed(ret)
+    }
+
+    /// Like `channel_new_tcp_server`, but secured with TLS. The client must present a certificate issued by one
+    /// of the authorities in `ca`.
+    #[no_mangle]
+    pub unsafe extern "C" fn channel_new_tls_server(
+        host: *const c_char,
+        port: u16,
+        cert: *const c_char,
+        key: *const c_char,
+        ca: *const c_char,
+    ) -> *mut Channel {
+        let ret = (|| {
+            let host_str = CStr::from_ptr(host).to_str()?;
+            let identity = identity(cert, key, ca)?;
+            let listener = TcpListener::bind((host_str, port))?;
+            let stream = listener.accept()?.0;
+            Ok(Channel::Tls(TlsChannel::server(stream, &identity)?))
+        })();
+        error::boxed(ret)
+    }
+
+    /// Encrypts and authenticates `inner` with the `key_len`-byte pre-shared `key`, which must be 16 bytes long.
+    /// Takes ownership of `inner`, which is dropped if this fails.
+    #[no_mangle]
+    pub unsafe extern "C" fn channel_new_psk(
+        inner: *mut Channel,
+        key: *const u8,
+        key_len: usize,
+    ) -> *mut Channel {
+        let inner = *Box::from_raw(inner);
+        let ret = (|| {
+            let key = std::slice::from_raw_parts(key, key_len)
+                .try_into()
+                .map_err(|_| {
+                    error::Error::Malformed(format!("key is {} bytes, not 16", key_len))
+                })?;
+            Ok(Channel::Psk(PskChannel::new(inner, key)?))
+        })();
+        error::boxed(ret)
+    }
+
+    unsafe fn identity(
+        cert: *const c_char,
+        key: *const c_char,
+        ca: *const c_char,
+    ) -> Result<TlsIdentity> {
+        TlsIdentity::load(
+            CStr::from_ptr(cert).to_str()?,
+            CStr::from_ptr(key).to_str()?,
+            CStr::from_ptr(ca).to_str()?,
+        )
     }
 
     #[no_mangle]
@@ -166,21 +817,215 @@ pub mod ffi {
     }
 
     #[no_mangle]
-    pub unsafe extern "C" fn channel_send_all(this: *mut Channel, buf: *const u8, len: usize) {
-        (&mut *this)
-            .write_all(std::slice::from_raw_parts(buf, len))
-            .expect("TODO")
+    pub unsafe extern "C" fn channel_send_all(
+        this: *mut Channel,
+        buf: *const u8,
+        len: usize,
+    ) -> Status {
+        let ret = (&mut *this).write_all(std::slice::from_raw_parts(buf, len));
+        error::status(ret.map_err(Into::into))
     }
 
     #[no_mangle]
-    pub unsafe extern "C" fn channel_recv_all(this: *mut Channel, buf: *mut u8, len: usize) {
-        (&mut *this)
-            .read_exact(std::slice::from_raw_parts_mut(buf, len))
-            .expect("TODO")
+    pub unsafe extern "C" fn channel_recv_all(
+        this: *mut Channel,
+        buf: *mut u8,
+        len: usize,
+    ) -> Status {
+        let ret = (&mut *this).read_exact(std::slice::from_raw_parts_mut(buf, len));
+        error::status(ret.map_err(Into::into))
     }
 
     #[no_mangle]
-    pub unsafe extern "C" fn channel_flush(this: *mut Channel) {
-        (&mut *this).flush().expect("TODO")
+    pub unsafe extern "C" fn channel_flush(this: *mut Channel) -> Status {
+        error::status((&mut *this).flush().map_err(Into::into))
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn channel_stats(this: *const Channel) -> ChannelStats {
+        (*this).stats()
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn channel_reset_stats(this: *mut Channel) {
+        (*this).reset_stats()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+
+    /// A port that nothing is listening on.
+    fn closed_port() -> u16 {
+        TcpListener::bind(("127.0.0.1", 0))
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port()
+    }
+
+    #[test]
+    fn connect_gives_up() {
+        let options = ConnectOptions {
+            max_attempts: 3,
+            ..ConnectOptions::default()
+        };
+        let err = connect("127.0.0.1", closed_port(), &options).unwrap_err();
+        assert!(err.to_string().contains("after 3 attempts"), "{}", err);
+    }
+
+    #[test]
+    fn backoff_never_spins() {
+        let options = ConnectOptions {
+            initial_backoff_ms: 0,
+            max_backoff_ms: 0,
+            ..ConnectOptions::default()
+        };
+        let mut attempts = Attempts::new("peer", &options);
+        for _ in 0..3 {
+            attempts.start().unwrap();
+            let e = std::io::Error::from(std::io::ErrorKind::ConnectionRefused);
+            assert_eq!(attempts.failed(e).unwrap(), Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    fn connect_can_be_cancelled() {
+        let cancel = AtomicBool::new(true);
+        let options = ConnectOptions {
+            cancel: &cancel,
+            ..ConnectOptions::default()
+        };
+        match connect("127.0.0.1", closed_port(), &options) {
+            Err(Error::Network(e)) => assert_eq!(e.kind(), std::io::ErrorKind::Interrupted),
+            _ => panic!("connecting was not cancelled"),
+        }
+    }
+
+    #[test]
+    fn reads_time_out() {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let options = ConnectOptions {
+            read_timeout_ms: 50,
+            ..ConnectOptions::default()
+        };
+        let stream = connect("127.0.0.1", port, &options).unwrap();
+        let _peer = listener.accept().unwrap();
+        let mut channel = Channel::Tcp(TcpChannel::new(stream).unwrap());
+        assert!(channel.read_exact(&mut [0; 1]).is_err());
+    }
+
+    #[test]
+    fn unix_round_trip() {
+        let path = std::env::temp_dir().join(format!("symphony-unix-{}.sock", std::process::id()));
+        let server = {
+            let path = path.clone();
+            std::thread::spawn(move || {
+                let mut channel = Channel::Unix(UnixChannel::accept(path).unwrap());
+                let mut buf = [0; 5];
+                channel.read_exact(&mut buf).unwrap();
+                channel.write_all(&buf).unwrap();
+                channel.flush().unwrap();
+            })
+        };
+        let stream = connect_unix(&path, &ConnectOptions::default()).unwrap();
+        let mut channel = Channel::Unix(UnixChannel::new(stream).unwrap());
+        assert!(channel.try_get_socket().is_some());
+        channel.write_all(b"hello").unwrap();
+        channel.flush().unwrap();
+        let mut buf = [0; 5];
+        channel.read_exact(&mut buf).unwrap();
+        server.join().unwrap();
+        assert_eq!(&buf, b"hello");
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn local_loopback() {
+        let mut channel = LocalChannel::new();
+        channel.write_all(b"hello").unwrap();
+        let mut buf = [0; 5];
+        channel.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"hello");
+        let err = channel.read_exact(&mut buf).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn local_pair_waits_for_peer() {
+        let (mut a, mut b) = LocalChannel::pair();
+        let writer = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(50));
+            b.write_all(b"hello").unwrap();
+            // Nothing arrives before the flush.
+            std::thread::sleep(Duration::from_millis(50));
+            b.flush().unwrap();
+        });
+        let mut buf = [0; 5];
+        a.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"hello");
+        writer.join().unwrap();
+        // The peer is gone, so the stream has ended and writes fail.
+        assert_eq!(a.read(&mut buf).unwrap(), 0);
+        a.write_all(b"late").unwrap();
+        assert_eq!(
+            a.flush().unwrap_err().kind(),
+            std::io::ErrorKind::BrokenPipe
+        );
+    }
+
+    #[test]
+    fn bounded_pair_streams() {
+        let data: Vec<u8> = (0..100_000u32).map(|i| (i * 7) as u8).collect();
+        let (mut a, mut b) = LocalChannel::bounded_pair(64);
+        let sent = data.clone();
+        let writer = std::thread::spawn(move || {
+            b.write_all(&sent).unwrap();
+            b.flush().unwrap();
+        });
+        let mut received = vec![0; data.len()];
+        for chunk in received.chunks_mut(1000) {
+            a.read_exact(chunk).unwrap();
+            assert!(a.input.state.lock().unwrap().bytes.len() <= 64);
+        }
+        writer.join().unwrap();
+        assert_eq!(received, data);
+    }
+
+    #[test]
+    fn stats_count_rounds() {
+        let (a, b) = LocalChannel::pair();
+        let (mut a, mut b) = (Channel::Local(a), Channel::Local(b));
+        let mut buf = [0; 5];
+        for _ in 0..2 {
+            a.write_all(b"hello").unwrap();
+            a.flush().unwrap();
+            b.read_exact(&mut buf).unwrap();
+            b.write_all(&buf[..3]).unwrap();
+            b.flush().unwrap();
+            a.read_exact(&mut buf[..3]).unwrap();
+        }
+        // Flushing with nothing written does not start a round.
+        a.flush().unwrap();
+        let a_stats = ChannelStats {
+            bytes_sent: 10,
+            bytes_received: 6,
+            flushes: 3,
+            rounds: 2,
+        };
+        let b_stats = ChannelStats {
+            bytes_sent: 6,
+            bytes_received: 10,
+            flushes: 2,
+            rounds: 1,
+        };
+        assert_eq!(a.stats(), a_stats);
+        assert_eq!(b.stats(), b_stats);
+
+        a.reset_stats();
+        assert_eq!(a.stats(), ChannelStats::default());
     }
 }
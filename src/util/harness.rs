@@ -0,0 +1,98 @@
+//! Runs several parties on threads within one process, connected by in-memory channels.
+
+use crate::util::{Channel, LocalChannel};
+use std::net::TcpListener;
+use std::thread;
+
+/// Ports on the loopback interface that are free at the time of the call, for tests that need real sockets.
+pub fn free_ports(n: usize) -> Vec<u16> {
+    let listeners: Vec<_> = (0..n)
+        .map(|_| TcpListener::bind(("127.0.0.1", 0)).unwrap())
+        .collect();
+    listeners
+        .iter()
+        .map(|l| l.local_addr().unwrap().port())
+        .collect()
+}
+
+/// Connects every pair of `parties` parties with a `LocalChannel`. Entry `[i][j]` is party `i`'s end of the
+/// channel to party `j`, and `[i][i]` is `None`.
+pub fn mesh(parties: usize) -> Vec<Vec<Option<Channel>>> {
+    let mut mesh: Vec<Vec<Option<Channel>>> = (0..parties)
+        .map(|_| (0..parties).map(|_| None).collect())
+        .collect();
+
+    for i in 0..parties {
+        for j in (i + 1)..parties {
+            let (a, b) = LocalChannel::pair();
+            mesh[i][j] = Some(Channel::Local(a));
+            mesh[j][i] = Some(Channel::Local(b));
+        }
+    }
+
+    mesh
+}
+
+/// Runs `program` once per party, each on its own thread, passing the party's id and its entry of `inputs`.
+/// Returns the outputs indexed by party id.
+pub fn run_with<S, T, F>(inputs: Vec<S>, program: F) -> Vec<T>
+where
+    S: Send,
+    T: Send,
+    F: Fn(usize, S) -> T + Sync,
+{
+    let program = &program;
+    thread::scope(|scope| {
+        let handles: Vec<_> = inputs
+            .into_iter()
+            .enumerate()
+            .map(|(id, input)| scope.spawn(move || program(id, input)))
+            .collect();
+
+        handles
+            .into_iter()
+            .map(|h| h.join().expect("party panicked"))
+            .collect()
+    })
+}
+
+/// Runs `program` as each of `parties` parties, connected by a fresh `mesh`.
+pub fn run<T, F>(parties: usize, program: F) -> Vec<T>
+where
+    T: Send,
+    F: Fn(usize, Vec<Option<Channel>>) -> T + Sync,
+{
+    run_with(mesh(parties), program)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+
+    #[test]
+    fn channels_connect_every_pair() {
+        for parties in [2, 3, 5] {
+            let received = run(parties, |id, mut channels| {
+                for c in channels.iter_mut().flatten() {
+                    c.write_all(&[id as u8; 3]).unwrap();
+                    c.flush().unwrap();
+                }
+
+                let mut received = Vec::new();
+                for c in channels.iter_mut() {
+                    let mut buf = [0u8; 3];
+                    match c {
+                        Some(c) => c.read_exact(&mut buf).unwrap(),
+                        None => buf = [id as u8; 3],
+                    }
+                    received.push(buf[0]);
+                }
+                received
+            });
+
+            let expected: Vec<u8> = (0..parties as u8).collect();
+            assert!(received.iter().all(|r| *r == expected));
+        }
+    }
+}
@@ -0,0 +1,284 @@
+//! Connects every pair of parties over TCP from the one description of where each party listens that all parties
+//! share, so no caller has to decide who dials whom.
+
+use crate::error::{Error, Result};
+#[cfg(feature = "async")]
+use crate::util::AsyncChannel;
+use crate::util::{connect, Channel, ConnectOptions, TcpChannel, TlsChannel, TlsIdentity};
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+
+/// Builds the channels from one party to every other. Party `i` listens on `hosts[i]:ports[i]` and accepts
+/// connections from all parties with a larger id, and dials all parties with a smaller one. Each connection starts
+/// with both ends announcing their id and the number of parties, so that a misconfigured address is reported
+/// rather than silently pairing the wrong parties.
+pub struct Mesh<'a> {
+    my_id: usize,
+    hosts: &'a [String],
+    ports: &'a [u16],
+    options: ConnectOptions,
+    tls: Option<&'a TlsIdentity>,
+}
+
+impl<'a> Mesh<'a> {
+    pub fn new(my_id: usize, hosts: &'a [String], ports: &'a [u16]) -> Self {
+        debug_assert_eq!(hosts.len(), ports.len());
+        Self {
+            my_id,
+            hosts,
+            ports,
+            options: ConnectOptions::default(),
+            tls: None,
+        }
+    }
+
+    /// Sets how persistently to dial the other parties, and the timeouts of every established channel.
+    pub fn options(mut self, options: ConnectOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Secures every channel with TLS once the parties have identified themselves, with the dialing party as the
+    /// client. Each party's certificate must be valid for the host it listens on.
+    pub fn tls(mut self, identity: &'a TlsIdentity) -> Self {
+        self.tls = Some(identity);
+        self
+    }
+
+    /// Establishes the channels, returning them indexed by party id, with `None` in this party's own entry.
+    pub fn connect(&self) -> Result<Vec<Option<Channel>>> {
+        let parties = self.hosts.len();
+        let mut channels: Vec<Option<Channel>> = (0..parties).map(|_| None).collect();
+
+        for (id, slot) in channels.iter_mut().enumerate().take(self.my_id) {
+            let mut stream = connect(&self.hosts[id], self.ports[id], &self.options)?;
+            self.announce(&mut stream)?;
+            let (their_id, their_parties) = self.identify(&mut stream)?;
+            self.check_dialed(id, their_id, their_parties)?;
+            *slot = Some(self.channel(stream, Some(&self.hosts[id]))?);
+        }
+
+        if self.my_id + 1 < parties {
+            let listener =
+                TcpListener::bind((self.hosts[self.my_id].as_str(), self.ports[self.my_id]))?;
+            for _ in (self.my_id + 1)..parties {
+                let mut stream = listener.accept()?.0;
+                self.options.configure(&stream)?;
+                let (id, their_parties) = self.identify(&mut stream)?;
+                self.check_accepted(&channels, id, their_parties)?;
+                self.announce(&mut stream)?;
+                channels[id] = Some(self.channel(stream, None)?);
+            }
+        }
+
+        Ok(channels)
+    }
+
+    /// Establishes the channels as `connect` does, but without blocking the runtime. TLS is not supported, and
+    /// the read and write timeouts in the options are not applied.
+    #[cfg(feature = "async")]
+    pub async fn connect_async(&self) -> Result<Vec<Option<AsyncChannel>>> {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        if self.tls.is_some() {
+            return Err(Error::Unsupported("TLS over async channels"));
+        }
+
+        let parties = self.hosts.len();
+        let mut channels: Vec<Option<AsyncChannel>> = (0..parties).map(|_| None).collect();
+        let mut buf = [0u8; 16];
+
+        for (id, slot) in channels.iter_mut().enumerate().take(self.my_id) {
+            let mut channel =
+                AsyncChannel::connect(&self.hosts[id], self.ports[id], &self.options).await?;
+            channel.write_all(&self.announcement()).await?;
+            channel.read_exact(&mut buf).await?;
+            let (their_id, their_parties) = Self::identity(&buf);
+            self.check_dialed(id, their_id, their_parties)?;
+            *slot = Some(channel);
+        }
+
+        if self.my_id + 1 < parties {
+            let listener = tokio::net::TcpListener::bind((
+                self.hosts[self.my_id].as_str(),
+                self.ports[self.my_id],
+            ))
+            .await?;
+            for _ in (self.my_id + 1)..parties {
+                let mut channel = AsyncChannel::Tcp(listener.accept().await?.0);
+                channel.read_exact(&mut buf).await?;
+                let (id, their_parties) = Self::identity(&buf);
+                self.check_accepted(&channels, id, their_parties)?;
+                channel.write_all(&self.announcement()).await?;
+                channels[id] = Some(channel);
+            }
+        }
+
+        Ok(channels)
+    }
+
+    /// Checks that dialing party `id` reached it.
+    fn check_dialed(&self, id: usize, their_id: usize, their_parties: usize) -> Result<()> {
+        let parties = self.hosts.len();
+        if their_id != id || their_parties != parties {
+            return Err(Error::Mismatch(format!(
+                "party {} dialed {}:{} expecting party {} of {}, but reached party {} of {}",
+                self.my_id, self.hosts[id], self.ports[id], id, parties, their_id, their_parties
+            )));
+        }
+        Ok(())
+    }
+
+    /// Checks that an accepted connection comes from a party that should dial this one and has not yet.
+    fn check_accepted<T>(
+        &self,
+        channels: &[Option<T>],
+        id: usize,
+        their_parties: usize,
+    ) -> Result<()> {
+        let parties = self.hosts.len();
+        if id <= self.my_id || id >= parties || their_parties != parties || channels[id].is_some() {
+            return Err(Error::Mismatch(format!(
+                "party {} of {} received a connection from unexpected party {} of {}",
+                self.my_id, parties, id, their_parties
+            )));
+        }
+        Ok(())
+    }
+
+    /// Wraps an identified connection, which this party either dialed at `dialed` or accepted.
+    fn channel(&self, stream: TcpStream, dialed: Option<&str>) -> Result<Channel> {
+        Ok(match (self.tls, dialed) {
+            (None, _) => Channel::Tcp(TcpChannel::new(stream)?),
+            (Some(identity), Some(host)) => {
+                Channel::Tls(TlsChannel::client(stream, host, identity)?)
+            }
+            (Some(identity), None) => Channel::Tls(TlsChannel::server(stream, identity)?),
+        })
+    }
+
+    fn announce(&self, stream: &mut TcpStream) -> Result<()> {
+        stream.write_all(&self.announcement())?;
+        Ok(())
+    }
+
+    /// This party's id and the number of parties, as announced to each peer.
+    fn announcement(&self) -> [u8; 16] {
+        let mut buf = [0u8; 16];
+        buf[..8].copy_from_slice(&(self.my_id as u64).to_le_bytes());
+        buf[8..].copy_from_slice(&(self.hosts.len() as u64).to_le_bytes());
+        buf
+    }
+
+    /// Reads the peer's announced id and number of parties.
+    fn identify(&self, stream: &mut TcpStream) -> Result<(usize, usize)> {
+        let mut buf = [0u8; 16];
+        stream.read_exact(&mut buf)?;
+        Ok(Self::identity(&buf))
+    }
+
+    fn identity(buf: &[u8; 16]) -> (usize, usize) {
+        let id = u64::from_le_bytes(buf[..8].try_into().unwrap()) as usize;
+        let parties = u64::from_le_bytes(buf[8..].try_into().unwrap()) as usize;
+        (id, parties)
+    }
+}
+
+pub mod ffi {
+    use super::*;
+    use crate::error::{self, Status};
+    use crate::util::ffi::c_to_vec;
+    use libc::c_char;
+    use std::ffi::CStr;
+
+    /// Connects party `id` to every other of the `len` parties listening on `hosts` and `ports`, writing party
+    /// `i`'s channel to `out[i]` and null to `out[id]`. `options` may be null to use the defaults.
+    #[no_mangle]
+    pub unsafe extern "C" fn channel_new_mesh(
+        id: usize,
+        hosts: *const *const c_char,
+        ports: *const u16,
+        len: usize,
+        options: *const ConnectOptions,
+        out: *mut *mut Channel,
+    ) -> Status {
+        let ret = (|| {
+            let hosts = c_to_vec(hosts, len)
+                .into_iter()
+                .map(|host_ptr| Ok(CStr::from_ptr(host_ptr).to_str()?.to_owned()))
+                .collect::<Result<Vec<_>>>()?;
+            let ports = c_to_vec(ports, len);
+            let options = options.as_ref().copied().unwrap_or_default();
+            Mesh::new(id, &hosts, &ports).options(options).connect()
+        })();
+        match ret {
+            Ok(channels) => {
+                for (i, channel) in channels.into_iter().enumerate() {
+                    *out.add(i) = match channel {
+                        Some(channel) => Box::into_raw(Box::new(channel)),
+                        None => std::ptr::null_mut(),
+                    };
+                }
+                Status::Ok
+            }
+            Err(e) => error::record(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::harness::free_ports;
+    use std::thread;
+
+    #[test]
+    fn connects_every_pair() {
+        let parties = 4;
+        let hosts = vec!["127.0.0.1".to_owned(); parties];
+        let ports = free_ports(parties);
+        let received: Vec<Vec<u8>> = thread::scope(|scope| {
+            let handles: Vec<_> = (0..parties)
+                .map(|id| {
+                    let (hosts, ports) = (&hosts, &ports);
+                    scope.spawn(move || {
+                        let mut channels = Mesh::new(id, hosts, ports).connect().unwrap();
+                        for c in channels.iter_mut().flatten() {
+                            c.write_all(&[id as u8]).unwrap();
+                            c.flush().unwrap();
+                        }
+                        channels
+                            .iter_mut()
+                            .map(|c| {
+                                let mut buf = [id as u8];
+                                if let Some(c) = c {
+                                    c.read_exact(&mut buf).unwrap();
+                                }
+                                buf[0]
+                            })
+                            .collect()
+                    })
+                })
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+        let expected: Vec<u8> = (0..parties as u8).collect();
+        assert!(received.iter().all(|r| *r == expected));
+    }
+
+    #[test]
+    fn misconfigured_peer_is_reported() {
+        // Party 1 thinks there are three parties, while party 0 thinks there are two.
+        let ports = free_ports(3);
+        let hosts = vec!["127.0.0.1".to_owned(); 3];
+        let (listener_hosts, listener_ports) = (hosts[..2].to_vec(), ports[..2].to_vec());
+        let listener = thread::spawn(move || {
+            Mesh::new(0, &listener_hosts, &listener_ports)
+                .connect()
+                .map(|_| ())
+        });
+        let dialer = Mesh::new(1, &hosts[..3], &ports).connect();
+        assert!(matches!(listener.join().unwrap(), Err(Error::Mismatch(_))));
+        assert!(dialer.is_err());
+    }
+}
@@ -0,0 +1,265 @@
+//! TLS for the TCP transport, with mutual certificate authentication, so that shares crossing untrusted networks
+//! are neither readable nor forgeable by anyone but the parties themselves.
+
+use crate::error::{Error, Result};
+use crate::util::Traffic;
+use rustls::server::AllowAnyAuthenticatedClient;
+use rustls::{
+    Certificate, ClientConfig, ClientConnection, Connection, PrivateKey, RootCertStore,
+    ServerConfig, ServerConnection, ServerName,
+};
+use std::fs::File;
+use std::io::{BufReader, Read, Write};
+use std::net::TcpStream;
+use std::path::Path;
+use std::sync::Arc;
+
+/// A party's certificate chain and private key, along with the certificate authorities it accepts its peers'
+/// certificates from. Every party presents its own certificate and verifies its peer's, whichever end of the
+/// connection it is on.
+#[derive(Clone)]
+pub struct TlsIdentity {
+    certs: Vec<Certificate>,
+    key: PrivateKey,
+    roots: RootCertStore,
+}
+
+impl TlsIdentity {
+    /// Loads PEM files holding this party's certificate chain, its private key (PKCS#8, PKCS#1 or SEC1), and the
+    /// trusted certificate authorities.
+    pub fn load<P: AsRef<Path>>(cert: P, key: P, ca: P) -> Result<Self> {
+        let certs = read_certs(cert.as_ref())?;
+        let key = read_key(key.as_ref())?;
+        let ca = ca.as_ref();
+        let mut roots = RootCertStore::empty();
+        for cert in read_certs(ca)? {
+            roots
+                .add(&cert)
+                .map_err(|e| Error::Malformed(format!("{}: {}", ca.display(), e)))?;
+        }
+        Ok(Self { certs, key, roots })
+    }
+
+    fn client_config(&self) -> Result<Arc<ClientConfig>> {
+        let config = ClientConfig::builder()
+            .with_safe_defaults()
+            .with_root_certificates(self.roots.clone())
+            .with_client_auth_cert(self.certs.clone(), self.key.clone())
+            .map_err(|e| Error::Malformed(e.to_string()))?;
+        Ok(Arc::new(config))
+    }
+
+    fn server_config(&self) -> Result<Arc<ServerConfig>> {
+        let verifier = AllowAnyAuthenticatedClient::new(self.roots.clone()).boxed();
+        let config = ServerConfig::builder()
+            .with_safe_defaults()
+            .with_client_cert_verifier(verifier)
+            .with_single_cert(self.certs.clone(), self.key.clone())
+            .map_err(|e| Error::Malformed(e.to_string()))?;
+        Ok(Arc::new(config))
+    }
+}
+
+fn open(path: &Path) -> Result<BufReader<File>> {
+    let file =
+        File::open(path).map_err(|e| Error::Malformed(format!("{}: {}", path.display(), e)))?;
+    Ok(BufReader::new(file))
+}
+
+fn read_certs(path: &Path) -> Result<Vec<Certificate>> {
+    let certs = rustls_pemfile::certs(&mut open(path)?)
+        .map_err(|e| Error::Malformed(format!("{}: {}", path.display(), e)))?;
+    if certs.is_empty() {
+        return Err(Error::Malformed(format!(
+            "{}: no certificates found",
+            path.display()
+        )));
+    }
+    Ok(certs.into_iter().map(Certificate).collect())
+}
+
+fn read_key(path: &Path) -> Result<PrivateKey> {
+    let mut reader = open(path)?;
+    loop {
+        let item = rustls_pemfile::read_one(&mut reader)
+            .map_err(|e| Error::Malformed(format!("{}: {}", path.display(), e)))?;
+        match item {
+            Some(rustls_pemfile::Item::PKCS8Key(key))
+            | Some(rustls_pemfile::Item::RSAKey(key))
+            | Some(rustls_pemfile::Item::ECKey(key)) => return Ok(PrivateKey(key)),
+            Some(_) => continue,
+            None => {
+                return Err(Error::Malformed(format!(
+                    "{}: no private key found",
+                    path.display()
+                )))
+            }
+        }
+    }
+}
+
+/// A TCP connection secured with TLS.
+///
+/// Like `TcpChannel`, writes are buffered until `flush`; reads first send anything still buffered.
+pub struct TlsChannel {
+    conn: Connection,
+    stream: TcpStream,
+    pub(crate) traffic: Traffic,
+}
+
+impl TlsChannel {
+    /// Secures `stream` as the connecting party, expecting the peer's certificate to be issued for `host`.
+    pub fn client(stream: TcpStream, host: &str, identity: &TlsIdentity) -> Result<Self> {
+        let name = ServerName::try_from(host)
+            .map_err(|_| Error::Malformed(format!("invalid server name {:?}", host)))?;
+        let conn = ClientConnection::new(identity.client_config()?, name)
+            .map_err(|e| Error::Malformed(e.to_string()))?;
+        Self::handshake(conn.into(), stream)
+    }
+
+    /// Secures `stream` as the accepting party.
+    pub fn server(stream: TcpStream, identity: &TlsIdentity) -> Result<Self> {
+        let conn = ServerConnection::new(identity.server_config()?)
+            .map_err(|e| Error::Malformed(e.to_string()))?;
+        Self::handshake(conn.into(), stream)
+    }
+
+    fn handshake(mut conn: Connection, mut stream: TcpStream) -> Result<Self> {
+        while conn.is_handshaking() {
+            conn.complete_io(&mut stream).map_err(|e| {
+                if e.kind() == std::io::ErrorKind::InvalidData {
+                    Error::Mismatch(format!("TLS handshake failed: {}", e))
+                } else {
+                    e.into()
+                }
+            })?;
+        }
+        Ok(Self {
+            conn,
+            stream,
+            traffic: Traffic::default(),
+        })
+    }
+}
+
+impl Read for TlsChannel {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match &mut self.conn {
+            Connection::Client(conn) => rustls::Stream::new(conn, &mut self.stream).read(buf),
+            Connection::Server(conn) => rustls::Stream::new(conn, &mut self.stream).read(buf),
+        }
+    }
+}
+
+impl Write for TlsChannel {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let mut size = self.conn.writer().write(buf)?;
+        if size == 0 && !buf.is_empty() {
+            // The connection's plaintext buffer is full, so encrypt and send it before accepting more.
+            self.flush()?;
+            size = self.conn.writer().write(buf)?;
+        }
+        std::io::Result::Ok(size)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        while self.conn.wants_write() {
+            self.conn.write_tls(&mut self.stream)?;
+        }
+        self.stream.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+    use std::path::PathBuf;
+    use std::thread;
+
+    fn authority() -> rcgen::Certificate {
+        let mut params = rcgen::CertificateParams::new(Vec::new());
+        params.is_ca = rcgen::IsCa::Ca(rcgen::BasicConstraints::Unconstrained);
+        rcgen::Certificate::from_params(params).unwrap()
+    }
+
+    /// Issues a certificate for `localhost` from `ca`, writing it, its key and `trusted` to files named after
+    /// `name`, and returns their paths.
+    fn issue(
+        name: &str,
+        ca: &rcgen::Certificate,
+        trusted: &rcgen::Certificate,
+    ) -> (PathBuf, PathBuf, PathBuf) {
+        let cert = rcgen::generate_simple_self_signed(vec!["localhost".to_owned()]).unwrap();
+        let dir = std::env::temp_dir();
+        let path = |kind: &str| {
+            dir.join(format!(
+                "symphony-tls-{}-{}-{}.pem",
+                std::process::id(),
+                name,
+                kind
+            ))
+        };
+        let paths = (path("cert"), path("key"), path("ca"));
+        std::fs::write(&paths.0, cert.serialize_pem_with_signer(ca).unwrap()).unwrap();
+        std::fs::write(&paths.1, cert.serialize_private_key_pem()).unwrap();
+        std::fs::write(&paths.2, trusted.serialize_pem().unwrap()).unwrap();
+        paths
+    }
+
+    fn load(paths: &(PathBuf, PathBuf, PathBuf)) -> TlsIdentity {
+        let identity = TlsIdentity::load(&paths.0, &paths.1, &paths.2).unwrap();
+        for path in [&paths.0, &paths.1, &paths.2] {
+            std::fs::remove_file(path).unwrap();
+        }
+        identity
+    }
+
+    /// Connects a client and a server over loopback TCP, returning both ends' handshake results.
+    fn connect(
+        client: TlsIdentity,
+        server: TlsIdentity,
+    ) -> (Result<TlsChannel>, Result<TlsChannel>) {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            let stream = listener.accept().unwrap().0;
+            TlsChannel::server(stream, &server)
+        });
+        let stream = TcpStream::connect(addr).unwrap();
+        let client = TlsChannel::client(stream, "localhost", &client);
+        (client, server.join().unwrap())
+    }
+
+    #[test]
+    fn mutual_authentication() {
+        let ca = authority();
+        let client = load(&issue("client", &ca, &ca));
+        let server = load(&issue("server", &ca, &ca));
+        let (client, server) = connect(client, server);
+        let (mut client, mut server) = (client.unwrap(), server.unwrap());
+
+        let message = vec![0x5a; 100_000];
+        let echo = thread::spawn(move || {
+            let mut buf = vec![0u8; 100_000];
+            server.read_exact(&mut buf).unwrap();
+            server.write_all(&buf).unwrap();
+            server.flush().unwrap();
+        });
+        client.write_all(&message).unwrap();
+        client.flush().unwrap();
+        let mut buf = vec![0u8; message.len()];
+        client.read_exact(&mut buf).unwrap();
+        echo.join().unwrap();
+        assert_eq!(buf, message);
+    }
+
+    #[test]
+    fn untrusted_client_is_rejected() {
+        let (ca, rogue) = (authority(), authority());
+        let client = load(&issue("rogue", &rogue, &ca));
+        let server = load(&issue("trusting", &ca, &ca));
+        let (_, server) = connect(client, server);
+        assert!(matches!(server, Err(Error::Mismatch(_))));
+    }
+}
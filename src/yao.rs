@@ -1,81 +1,147 @@
-mod emp {
-    pub struct NetIo {
-        repr: *mut libc::c_void,
-    }
+use crate::error::Result;
+use std::ffi::CStr;
 
-    impl NetIo {
-        pub fn new_server(port: u16) -> Self {
-            let repr = unsafe { ffi::emp_netio_new(std::ptr::null(), port) };
-            Self { repr }
-        }
+mod garble;
+use garble as engine;
 
-        pub fn new_client(host: String, port: u16) -> Self {
-            let c_host = std::ffi::CString::new(host).expect("TODO").as_ptr();
-            let repr = unsafe { ffi::emp_netio_new(c_host, port) };
-            Self { repr }
-        }
-    }
+use crate::util::Channel;
 
-    impl Drop for NetIo {
-        fn drop(&mut self) {
-            unsafe { ffi::emp_netio_delete(self.repr) }
-        }
-    }
+/// A two-party Yao (garbled circuit) protocol instance, each owned by a participating party.
+///
+/// Party 0 garbles and party 1 evaluates. Values enter and leave the circuit as XOR shares, exactly as in
+/// `gmw`, so the `gmw_share_*` and `gmw_reveal_*` helpers apply unchanged.
+pub struct Protocol {
+    context: engine::Context,
+}
 
-    pub struct Context {
-        net: NetIo,
-        circuitExe: *mut libc::c_void,
-        protocolExe: *mut libc::c_void,
+impl Protocol {
+    /// Party 0 listens on `port`, and party 1 connects to it at `host:port`. A protocol that exchanges shares with
+    /// a `gmw::Protocol` still needs this connection of its own, on a port the GMW protocol does not use.
+    pub fn new(my_id: usize, host: String, port: u16) -> Result<Self> {
+        debug_assert!(my_id < 2);
+        let (party, net) = if my_id == 0 {
+            (engine::ALICE, engine::NetIo::new_server(port)?)
+        } else {
+            (engine::BOB, engine::NetIo::new_client(host, port)?)
+        };
+        Ok(Self {
+            context: engine::Context::new(party, net),
+        })
     }
 
-    impl Context {
-        pub fn new(party: i8, net: NetIo) -> Self {
-            unsafe { ffi::emp_setup_semi_honest(net.repr, party) };
-            let circuitExe = unsafe { ffi::emp_get_circuit_exe() };
-            let protocolExe = unsafe { ffi::emp_get_protocol_exe() };
-            Self {
-                net,
-                circuitExe,
-                protocolExe,
-            }
-        }
-
-        fn set_context(&mut self) {
-            unsafe { ffi::emp_set_exe(self.circuitExe, self.protocolExe) }
+    /// Runs over an already-established channel to the other party.
+    pub fn from_channel(my_id: usize, channel: Channel) -> Self {
+        debug_assert!(my_id < 2);
+        let party = if my_id == 0 {
+            engine::ALICE
+        } else {
+            engine::BOB
+        };
+        Self {
+            context: engine::Context::new(party, engine::NetIo::from_channel(channel)),
         }
     }
+}
 
-    impl Drop for Context {
-        fn drop(&mut self) {
-            self.set_context();
-            unsafe { ffi::emp_finalize_semi_honest() }
-        }
+pub mod ffi {
+    use super::*;
+    use crate::error;
+
+    #[no_mangle]
+    pub unsafe extern "C" fn yao_protocol_new(
+        id: usize,
+        host: *const libc::c_char,
+        port: u16,
+    ) -> *mut Protocol {
+        let ret = CStr::from_ptr(host)
+            .to_str()
+            .map_err(Into::into)
+            .and_then(|host| Protocol::new(id, host.to_owned(), port));
+        error::boxed(ret)
     }
 
-    pub struct Bool {
-        repr: *mut libc::c_void,
+    #[no_mangle]
+    pub unsafe extern "C" fn yao_protocol_drop(protocol: *mut Protocol) {
+        Box::from_raw(protocol);
     }
 
-    impl Bool {
-        pub fn new(context: &mut Context, share: bool) -> Self {
-            context.set_context();
-            let repr = unsafe { ffi::emp_yao_bool_new(share) };
-            Self { repr }
-        }
+    pub use boolean::ffi::*;
+    pub use convert::ffi::*;
+    pub use integer::ffi::*;
+    pub use natural::ffi::*;
+}
+
+mod boolean;
+pub use boolean::Bool;
+
+mod natural;
+pub use natural::Nat;
+
+mod integer;
+pub use integer::Int;
+
+mod convert;
+pub use convert::{b2y, b2y_int, b2y_nat, y2b, y2b_int, y2b_nat};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::harness;
+
+    #[test]
+    fn shares_in_and_out() {
+        let (a, b) = (1234u32, 56u32);
+        let (a_mask, b_mask) = (0x5a5a_5a5a_u32, 0x0f0f_f0f0_u32);
+        let inputs = [(a ^ a_mask, b ^ b_mask), (a_mask, b_mask)];
+
+        let outputs = harness::run(2, |my_id, mut channels| {
+            let channel = channels[1 - my_id].take().unwrap();
+            let protocol = &mut Protocol::from_channel(my_id, channel);
+            let (a, b) = inputs[my_id];
+
+            let x = Nat::new(protocol, &a.to_le_bytes());
+            let y = Nat::new(protocol, &b.to_le_bytes());
+            let sum = Nat::add(protocol, &x, &y);
+            let quot = {
+                let x = Int::new(protocol, &a.to_le_bytes());
+                let y = Int::new(protocol, &b.to_le_bytes());
+                Int::div(protocol, &x, &y)
+            };
+            let lt = Nat::lt(protocol, &x, &y);
+
+            let sum = Nat::get(protocol, &sum).unwrap();
+            let quot = Int::get(protocol, &quot).unwrap();
+            let sum = u32::from_le_bytes(sum.try_into().unwrap());
+            let quot = u32::from_le_bytes(quot.try_into().unwrap());
+            let lt = Bool::get(protocol, &lt).unwrap();
+            (sum, quot, lt)
+        });
+
+        assert_eq!(outputs[0].0 ^ outputs[1].0, a + b);
+        assert_eq!(outputs[0].1 ^ outputs[1].1, a / b);
+        assert_eq!(outputs[0].2 ^ outputs[1].2, a < b);
     }
 
-    mod ffi {
-        extern "C" {
-            pub fn emp_netio_new(host: *const libc::c_char, port: u16) -> *mut libc::c_void;
-            pub fn emp_netio_delete(netio: *mut libc::c_void);
+    #[test]
+    fn fixed_width_getters_check_width() {
+        use crate::error::Status;
 
-            pub fn emp_setup_semi_honest(net: *mut libc::c_void, party: i8);
-            pub fn emp_get_circuit_exe() -> *mut libc::c_void;
-            pub fn emp_get_protocol_exe() -> *mut libc::c_void;
-            pub fn emp_set_exe(circuitExe: *mut libc::c_void, protocolExe: *mut libc::c_void);
-            pub fn emp_finalize_semi_honest();
+        let statuses = harness::run(2, |my_id, mut channels| {
+            let channel = channels[1 - my_id].take().unwrap();
+            let protocol = &mut Protocol::from_channel(my_id, channel);
+            let mut nat = Nat::new(protocol, &[1, 2]);
+            let mut int = Int::new(protocol, &[1, 2, 3, 4, 5, 6, 7, 8]);
+            let (mut nat_out, mut int_out) = (0, 0);
+            unsafe {
+                (
+                    ffi::yao_nat32_get(protocol, &mut nat, &mut nat_out),
+                    ffi::yao_int32_get(protocol, &mut int, &mut int_out),
+                )
+            }
+        });
 
-            pub fn emp_yao_bool_new(share: bool) -> *mut libc::c_void;
+        for status in statuses {
+            assert_eq!(status, (Status::Malformed, Status::Malformed));
         }
     }
 }
@@ -0,0 +1,139 @@
+use crate::error::Result;
+use crate::yao::engine;
+use crate::yao::Protocol;
+
+/// A boolean garbled-circuit value.
+#[derive(Debug, Clone)]
+pub struct Bool {
+    pub(super) repr: engine::Bool,
+}
+
+impl Bool {
+    /// Inputs this party's XOR share of a value. Both parties must call `new` at the same point.
+    pub fn new(protocol: &mut Protocol, share: bool) -> Self {
+        let context = &mut protocol.context;
+        let alice = engine::Bool::new(context, share, engine::ALICE);
+        let bob = engine::Bool::new(context, share, engine::BOB);
+        let repr = engine::Bool::xor(context, &alice, &bob);
+        Self { repr }
+    }
+
+    pub fn constant(protocol: &mut Protocol, value: bool) -> Self {
+        let repr = engine::Bool::constant(&mut protocol.context, value);
+        Self { repr }
+    }
+
+    pub fn xor(protocol: &mut Protocol, a: &Self, b: &Self) -> Self {
+        let repr = engine::Bool::xor(&mut protocol.context, &a.repr, &b.repr);
+        Self { repr }
+    }
+
+    pub fn or(protocol: &mut Protocol, a: &Self, b: &Self) -> Self {
+        let ab = Self::and(protocol, a, b);
+        let axb = Self::xor(protocol, a, b);
+        Self::xor(protocol, &ab, &axb)
+    }
+
+    pub fn and(protocol: &mut Protocol, a: &Self, b: &Self) -> Self {
+        let repr = engine::Bool::and(&mut protocol.context, &a.repr, &b.repr);
+        Self { repr }
+    }
+
+    pub fn not(protocol: &mut Protocol, share: &Self) -> Self {
+        let repr = engine::Bool::not(&mut protocol.context, &share.repr);
+        Self { repr }
+    }
+
+    pub fn mux(protocol: &mut Protocol, g: &Self, a: &Self, b: &Self) -> Self {
+        let repr = engine::Bool::mux(&mut protocol.context, &g.repr, &a.repr, &b.repr);
+        Self { repr }
+    }
+
+    pub fn eq(protocol: &mut Protocol, a: &Self, b: &Self) -> Self {
+        let axb = Self::xor(protocol, a, b);
+        Self::not(protocol, &axb)
+    }
+
+    /// Returns this party's XOR share of the value.
+    pub fn get(protocol: &mut Protocol, share: &Self) -> Result<bool> {
+        engine::Bool::get(&mut protocol.context, &share.repr)
+    }
+}
+
+pub mod ffi {
+    use super::*;
+    use crate::error::{self, Status};
+
+    #[no_mangle]
+    pub unsafe extern "C" fn yao_bool_new(protocol: *mut Protocol, share: bool) -> *mut Bool {
+        let ret = Bool::new(&mut *protocol, share);
+        Box::into_raw(Box::new(ret))
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn yao_bool_constant(protocol: *mut Protocol, value: bool) -> *mut Bool {
+        let ret = Bool::constant(&mut *protocol, value);
+        Box::into_raw(Box::new(ret))
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn yao_bool_get(
+        protocol: *mut Protocol,
+        share: *mut Bool,
+        out: *mut bool,
+    ) -> Status {
+        error::status_out(Bool::get(&mut *protocol, &*share), out)
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn yao_bool_xor(
+        protocol: *mut Protocol,
+        a: *mut Bool,
+        b: *mut Bool,
+    ) -> *mut Bool {
+        let ret = Bool::xor(&mut *protocol, &*a, &*b);
+        Box::into_raw(Box::new(ret))
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn yao_bool_and(
+        protocol: *mut Protocol,
+        a: *mut Bool,
+        b: *mut Bool,
+    ) -> *mut Bool {
+        let ret = Bool::and(&mut *protocol, &*a, &*b);
+        Box::into_raw(Box::new(ret))
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn yao_bool_or(
+        protocol: *mut Protocol,
+        a: *mut Bool,
+        b: *mut Bool,
+    ) -> *mut Bool {
+        let ret = Bool::or(&mut *protocol, &*a, &*b);
+        Box::into_raw(Box::new(ret))
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn yao_bool_not(protocol: *mut Protocol, share: *mut Bool) -> *mut Bool {
+        let ret = Bool::not(&mut *protocol, &*share);
+        Box::into_raw(Box::new(ret))
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn yao_bool_mux(
+        protocol: *mut Protocol,
+        g: *mut Bool,
+        a: *mut Bool,
+        b: *mut Bool,
+    ) -> *mut Bool {
+        let ret = Bool::mux(&mut *protocol, &*g, &*a, &*b);
+        Box::into_raw(Box::new(ret))
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn yao_bool_drop(share: *mut Bool) {
+        Box::from_raw(share);
+    }
+}
@@ -0,0 +1,210 @@
+//! Conversions between two-party GMW shares and garbled values, for programs that mix the two protocols.
+//!
+//! Both protocols represent values as XOR shares between the same two parties, with GMW party 0 acting as the
+//! Yao garbler. B2Y evaluates the GMW share and inputs it into the garbled circuit, costing one oblivious
+//! transfer per bit for the evaluator's half. Y2B needs no communication at all, since each party's Yao share
+//! is just the permute bit of its label. Every conversion fails with `Error::Mismatch` if the GMW protocol does
+//! not have exactly two parties.
+//!
+//! The two protocols do not share a connection. The Yao `Protocol` needs a channel of its own to the other party,
+//! alongside the GMW protocol's, for instance a second port passed to `yao::Protocol::new`.
+
+use crate::error::{Error, Result};
+use crate::gmw;
+use crate::gmw::Backend;
+use crate::yao::{Bool, Int, Nat, Protocol};
+
+fn check_parties<B: Backend>(gmw: &gmw::Protocol<B>) -> Result<()> {
+    match gmw.parties() {
+        2 => Ok(()),
+        n => Err(Error::Mismatch(format!(
+            "converting to or from Yao needs two GMW parties, not {}",
+            n
+        ))),
+    }
+}
+
+pub fn b2y<B: Backend>(
+    yao: &mut Protocol,
+    gmw: &mut gmw::Protocol<B>,
+    share: &gmw::Bool<B>,
+) -> Result<Bool> {
+    check_parties(gmw)?;
+    let share = gmw::Bool::get(gmw, share)?;
+    Ok(Bool::new(yao, share))
+}
+
+pub fn y2b<B: Backend>(
+    yao: &mut Protocol,
+    gmw: &mut gmw::Protocol<B>,
+    share: &Bool,
+) -> Result<gmw::Bool<B>> {
+    check_parties(gmw)?;
+    let share = Bool::get(yao, share)?;
+    Ok(gmw::Bool::new(gmw, share))
+}
+
+pub fn b2y_nat<B: Backend>(
+    yao: &mut Protocol,
+    gmw: &mut gmw::Protocol<B>,
+    share: &gmw::Nat<B>,
+) -> Result<Nat> {
+    check_parties(gmw)?;
+    let share = gmw::Nat::get(gmw, share)?;
+    Ok(Nat::new(yao, &share))
+}
+
+pub fn y2b_nat<B: Backend>(
+    yao: &mut Protocol,
+    gmw: &mut gmw::Protocol<B>,
+    share: &Nat,
+) -> Result<gmw::Nat<B>> {
+    check_parties(gmw)?;
+    let share = Nat::get(yao, share)?;
+    Ok(gmw::Nat::new(gmw, &share))
+}
+
+pub fn b2y_int<B: Backend>(
+    yao: &mut Protocol,
+    gmw: &mut gmw::Protocol<B>,
+    share: &mut gmw::Int<B>,
+) -> Result<Int> {
+    check_parties(gmw)?;
+    let share = gmw::Int::get(gmw, share)?;
+    Ok(Int::new(yao, &share))
+}
+
+pub fn y2b_int<B: Backend>(
+    yao: &mut Protocol,
+    gmw: &mut gmw::Protocol<B>,
+    share: &Int,
+) -> Result<gmw::Int<B>> {
+    check_parties(gmw)?;
+    let share = Int::get(yao, share)?;
+    Ok(gmw::Int::new(gmw, &share))
+}
+
+/// `yao` and `gmw` must connect the same two parties with the same ids, each over its own connection: the port
+/// given to `yao_protocol_new` must differ from those given to `gmw_protocol_new`.
+pub mod ffi {
+    use super::*;
+    use crate::error;
+    use crate::gmw::CachedBool;
+    use std::sync::Mutex;
+
+    #[no_mangle]
+    pub unsafe extern "C" fn yao_b2y_bool(
+        yao: *mut Protocol,
+        gmw: *mut gmw::Protocol,
+        share_raw: *const Mutex<CachedBool>,
+    ) -> *mut Bool {
+        let share = gmw::Bool::from_raw(share_raw);
+        let ret = b2y(&mut *yao, &mut *gmw, &share);
+        assert_eq!(share_raw, gmw::Bool::into_raw(share));
+        error::boxed(ret)
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn yao_y2b_bool(
+        yao: *mut Protocol,
+        gmw: *mut gmw::Protocol,
+        share: *mut Bool,
+    ) -> *const Mutex<CachedBool> {
+        match y2b(&mut *yao, &mut *gmw, &*share) {
+            Ok(ret) => gmw::Bool::into_raw(ret),
+            Err(e) => {
+                error::record(e);
+                std::ptr::null()
+            }
+        }
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn yao_b2y_nat(
+        yao: *mut Protocol,
+        gmw: *mut gmw::Protocol,
+        share: *mut gmw::Nat,
+    ) -> *mut Nat {
+        error::boxed(b2y_nat(&mut *yao, &mut *gmw, &*share))
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn yao_y2b_nat(
+        yao: *mut Protocol,
+        gmw: *mut gmw::Protocol,
+        share: *mut Nat,
+    ) -> *mut gmw::Nat {
+        error::boxed(y2b_nat(&mut *yao, &mut *gmw, &*share))
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn yao_b2y_int(
+        yao: *mut Protocol,
+        gmw: *mut gmw::Protocol,
+        share: *mut gmw::Int,
+    ) -> *mut Int {
+        error::boxed(b2y_int(&mut *yao, &mut *gmw, &mut *share))
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn yao_y2b_int(
+        yao: *mut Protocol,
+        gmw: *mut gmw::Protocol,
+        share: *mut Int,
+    ) -> *mut gmw::Int {
+        error::boxed(y2b_int(&mut *yao, &mut *gmw, &*share))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gmw::{Native, Plain};
+    use crate::util::harness;
+    use crate::util::{Channel, LocalChannel};
+
+    #[test]
+    fn round_trip() {
+        let (a, b) = (1234u32, 4321u32);
+        let (a_mask, b_mask) = (0x1357_9bdf_u32, 0x0246_8ace_u32);
+        let inputs = [(a ^ a_mask, b ^ b_mask), (a_mask, b_mask)];
+
+        let meshes = harness::mesh(2).into_iter().zip(harness::mesh(2)).collect();
+        let outputs = harness::run_with(meshes, |my_id, (mut yao, engine)| {
+            let yao = &mut Protocol::from_channel(my_id, yao[1 - my_id].take().unwrap());
+            let gmw = &mut gmw::Protocol::with_backend(Native::from_channels(my_id, engine));
+            let (a, b) = inputs[my_id];
+
+            // Add in GMW, compare in Yao, and select in GMW.
+            let x = gmw::Nat::new(gmw, &a.to_le_bytes());
+            let y = gmw::Nat::new(gmw, &b.to_le_bytes());
+            let sum = gmw::Nat::add(gmw, &x, &y);
+
+            let sum_y = b2y_nat(yao, gmw, &sum).unwrap();
+            let y_y = b2y_nat(yao, gmw, &y).unwrap();
+            let gt = Nat::gt(yao, &sum_y, &y_y);
+            let gt = y2b(yao, gmw, &gt).unwrap();
+
+            let sum = y2b_nat(yao, gmw, &sum_y).unwrap();
+            let max = gmw::Nat::mux(gmw, &gt, &sum, &y);
+
+            let max = gmw::Nat::get(gmw, &max).unwrap();
+            let max = u32::from_le_bytes(max.try_into().unwrap());
+            let gt = gmw::Bool::get(gmw, &gt).unwrap();
+            (max, gt)
+        });
+
+        assert_eq!(outputs[0].0 ^ outputs[1].0, a + b);
+        assert!(outputs[0].1 ^ outputs[1].1);
+    }
+
+    #[test]
+    fn needs_two_parties() {
+        let yao = &mut Protocol::from_channel(0, Channel::Local(LocalChannel::new()));
+        let gmw = &mut gmw::Protocol::with_backend(Plain::new(3));
+        let share = gmw::Bool::new(gmw, true);
+        assert!(matches!(b2y(yao, gmw, &share), Err(Error::Mismatch(_))));
+        let share = Bool::constant(yao, true);
+        assert!(matches!(y2b(yao, gmw, &share), Err(Error::Mismatch(_))));
+    }
+}
@@ -0,0 +1,588 @@
+//! A pure-Rust semi-honest Yao engine, which the `yao` module runs on.
+//!
+//! Gates are garbled with free-XOR and half-gates ([ZRE15](https://eprint.iacr.org/2014/756)), hashing labels
+//! with fixed-key AES. Circuits are garbled and evaluated as they are built: the garbler streams the two
+//! ciphertexts of each AND gate to the evaluator, which consumes them as it reaches the same gate. The evaluator
+//! obtains the labels of its own inputs by oblivious transfer.
+//!
+//! The garbler holds the zero label of each wire and the evaluator holds its active label. Since the free-XOR
+//! offset has its least significant bit set, the least significant bits of the two form an XOR sharing of the
+//! wire's value, which is what `get` returns.
+//!
+//! Gates are built infallibly, so a failure while garbling or evaluating is recorded in the `Context`, which then
+//! stops communicating and reports the failure from every later `get`.
+
+use crate::error::{Error, Result};
+use crate::util;
+use crate::util::{Channel, ConnectOptions, SwankyChannel, TcpChannel};
+use ocelot::ot::{AlszReceiver, AlszSender, Receiver, Sender};
+use rand::Rng;
+use scuttlebutt::{AbstractChannel, AesRng, Block, AES_HASH};
+use std::io::Write;
+use std::net::TcpListener;
+
+pub const ALICE: i8 = 1;
+pub const BOB: i8 = 2;
+
+pub struct NetIo {
+    channel: Channel,
+}
+
+impl NetIo {
+    pub fn new_server(port: u16) -> Result<Self> {
+        let listener = TcpListener::bind(("0.0.0.0", port))?;
+        let stream = listener.accept()?.0;
+        Ok(Self::from_channel(Channel::Tcp(TcpChannel::new(stream)?)))
+    }
+
+    pub fn new_client(host: String, port: u16) -> Result<Self> {
+        let stream = util::connect(&host, port, &ConnectOptions::default())?;
+        Ok(Self::from_channel(Channel::Tcp(TcpChannel::new(stream)?)))
+    }
+
+    pub fn from_channel(channel: Channel) -> Self {
+        Self { channel }
+    }
+}
+
+pub struct Context {
+    party: i8,
+    channel: Channel,
+    rng: AesRng,
+    /// The free-XOR offset between the two labels of every wire. Only known to the garbler.
+    delta: Block,
+    /// The number of AND gates so far, from which each gate's hash tweaks are derived.
+    gates: u128,
+    sender: Option<AlszSender>,
+    receiver: Option<AlszReceiver>,
+    /// The first failure, after which the context no longer communicates.
+    error: Option<Error>,
+}
+
+impl Context {
+    pub fn new(party: i8, net: NetIo) -> Self {
+        debug_assert!(party == ALICE || party == BOB);
+        let mut rng = AesRng::new();
+        let delta = if party == ALICE {
+            rng.gen::<Block>().set_lsb()
+        } else {
+            Block::default()
+        };
+        Self {
+            party,
+            channel: net.channel,
+            rng,
+            delta,
+            gates: 0,
+            sender: None,
+            receiver: None,
+            error: None,
+        }
+    }
+
+    fn is_garbler(&self) -> bool {
+        self.party == ALICE
+    }
+
+    /// The garbler's zero label for a wire carrying the public value `value`.
+    fn select(&self, value: bool) -> Block {
+        if value {
+            self.delta
+        } else {
+            Block::default()
+        }
+    }
+
+    /// Runs `f` unless the context has already failed, recording its error if it fails now. Failed operations
+    /// produce `T::default()`, which is meaningless but never observed, since `get` reports the failure instead.
+    fn guard<T: Default>(&mut self, f: impl FnOnce(&mut Self) -> Result<T>) -> T {
+        if self.error.is_some() {
+            return T::default();
+        }
+        f(self).unwrap_or_else(|e| {
+            self.error = Some(e);
+            T::default()
+        })
+    }
+
+    /// Inputs `values` on behalf of `party`, returning this party's label for each.
+    fn input(&mut self, values: &[bool], party: i8) -> Vec<Block> {
+        let labels = self.guard(|context| context.try_input(values, party));
+        if labels.is_empty() {
+            vec![Block::default(); values.len()]
+        } else {
+            labels
+        }
+    }
+
+    fn try_input(&mut self, values: &[bool], party: i8) -> Result<Vec<Block>> {
+        match (self.is_garbler(), party == ALICE) {
+            (true, true) => {
+                let labels: Vec<Block> = values.iter().map(|_| self.rng.gen()).collect();
+                let actives: Vec<Block> = labels
+                    .iter()
+                    .zip(values)
+                    .map(|(&label, &value)| label ^ self.select(value))
+                    .collect();
+                let mut channel = SwankyChannel::new(&mut self.channel);
+                for active in actives {
+                    channel.write_block(&active)?;
+                }
+                Ok(labels)
+            }
+            (false, true) => {
+                let mut channel = SwankyChannel::new(&mut self.channel);
+                let labels = values
+                    .iter()
+                    .map(|_| channel.read_block())
+                    .collect::<std::io::Result<_>>()?;
+                Ok(labels)
+            }
+            (true, false) => {
+                let labels: Vec<Block> = values.iter().map(|_| self.rng.gen()).collect();
+                let inputs: Vec<(Block, Block)> =
+                    labels.iter().map(|&l| (l, l ^ self.delta)).collect();
+
+                let Self {
+                    channel,
+                    rng,
+                    sender,
+                    ..
+                } = self;
+                let mut channel = SwankyChannel::new(channel);
+                channel.flush()?;
+                if sender.is_none() {
+                    *sender = Some(AlszSender::init(&mut channel, rng)?);
+                }
+                let sender = sender.as_mut().unwrap();
+                sender.send(&mut channel, &inputs, rng)?;
+                Ok(labels)
+            }
+            (false, false) => {
+                let Self {
+                    channel,
+                    rng,
+                    receiver,
+                    ..
+                } = self;
+                let mut channel = SwankyChannel::new(channel);
+                channel.flush()?;
+                if receiver.is_none() {
+                    *receiver = Some(AlszReceiver::init(&mut channel, rng)?);
+                }
+                let receiver = receiver.as_mut().unwrap();
+                Ok(receiver.receive(&mut channel, values, rng)?)
+            }
+        }
+    }
+
+    /// Garbles (or evaluates) a half-gates AND gate.
+    fn and(&mut self, a: Block, b: Block) -> Block {
+        self.guard(|context| context.try_and(a, b))
+    }
+
+    fn try_and(&mut self, a: Block, b: Block) -> Result<Block> {
+        let tweak_g = Block::from(2 * self.gates);
+        let tweak_e = Block::from(2 * self.gates + 1);
+        self.gates += 1;
+
+        if self.is_garbler() {
+            let (a1, b1) = (a ^ self.delta, b ^ self.delta);
+            let (ha0, ha1) = (hash(a, tweak_g), hash(a1, tweak_g));
+            let (hb0, hb1) = (hash(b, tweak_e), hash(b1, tweak_e));
+
+            let table_g = ha0 ^ ha1 ^ self.select(b.lsb());
+            let table_e = hb0 ^ hb1 ^ a;
+            let mut channel = SwankyChannel::new(&mut self.channel);
+            channel.write_block(&table_g)?;
+            channel.write_block(&table_e)?;
+
+            let mut label = ha0 ^ hb0;
+            if a.lsb() {
+                label ^= table_g;
+            }
+            if b.lsb() {
+                label ^= table_e ^ a;
+            }
+            Ok(label)
+        } else {
+            let mut channel = SwankyChannel::new(&mut self.channel);
+            let table_g = channel.read_block()?;
+            let table_e = channel.read_block()?;
+
+            let mut label = hash(a, tweak_g) ^ hash(b, tweak_e);
+            if a.lsb() {
+                label ^= table_g;
+            }
+            if b.lsb() {
+                label ^= table_e ^ a;
+            }
+            Ok(label)
+        }
+    }
+
+    /// Sends any buffered garbled tables. The garbler never waits on the evaluator while garbling, so this must
+    /// happen before it might wait on anything else. Reports the context's first failure, if any.
+    fn flush(&mut self) -> Result<()> {
+        self.guard(|context| Ok(context.channel.flush()?));
+        match &self.error {
+            Some(e) => Err(e.clone()),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for Context {
+    fn drop(&mut self) {
+        let _ = self.channel.flush();
+    }
+}
+
+fn hash(label: Block, tweak: Block) -> Block {
+    AES_HASH.tccr_hash(tweak, label)
+}
+
+impl util::Gates for Context {
+    type Bool = Bool;
+
+    fn constant(&mut self, value: bool) -> Bool {
+        Bool::constant(self, value)
+    }
+
+    fn xor(&mut self, a: &Bool, b: &Bool) -> Bool {
+        Bool::xor(self, a, b)
+    }
+
+    fn and(&mut self, a: &Bool, b: &Bool) -> Bool {
+        Bool::and(self, a, b)
+    }
+
+    fn not(&mut self, a: &Bool) -> Bool {
+        Bool::not(self, a)
+    }
+
+    fn mux(&mut self, g: &Bool, a: &Bool, b: &Bool) -> Bool {
+        Bool::mux(self, g, a, b)
+    }
+}
+
+/// A garbled wire: the zero label for the garbler, and the active label for the evaluator.
+#[derive(Debug, Clone)]
+pub struct Bool {
+    label: Block,
+}
+
+impl Bool {
+    /// Inputs `value` on behalf of `party`. The other party's `value` is ignored.
+    pub fn new(context: &mut Context, value: bool, party: i8) -> Self {
+        let label = context.input(&[value], party)[0];
+        Self { label }
+    }
+
+    pub fn constant(context: &mut Context, value: bool) -> Self {
+        // Both labels of a public wire are known to the garbler, and the evaluator's is the zero block.
+        Self {
+            label: context.select(value),
+        }
+    }
+
+    pub fn xor(_context: &mut Context, a: &Self, b: &Self) -> Self {
+        Self {
+            label: a.label ^ b.label,
+        }
+    }
+
+    pub fn and(context: &mut Context, a: &Self, b: &Self) -> Self {
+        Self {
+            label: context.and(a.label, b.label),
+        }
+    }
+
+    pub fn not(context: &mut Context, a: &Self) -> Self {
+        Self {
+            label: a.label ^ context.delta,
+        }
+    }
+
+    pub fn mux(context: &mut Context, g: &Self, a: &Self, b: &Self) -> Self {
+        let axb = Self::xor(context, a, b);
+        let t = Self::and(context, g, &axb);
+        Self::xor(context, b, &t)
+    }
+
+    /// Returns this party's XOR share of the value, i.e. the permute bit of its label.
+    pub fn get(context: &mut Context, share: &Self) -> Result<bool> {
+        context.flush()?;
+        Ok(share.label.lsb())
+    }
+}
+
+/// A two's complement bit vector of garbled wires.
+#[derive(Debug, Clone)]
+pub struct Integer {
+    repr: Vec<Bool>,
+}
+
+impl Integer {
+    pub fn precision(&self) -> usize {
+        self.repr.len()
+    }
+
+    /// Inputs `value` (least significant bit first) on behalf of `party`. The other party's `value` is only
+    /// used for its length.
+    pub fn new(context: &mut Context, value: Vec<bool>, party: i8) -> Self {
+        let repr = context
+            .input(&value, party)
+            .into_iter()
+            .map(|label| Bool { label })
+            .collect();
+        Self { repr }
+    }
+
+    pub fn constant(context: &mut Context, value: Vec<bool>) -> Self {
+        let repr = value
+            .into_iter()
+            .map(|b| Bool::constant(context, b))
+            .collect();
+        Self { repr }
+    }
+
+    pub fn xor(context: &mut Context, a: &Self, b: &Self) -> Self {
+        debug_assert_eq!(a.precision(), b.precision());
+        let repr = a
+            .repr
+            .iter()
+            .zip(b.repr.iter())
+            .map(|(a, b)| Bool::xor(context, a, b))
+            .collect();
+        Self { repr }
+    }
+
+    pub fn add(context: &mut Context, a: &Self, b: &Self) -> Self {
+        debug_assert_eq!(a.precision(), b.precision());
+        let mut repr = vec![Bool::constant(context, false); a.precision()];
+        unsafe {
+            util::full_add(
+                context,
+                repr.as_mut_ptr(),
+                a.repr.as_ptr(),
+                b.repr.as_ptr(),
+                repr.len(),
+            )
+        };
+        Self { repr }
+    }
+
+    pub fn sub(context: &mut Context, a: &Self, b: &Self) -> Self {
+        debug_assert_eq!(a.precision(), b.precision());
+        let mut repr = vec![Bool::constant(context, false); a.precision()];
+        unsafe {
+            util::full_sub(
+                context,
+                repr.as_mut_ptr(),
+                std::ptr::null_mut(),
+                a.repr.as_ptr(),
+                b.repr.as_ptr(),
+                repr.len(),
+            )
+        };
+        Self { repr }
+    }
+
+    pub fn mul(context: &mut Context, a: &Self, b: &Self) -> Self {
+        debug_assert_eq!(a.precision(), b.precision());
+        let mut repr = vec![Bool::constant(context, false); a.precision()];
+        util::full_mul(context, &mut repr, &a.repr, &b.repr);
+        Self { repr }
+    }
+
+    fn abs(context: &mut Context, a: &Self) -> Self {
+        let len = a.precision();
+        let sign = Self {
+            repr: vec![a.repr[len - 1].clone(); len],
+        };
+        let sum = Self::add(context, a, &sign);
+        Self::xor(context, &sum, &sign)
+    }
+
+    /// Signed division, rounding towards zero.
+    pub fn div(context: &mut Context, a: &Self, b: &Self) -> Self {
+        debug_assert_eq!(a.precision(), b.precision());
+        let len = a.precision();
+        let a_abs = Self::abs(context, a);
+        let b_abs = Self::abs(context, b);
+        let sign = Bool::xor(context, &a.repr[len - 1], &b.repr[len - 1]);
+        let (mut repr, _) = util::full_div(context, &a_abs.repr, &b_abs.repr);
+        unsafe {
+            util::cond_neg(context, &sign, repr.as_mut_ptr(), repr.as_ptr(), len);
+        }
+        Self { repr }
+    }
+
+    /// Signed remainder, taking the sign of `a`.
+    pub fn modulo(context: &mut Context, a: &Self, b: &Self) -> Self {
+        debug_assert_eq!(a.precision(), b.precision());
+        let len = a.precision();
+        let a_abs = Self::abs(context, a);
+        let b_abs = Self::abs(context, b);
+        let sign = a.repr[len - 1].clone();
+        let (_, mut repr) = util::full_div(context, &a_abs.repr, &b_abs.repr);
+        unsafe {
+            util::cond_neg(context, &sign, repr.as_mut_ptr(), repr.as_ptr(), len);
+        }
+        Self { repr }
+    }
+
+    pub fn mux(context: &mut Context, g: &Bool, a: &Self, b: &Self) -> Self {
+        debug_assert_eq!(a.precision(), b.precision());
+        let repr = a
+            .repr
+            .iter()
+            .zip(b.repr.iter())
+            .map(|(a, b)| Bool::mux(context, g, a, b))
+            .collect();
+        Self { repr }
+    }
+
+    pub fn eq(context: &mut Context, a: &Self, b: &Self) -> Bool {
+        debug_assert_eq!(a.precision(), b.precision());
+        let mut acc = Bool::constant(context, true);
+        for (a, b) in a.repr.iter().zip(b.repr.iter()) {
+            let axb = Bool::xor(context, a, b);
+            let eq = Bool::not(context, &axb);
+            acc = Bool::and(context, &acc, &eq);
+        }
+        acc
+    }
+
+    /// Signed `a >= b`.
+    pub fn geq(context: &mut Context, a: &Self, b: &Self) -> Bool {
+        debug_assert_eq!(a.precision(), b.precision());
+        let len = a.precision();
+        let mut a_ext = a.clone();
+        a_ext.repr.push(a.repr[len - 1].clone());
+        let mut b_ext = b.clone();
+        b_ext.repr.push(b.repr[len - 1].clone());
+
+        let difference = Self::sub(context, &a_ext, &b_ext);
+        Bool::not(context, &difference.repr[len])
+    }
+
+    /// Returns this party's XOR share of each bit, least significant first.
+    pub fn get(context: &mut Context, share: &Self) -> Result<Vec<bool>> {
+        context.flush()?;
+        Ok(share.repr.iter().map(|b| b.label.lsb()).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::harness;
+
+    /// Runs `program` as the garbler and the evaluator over a local channel, returning the XOR of their outputs.
+    fn garble<F>(program: F) -> Vec<bool>
+    where
+        F: Fn(&mut Context) -> Vec<bool> + Sync,
+    {
+        let shares = harness::run(2, |my_id, mut channels| {
+            let channel = channels[1 - my_id].take().unwrap();
+            let party = if my_id == 0 { ALICE } else { BOB };
+            let mut context = Context::new(party, NetIo::from_channel(channel));
+            program(&mut context)
+        });
+        shares[0]
+            .iter()
+            .zip(shares[1].iter())
+            .map(|(a, b)| a ^ b)
+            .collect()
+    }
+
+    fn bits(value: i8) -> Vec<bool> {
+        (0..8).map(|i| (value >> i) & 1 == 1).collect()
+    }
+
+    fn value(bits: &[bool]) -> i8 {
+        bits.iter()
+            .enumerate()
+            .fold(0u8, |acc, (i, &b)| acc | ((b as u8) << i)) as i8
+    }
+
+    #[test]
+    fn bool_gates() {
+        for a in [false, true] {
+            for b in [false, true] {
+                let out = garble(|ctx| {
+                    let x = Bool::new(ctx, a, ALICE);
+                    let y = Bool::new(ctx, b, BOB);
+                    let t = Bool::constant(ctx, true);
+                    let gates = [
+                        Bool::xor(ctx, &x, &y),
+                        Bool::and(ctx, &x, &y),
+                        Bool::not(ctx, &x),
+                        Bool::and(ctx, &t, &y),
+                        Bool::mux(ctx, &x, &y, &t),
+                    ];
+                    gates.iter().map(|g| Bool::get(ctx, g).unwrap()).collect()
+                });
+                assert_eq!(out, vec![a ^ b, a & b, !a, b, if a { b } else { true }]);
+            }
+        }
+    }
+
+    #[test]
+    fn integer_arithmetic() {
+        let samples = [0i8, 1, -1, 7, -7, 13, 100, -128, 127];
+        for &a in &samples {
+            for &b in &samples {
+                let out = garble(|ctx| {
+                    let x = Integer::new(ctx, bits(a), ALICE);
+                    let y = Integer::new(ctx, bits(b), BOB);
+                    let mut out = Vec::new();
+                    for r in [
+                        Integer::add(ctx, &x, &y),
+                        Integer::sub(ctx, &x, &y),
+                        Integer::mul(ctx, &x, &y),
+                    ] {
+                        out.extend(Integer::get(ctx, &r).unwrap());
+                    }
+                    if b != 0 && !(a == i8::MIN && b == -1) {
+                        for r in [Integer::div(ctx, &x, &y), Integer::modulo(ctx, &x, &y)] {
+                            out.extend(Integer::get(ctx, &r).unwrap());
+                        }
+                    }
+                    let eq = Integer::eq(ctx, &x, &y);
+                    let geq = Integer::geq(ctx, &x, &y);
+                    out.push(Bool::get(ctx, &eq).unwrap());
+                    out.push(Bool::get(ctx, &geq).unwrap());
+                    out
+                });
+
+                let mut expected = vec![a.wrapping_add(b), a.wrapping_sub(b), a.wrapping_mul(b)];
+                if b != 0 && !(a == i8::MIN && b == -1) {
+                    expected.push(a / b);
+                    expected.push(a % b);
+                }
+                let values: Vec<i8> = out[..out.len() - 2].chunks(8).map(value).collect();
+                assert_eq!(values, expected, "{} {}", a, b);
+                assert_eq!(out[out.len() - 2..], [a == b, a >= b]);
+            }
+        }
+    }
+
+    #[test]
+    fn disconnect_poisons_context() {
+        let channel = harness::mesh(2)[1][0].take().unwrap();
+        let mut context = Context::new(BOB, NetIo::from_channel(channel));
+        let x = Bool::new(&mut context, false, ALICE);
+        let y = Bool::and(&mut context, &x, &x);
+        assert!(matches!(
+            Bool::get(&mut context, &y),
+            Err(Error::Disconnected)
+        ));
+        assert!(matches!(
+            Bool::get(&mut context, &x),
+            Err(Error::Disconnected)
+        ));
+    }
+}
@@ -0,0 +1,225 @@
+use crate::error::Result;
+use crate::util;
+use crate::yao::engine;
+use crate::yao::Bool;
+use crate::yao::Protocol;
+
+/// A two's complement integer garbled-circuit value.
+#[derive(Debug, Clone)]
+pub struct Int {
+    repr: engine::Integer,
+}
+
+impl Int {
+    /// Inputs this party's XOR share of a value. Both parties must call `new` at the same point.
+    pub fn new(protocol: &mut Protocol, share: &[u8]) -> Self {
+        let context = &mut protocol.context;
+        let bits = util::to_bits(share);
+        let alice = engine::Integer::new(context, bits.clone(), engine::ALICE);
+        let bob = engine::Integer::new(context, bits, engine::BOB);
+        let repr = engine::Integer::xor(context, &alice, &bob);
+        Self { repr }
+    }
+
+    pub fn constant(protocol: &mut Protocol, value: &[u8]) -> Self {
+        let repr = engine::Integer::constant(&mut protocol.context, util::to_bits(value));
+        Self { repr }
+    }
+
+    pub fn xor(protocol: &mut Protocol, a: &Self, b: &Self) -> Self {
+        let repr = engine::Integer::xor(&mut protocol.context, &a.repr, &b.repr);
+        Self { repr }
+    }
+
+    pub fn add(protocol: &mut Protocol, a: &Self, b: &Self) -> Self {
+        let repr = engine::Integer::add(&mut protocol.context, &a.repr, &b.repr);
+        Self { repr }
+    }
+
+    pub fn sub(protocol: &mut Protocol, a: &Self, b: &Self) -> Self {
+        let repr = engine::Integer::sub(&mut protocol.context, &a.repr, &b.repr);
+        Self { repr }
+    }
+
+    pub fn mul(protocol: &mut Protocol, a: &Self, b: &Self) -> Self {
+        let repr = engine::Integer::mul(&mut protocol.context, &a.repr, &b.repr);
+        Self { repr }
+    }
+
+    pub fn div(protocol: &mut Protocol, a: &Self, b: &Self) -> Self {
+        let repr = engine::Integer::div(&mut protocol.context, &a.repr, &b.repr);
+        Self { repr }
+    }
+
+    pub fn modulo(protocol: &mut Protocol, a: &Self, b: &Self) -> Self {
+        let repr = engine::Integer::modulo(&mut protocol.context, &a.repr, &b.repr);
+        Self { repr }
+    }
+
+    pub fn mux(protocol: &mut Protocol, guard: &Bool, t: &Self, f: &Self) -> Self {
+        let repr = engine::Integer::mux(&mut protocol.context, &guard.repr, &t.repr, &f.repr);
+        Self { repr }
+    }
+
+    pub fn eq(protocol: &mut Protocol, a: &Self, b: &Self) -> Bool {
+        let repr = engine::Integer::eq(&mut protocol.context, &a.repr, &b.repr);
+        Bool { repr }
+    }
+
+    pub fn gte(protocol: &mut Protocol, a: &Self, b: &Self) -> Bool {
+        let repr = engine::Integer::geq(&mut protocol.context, &a.repr, &b.repr);
+        Bool { repr }
+    }
+
+    pub fn lt(protocol: &mut Protocol, a: &Self, b: &Self) -> Bool {
+        let tmp = Int::gte(protocol, a, b);
+        Bool::not(protocol, &tmp)
+    }
+
+    pub fn lte(protocol: &mut Protocol, a: &Self, b: &Self) -> Bool {
+        Int::gte(protocol, b, a)
+    }
+
+    pub fn gt(protocol: &mut Protocol, a: &Self, b: &Self) -> Bool {
+        let tmp = Int::lte(protocol, a, b);
+        Bool::not(protocol, &tmp)
+    }
+
+    /// Returns this party's XOR share of the value.
+    pub fn get(protocol: &mut Protocol, share: &Self) -> Result<Vec<u8>> {
+        Ok(util::from_bits(&Self::get_bits(protocol, share)?))
+    }
+
+    /// Returns this party's XOR share of each bit of the value, least significant first.
+    pub fn get_bits(protocol: &mut Protocol, share: &Self) -> Result<Vec<bool>> {
+        engine::Integer::get(&mut protocol.context, &share.repr)
+    }
+}
+
+pub mod ffi {
+    use super::*;
+    use crate::error::{self, Status};
+    use crate::gmw::check_width;
+
+    #[no_mangle]
+    pub unsafe extern "C" fn yao_int32_new(protocol: *mut Protocol, share: i32) -> *mut Int {
+        let ret = Int::new(&mut *protocol, &share.to_le_bytes());
+        Box::into_raw(Box::new(ret))
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn yao_int32_constant(protocol: *mut Protocol, value: i32) -> *mut Int {
+        let ret = Int::constant(&mut *protocol, &value.to_le_bytes());
+        Box::into_raw(Box::new(ret))
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn yao_int_add(
+        protocol: *mut Protocol,
+        a: *mut Int,
+        b: *mut Int,
+    ) -> *mut Int {
+        let ret = Int::add(&mut *protocol, &*a, &*b);
+        Box::into_raw(Box::new(ret))
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn yao_int_sub(
+        protocol: *mut Protocol,
+        a: *mut Int,
+        b: *mut Int,
+    ) -> *mut Int {
+        let ret = Int::sub(&mut *protocol, &*a, &*b);
+        Box::into_raw(Box::new(ret))
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn yao_int_mul(
+        protocol: *mut Protocol,
+        a: *mut Int,
+        b: *mut Int,
+    ) -> *mut Int {
+        let ret = Int::mul(&mut *protocol, &*a, &*b);
+        Box::into_raw(Box::new(ret))
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn yao_int_div(
+        protocol: *mut Protocol,
+        a: *mut Int,
+        b: *mut Int,
+    ) -> *mut Int {
+        let ret = Int::div(&mut *protocol, &*a, &*b);
+        Box::into_raw(Box::new(ret))
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn yao_int_mod(
+        protocol: *mut Protocol,
+        a: *mut Int,
+        b: *mut Int,
+    ) -> *mut Int {
+        let ret = Int::modulo(&mut *protocol, &*a, &*b);
+        Box::into_raw(Box::new(ret))
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn yao_int_mux(
+        protocol: *mut Protocol,
+        guard: *mut Bool,
+        t: *mut Int,
+        f: *mut Int,
+    ) -> *mut Int {
+        let ret = Int::mux(&mut *protocol, &*guard, &*t, &*f);
+        Box::into_raw(Box::new(ret))
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn yao_int_eq(
+        protocol: *mut Protocol,
+        a: *mut Int,
+        b: *mut Int,
+    ) -> *mut Bool {
+        let ret = Int::eq(&mut *protocol, &*a, &*b);
+        Box::into_raw(Box::new(ret))
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn yao_int_lt(
+        protocol: *mut Protocol,
+        a: *mut Int,
+        b: *mut Int,
+    ) -> *mut Bool {
+        let ret = Int::lt(&mut *protocol, &*a, &*b);
+        Box::into_raw(Box::new(ret))
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn yao_int_lte(
+        protocol: *mut Protocol,
+        a: *mut Int,
+        b: *mut Int,
+    ) -> *mut Bool {
+        let ret = Int::lte(&mut *protocol, &*a, &*b);
+        Box::into_raw(Box::new(ret))
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn yao_int32_get(
+        protocol: *mut Protocol,
+        share: *mut Int,
+        out: *mut i32,
+    ) -> Status {
+        let ret = Int::get_bits(&mut *protocol, &*share).and_then(|bits| {
+            check_width(&bits, i32::BITS as usize)?;
+            let bytes = util::from_bits(&bits);
+            Ok(i32::from_le_bytes(bytes.try_into().unwrap()))
+        });
+        error::status_out(ret, out)
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn yao_int_drop(share: *mut Int) {
+        Box::from_raw(share);
+    }
+}
@@ -0,0 +1,191 @@
+use crate::error::Result;
+use crate::util;
+use crate::yao::engine;
+use crate::yao::Bool;
+use crate::yao::Protocol;
+
+/// A natural number garbled-circuit value.
+#[derive(Debug, Clone)]
+pub struct Nat {
+    repr: engine::Integer,
+}
+
+impl Nat {
+    /// Inputs this party's XOR share of a value. Both parties must call `new` at the same point.
+    pub fn new(protocol: &mut Protocol, share: &[u8]) -> Self {
+        let context = &mut protocol.context;
+        let bits = util::to_bits(share);
+        let alice = engine::Integer::new(context, bits.clone(), engine::ALICE);
+        let bob = engine::Integer::new(context, bits, engine::BOB);
+        let repr = engine::Integer::xor(context, &alice, &bob);
+        Self { repr }
+    }
+
+    pub fn constant(protocol: &mut Protocol, value: &[u8]) -> Self {
+        let repr = engine::Integer::constant(&mut protocol.context, util::to_bits(value));
+        Self { repr }
+    }
+
+    pub fn add(protocol: &mut Protocol, a: &Self, b: &Self) -> Self {
+        let repr = engine::Integer::add(&mut protocol.context, &a.repr, &b.repr);
+        Self { repr }
+    }
+
+    pub fn sub(protocol: &mut Protocol, a: &Self, b: &Self) -> Self {
+        let repr = engine::Integer::sub(&mut protocol.context, &a.repr, &b.repr);
+        Self { repr }
+    }
+
+    pub fn mul(protocol: &mut Protocol, a: &Self, b: &Self) -> Self {
+        let repr = engine::Integer::mul(&mut protocol.context, &a.repr, &b.repr);
+        Self { repr }
+    }
+
+    pub fn mux(protocol: &mut Protocol, g: &Bool, a: &Self, b: &Self) -> Self {
+        let repr = engine::Integer::mux(&mut protocol.context, &g.repr, &a.repr, &b.repr);
+        Self { repr }
+    }
+
+    pub fn eq(protocol: &mut Protocol, a: &Self, b: &Self) -> Bool {
+        let repr = engine::Integer::eq(&mut protocol.context, &a.repr, &b.repr);
+        Bool { repr }
+    }
+
+    /// Flips the most significant bit, so that signed comparison of the results orders the originals as
+    /// unsigned values.
+    fn bias(protocol: &mut Protocol, a: &Self) -> engine::Integer {
+        let precision = a.repr.precision();
+        let mut msb = vec![false; precision];
+        msb[precision - 1] = true;
+        let msb = engine::Integer::constant(&mut protocol.context, msb);
+        engine::Integer::xor(&mut protocol.context, &a.repr, &msb)
+    }
+
+    pub fn gte(protocol: &mut Protocol, a: &Self, b: &Self) -> Bool {
+        let a_biased = Self::bias(protocol, a);
+        let b_biased = Self::bias(protocol, b);
+        let repr = engine::Integer::geq(&mut protocol.context, &a_biased, &b_biased);
+        Bool { repr }
+    }
+
+    pub fn lte(protocol: &mut Protocol, a: &Self, b: &Self) -> Bool {
+        Self::gte(protocol, b, a)
+    }
+
+    pub fn gt(protocol: &mut Protocol, a: &Self, b: &Self) -> Bool {
+        let altb = Self::lte(protocol, a, b);
+        Bool::not(protocol, &altb)
+    }
+
+    pub fn lt(protocol: &mut Protocol, a: &Self, b: &Self) -> Bool {
+        Self::gt(protocol, b, a)
+    }
+
+    /// Returns this party's XOR share of the value.
+    pub fn get(protocol: &mut Protocol, share: &Self) -> Result<Vec<u8>> {
+        Ok(util::from_bits(&Self::get_bits(protocol, share)?))
+    }
+
+    /// Returns this party's XOR share of each bit of the value, least significant first.
+    pub fn get_bits(protocol: &mut Protocol, share: &Self) -> Result<Vec<bool>> {
+        engine::Integer::get(&mut protocol.context, &share.repr)
+    }
+}
+
+pub mod ffi {
+    use super::*;
+    use crate::error::{self, Status};
+    use crate::gmw::check_width;
+
+    #[no_mangle]
+    pub unsafe extern "C" fn yao_nat32_new(protocol: *mut Protocol, share: u32) -> *mut Nat {
+        let ret = Nat::new(&mut *protocol, &share.to_le_bytes());
+        Box::into_raw(Box::new(ret))
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn yao_nat32_constant(protocol: *mut Protocol, value: u32) -> *mut Nat {
+        let ret = Nat::constant(&mut *protocol, &value.to_le_bytes());
+        Box::into_raw(Box::new(ret))
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn yao_nat_add(
+        protocol: *mut Protocol,
+        a: *mut Nat,
+        b: *mut Nat,
+    ) -> *mut Nat {
+        let ret = Nat::add(&mut *protocol, &*a, &*b);
+        Box::into_raw(Box::new(ret))
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn yao_nat_sub(
+        protocol: *mut Protocol,
+        a: *mut Nat,
+        b: *mut Nat,
+    ) -> *mut Nat {
+        let ret = Nat::sub(&mut *protocol, &*a, &*b);
+        Box::into_raw(Box::new(ret))
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn yao_nat_mul(
+        protocol: *mut Protocol,
+        a: *mut Nat,
+        b: *mut Nat,
+    ) -> *mut Nat {
+        let ret = Nat::mul(&mut *protocol, &*a, &*b);
+        Box::into_raw(Box::new(ret))
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn yao_nat_mux(
+        protocol: *mut Protocol,
+        guard: *mut Bool,
+        t: *mut Nat,
+        f: *mut Nat,
+    ) -> *mut Nat {
+        let ret = Nat::mux(&mut *protocol, &*guard, &*t, &*f);
+        Box::into_raw(Box::new(ret))
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn yao_nat_eq(
+        protocol: *mut Protocol,
+        a: *mut Nat,
+        b: *mut Nat,
+    ) -> *mut Bool {
+        let ret = Nat::eq(&mut *protocol, &*a, &*b);
+        Box::into_raw(Box::new(ret))
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn yao_nat_lte(
+        protocol: *mut Protocol,
+        a: *mut Nat,
+        b: *mut Nat,
+    ) -> *mut Bool {
+        let ret = Nat::lte(&mut *protocol, &*a, &*b);
+        Box::into_raw(Box::new(ret))
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn yao_nat32_get(
+        protocol: *mut Protocol,
+        share: *mut Nat,
+        out: *mut u32,
+    ) -> Status {
+        let ret = Nat::get_bits(&mut *protocol, &*share).and_then(|bits| {
+            check_width(&bits, u32::BITS as usize)?;
+            let bytes = util::from_bits(&bits);
+            Ok(u32::from_le_bytes(bytes.try_into().unwrap()))
+        });
+        error::status_out(ret, out)
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn yao_nat_drop(share: *mut Nat) {
+        Box::from_raw(share);
+    }
+}